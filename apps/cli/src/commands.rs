@@ -0,0 +1,630 @@
+use std::time::Duration;
+
+use clap::ArgMatches;
+use monitor_core::{
+    apply_profile_with, cycle_favorites, cycle_profiles, resolve_input, socket_path, switch_input,
+    toggle_input, ApplyOptions, Config, CycleDirection, Daemon, DaemonClient, DaemonError,
+    DaemonOptions, DdcHiBackend, Diagnostics, InputHistory, InputSource, Monitor, MonitorError,
+    MonitorErrorCode, MonitorSelector, MonitorSnapshot, PowerMode, ProfileReport, SelectorError,
+    SwitchOptions, TargetOutcome,
+};
+use serde_json::{json, Value};
+
+use crate::{monitor_exit_code, CliError};
+
+/// What a command prints, as text or with `--json`.
+pub struct Output {
+    pub text: String,
+    pub json: Value,
+    /// Non-zero when the command ran but part of it failed.
+    pub exit_code: u8,
+}
+
+impl Output {
+    fn new(text: impl Into<String>, json: Value) -> Self {
+        Output {
+            text: text.into(),
+            json,
+            exit_code: 0,
+        }
+    }
+}
+
+pub fn run(matches: &ArgMatches) -> Result<Output, CliError> {
+    match matches.subcommand() {
+        Some(("list", args)) => list(args),
+        Some(("get", args)) => get(args),
+        Some(("set", args)) => set(args),
+        Some(("toggle", args)) => toggle(args),
+        Some(("cycle", args)) => cycle(args),
+        Some(("alias", args)) => alias(args),
+        Some(("name", args)) => name(args),
+        Some(("favorite", args)) => match args.subcommand() {
+            Some(("list", _)) => favorite_list(),
+            Some(("add", args)) => favorite_change(args, true),
+            Some(("remove", args)) => favorite_change(args, false),
+            _ => unreachable!("favorite requires a subcommand"),
+        },
+        Some(("profile", args)) => match args.subcommand() {
+            Some(("list", _)) => profile_list(),
+            Some(("apply", args)) => profile_apply(args),
+            Some(("cycle", args)) => profile_cycle(args),
+            _ => unreachable!("profile requires a subcommand"),
+        },
+        Some(("daemon", _)) => serve(),
+        _ => unreachable!("a subcommand is required"),
+    }
+}
+
+fn list(args: &ArgMatches) -> Result<Output, CliError> {
+    let mut config = Config::load()?;
+    let monitors = match connect(args) {
+        Some(mut daemon) => daemon.list()?,
+        None => enumerate(&mut config)
+            .iter_mut()
+            .enumerate()
+            .map(|(index, monitor)| MonitorSnapshot {
+                current_input: monitor.get_current_input().ok(),
+                inputs: monitor.get_available_inputs().unwrap_or_default(),
+                ..MonitorSnapshot::new(monitor, index)
+            })
+            .collect(),
+    };
+
+    if monitors.is_empty() {
+        let probe = args.get_flag("probe");
+        let diagnostics = if probe {
+            Diagnostics::new().probe_buses()
+        } else {
+            Diagnostics::new()
+        };
+        let mut text = match diagnostics.run().problem() {
+            Some(problem) => match problem.hint() {
+                Some(hint) => format!("No monitors found: {}. {}", problem, hint),
+                None => format!("No monitors found: {}", problem),
+            },
+            None => "No monitors found".to_string(),
+        };
+        if !probe {
+            text.push_str("\nAdd --probe to ask each display bus whether a monitor answers.");
+        }
+        return Ok(Output::new(text, json!([])));
+    }
+
+    let mut text = Vec::new();
+    let mut entries = Vec::new();
+    for monitor in &monitors {
+        let id = &monitor.id;
+        let name = display_name(&config, monitor);
+        let current = monitor.current_input;
+
+        text.push(format!("{}: {} [{}]", monitor.index + 1, name, id));
+        for &input in &monitor.inputs {
+            let marker = if current == Some(input) { "*" } else { " " };
+            let favorite = if config.is_favorite(id, input.to_vcp_value()) {
+                " (favorite)"
+            } else {
+                ""
+            };
+            text.push(format!(
+                "  {} {}{}",
+                marker,
+                input_text(&config, id, input),
+                favorite
+            ));
+        }
+
+        let inputs_json: Vec<Value> = monitor
+            .inputs
+            .iter()
+            .map(|&input| {
+                let mut entry = input_json(&config, id, input);
+                entry["favorite"] = json!(config.is_favorite(id, input.to_vcp_value()));
+                entry
+            })
+            .collect();
+        entries.push(json!({
+            "index": monitor.index + 1,
+            "id": id,
+            "name": name,
+            "current_input": current.map(|input| input_json(&config, id, input)),
+            "inputs": inputs_json,
+        }));
+    }
+
+    Ok(Output::new(text.join("\n"), Value::Array(entries)))
+}
+
+fn get(args: &ArgMatches) -> Result<Output, CliError> {
+    let mut config = Config::load()?;
+    let setting = arg(args, "setting");
+    let (id, reading) = match connect(args) {
+        Some(mut daemon) => {
+            let monitor = daemon.get(arg(args, "monitor"))?;
+            let reading = match setting {
+                "input" => monitor.current_input.map(Reading::Input),
+                "power" => monitor.power_mode.map(Reading::Power),
+                "brightness" => monitor.brightness.map(Reading::Percent),
+                "contrast" => monitor.contrast.map(Reading::Percent),
+                _ => monitor.volume.map(Reading::Percent),
+            };
+            (monitor.id, reading.ok_or(MonitorError::NotSupported)?)
+        }
+        None => {
+            let mut monitors = enumerate(&mut config);
+            let index = find_monitor(&monitors, &config, arg(args, "monitor"))?;
+            let monitor = &mut monitors[index];
+            let reading = match setting {
+                "input" => Reading::Input(monitor.get_current_input()?),
+                "power" => Reading::Power(monitor.power_mode()?),
+                "brightness" => Reading::Percent(monitor.brightness()?),
+                "contrast" => Reading::Percent(monitor.contrast()?),
+                _ => Reading::Percent(monitor.volume()?),
+            };
+            (monitor.id(), reading)
+        }
+    };
+
+    let (text, value) = match reading {
+        Reading::Input(input) => (
+            input_text(&config, &id, input),
+            input_json(&config, &id, input),
+        ),
+        Reading::Power(mode) => (mode.name().to_string(), json!(mode.name())),
+        Reading::Percent(percent) => (percent.to_string(), json!(percent)),
+    };
+
+    let mut json = json!({ "monitor": id });
+    json[setting] = value;
+    Ok(Output::new(text, json))
+}
+
+/// A setting as read by `get`.
+enum Reading {
+    Input(InputSource),
+    Power(PowerMode),
+    Percent(u8),
+}
+
+fn set(args: &ArgMatches) -> Result<Output, CliError> {
+    let mut config = Config::load()?;
+    if let Some(mut daemon) = connect(args) {
+        let monitor = daemon.set_input(arg(args, "monitor"), arg(args, "input"), wake(args))?;
+        return switched(&config, &monitor);
+    }
+
+    let mut monitors = enumerate(&mut config);
+    let index = find_monitor(&monitors, &config, arg(args, "monitor"))?;
+    let monitor = &mut monitors[index];
+    let input = resolve_input(&config, &monitor.id(), arg(args, "input"))?;
+
+    let options = SwitchOptions {
+        ensure_powered_on: wake(args) || monitor.switch_options().ensure_powered_on,
+        ..monitor.switch_options().clone()
+    };
+    let mut history = InputHistory::load();
+    let result = switch_input(monitor, input, &options, &mut history);
+    save_history(&history);
+    result?;
+    switched_to(&config, monitor, index, input)
+}
+
+fn toggle(args: &ArgMatches) -> Result<Output, CliError> {
+    let mut config = Config::load()?;
+    let inputs: Option<Vec<&String>> = args.get_many::<String>("inputs").map(Iterator::collect);
+    if let Some(mut daemon) = connect(args) {
+        let pair = inputs.map(|inputs| (inputs[0].as_str(), inputs[1].as_str()));
+        let monitor = daemon.toggle_input(arg(args, "monitor"), pair, wake(args))?;
+        return switched(&config, &monitor);
+    }
+
+    let mut monitors = enumerate(&mut config);
+    let index = find_monitor(&monitors, &config, arg(args, "monitor"))?;
+    let monitor = &mut monitors[index];
+    let id = monitor.id();
+    let pair = match inputs {
+        Some(inputs) => {
+            let inputs = inputs
+                .into_iter()
+                .map(|input| resolve_input(&config, &id, input))
+                .collect::<Result<Vec<_>, _>>()?;
+            Some((inputs[0], inputs[1]))
+        }
+        None => None,
+    };
+
+    let mut history = InputHistory::load();
+    let result = toggle_input(monitor, pair, wake(args), &mut history);
+    save_history(&history);
+    let input = result?;
+    switched_to(&config, monitor, index, input)
+}
+
+fn cycle(args: &ArgMatches) -> Result<Output, CliError> {
+    let mut config = Config::load()?;
+    if let Some(mut daemon) = connect(args) {
+        let monitor = daemon.cycle_input(arg(args, "monitor"), direction(args), wake(args))?;
+        return switched(&config, &monitor);
+    }
+
+    let mut monitors = enumerate(&mut config);
+    let index = find_monitor(&monitors, &config, arg(args, "monitor"))?;
+    let monitor = &mut monitors[index];
+
+    let mut history = InputHistory::load();
+    let result = cycle_favorites(monitor, &config, direction(args), wake(args), &mut history);
+    save_history(&history);
+    let input = result?;
+    switched_to(&config, monitor, index, input)
+}
+
+fn switched_to(
+    config: &Config,
+    monitor: &Monitor,
+    index: usize,
+    input: InputSource,
+) -> Result<Output, CliError> {
+    let monitor = MonitorSnapshot {
+        current_input: Some(input),
+        ..MonitorSnapshot::new(monitor, index)
+    };
+    switched(config, &monitor)
+}
+
+/// Reports the input `monitor` was just switched to.
+fn switched(config: &Config, monitor: &MonitorSnapshot) -> Result<Output, CliError> {
+    let id = &monitor.id;
+    let input = monitor
+        .current_input
+        .ok_or_else(|| DaemonError::Protocol("no input after switching".to_string()))?;
+    let text = format!(
+        "Switched {} to {}",
+        display_name(config, monitor),
+        input_text(config, id, input)
+    );
+    Ok(Output::new(
+        text,
+        json!({ "monitor": id, "input": input_json(config, id, input) }),
+    ))
+}
+
+fn alias(args: &ArgMatches) -> Result<Output, CliError> {
+    let mut config = Config::load()?;
+    let id = monitor_id(args, &mut config)?;
+    let input = resolve_input(&config, &id, arg(args, "input"))?;
+    let value = input.to_vcp_value();
+
+    let alias = if args.get_flag("remove") {
+        Config::update(|c| c.remove_alias(&id, value))?;
+        None
+    } else if let Some(name) = args.get_one::<String>("name") {
+        Config::update(|c| c.set_alias(&id, value, name.clone()))?;
+        Some(name.clone())
+    } else {
+        config.get_alias(&id, value).map(String::from)
+    };
+
+    Ok(Output::new(
+        alias.clone().unwrap_or_default(),
+        json!({ "monitor": id, "input": value, "alias": alias }),
+    ))
+}
+
+fn name(args: &ArgMatches) -> Result<Output, CliError> {
+    let mut config = Config::load()?;
+    let id = monitor_id(args, &mut config)?;
+
+    let name = if args.get_flag("remove") {
+        Config::update(|c| c.set_monitor_alias(&id, None))?;
+        None
+    } else if let Some(name) = args.get_one::<String>("name") {
+        Config::update(|c| c.set_monitor_alias(&id, Some(name.clone())))?;
+        Some(name.clone())
+    } else {
+        config.get_monitor_alias(&id).map(String::from)
+    };
+
+    Ok(Output::new(
+        name.clone().unwrap_or_default(),
+        json!({ "monitor": id, "name": name }),
+    ))
+}
+
+fn favorite_list() -> Result<Output, CliError> {
+    let config = Config::load()?;
+    let text: Vec<String> = config
+        .get_favorites()
+        .iter()
+        .map(|f| {
+            let input = InputSource::from_vcp_value(f.input_value);
+            let mut line = format!(
+                "{}: {}",
+                f.monitor_id,
+                input_text(&config, &f.monitor_id, input)
+            );
+            if let Some(other) = f.toggle_with.map(InputSource::from_vcp_value) {
+                line.push_str(&format!(" ⇄ {}", input_text(&config, &f.monitor_id, other)));
+            }
+            line
+        })
+        .collect();
+    let json: Vec<Value> = config
+        .get_favorites()
+        .iter()
+        .map(|f| {
+            let input = InputSource::from_vcp_value(f.input_value);
+            let toggle_with = f.toggle_with.map(|other| {
+                input_json(&config, &f.monitor_id, InputSource::from_vcp_value(other))
+            });
+            json!({
+                "monitor": f.monitor_id,
+                "input": input_json(&config, &f.monitor_id, input),
+                "toggle_with": toggle_with,
+            })
+        })
+        .collect();
+    Ok(Output::new(text.join("\n"), Value::Array(json)))
+}
+
+fn favorite_change(args: &ArgMatches, add: bool) -> Result<Output, CliError> {
+    let mut config = Config::load()?;
+    let id = monitor_id(args, &mut config)?;
+    let input = resolve_input(&config, &id, arg(args, "input"))?;
+    let toggle_with = match args.get_one::<String>("toggle-with") {
+        Some(other) => Some(resolve_input(&config, &id, other)?),
+        None => None,
+    };
+    let value = input.to_vcp_value();
+    let other = toggle_with.map(InputSource::to_vcp_value);
+
+    Config::update(|c| {
+        if add {
+            c.add_toggle_favorite(&id, value, other);
+        } else {
+            c.remove_toggle_favorite(&id, value, other);
+        }
+    })?;
+    Ok(Output::new(
+        String::new(),
+        json!({
+            "monitor": id,
+            "input": input_json(&config, &id, input),
+            "toggle_with": toggle_with.map(|other| input_json(&config, &id, other)),
+            "favorite": add,
+        }),
+    ))
+}
+
+fn profile_list() -> Result<Output, CliError> {
+    let config = Config::load()?;
+    let mut text = Vec::new();
+    for profile in &config.profiles {
+        text.push(profile.name.clone());
+        for target in &profile.monitors {
+            let input = InputSource::from_vcp_value(target.input_value);
+            let brightness = target
+                .brightness
+                .map(|b| format!(", brightness {}%", b))
+                .unwrap_or_default();
+            text.push(format!(
+                "  {}: {}{}",
+                target.monitor_id,
+                input_text(&config, &target.monitor_id, input),
+                brightness
+            ));
+        }
+    }
+    let json = serde_json::to_value(&config.profiles).expect("profiles serialise to JSON");
+    Ok(Output::new(text.join("\n"), json))
+}
+
+fn profile_apply(args: &ArgMatches) -> Result<Output, CliError> {
+    let mut config = Config::load()?;
+    let name = arg(args, "name");
+    if let Some(mut daemon) = connect(args) {
+        let report = daemon.apply_profile(name, &apply_options(args))?;
+        return Ok(profile_output(&config, &report));
+    }
+    if config.get_profile(name).is_none() {
+        return Err(CliError::Usage(format!("No profile named \"{}\"", name)));
+    }
+    let mut monitors = enumerate(&mut config);
+    let profile = config.get_profile(name).expect("checked above");
+    let report = apply_profile_with(&mut monitors, profile, &apply_options(args));
+    Ok(profile_output(&config, &report))
+}
+
+fn profile_cycle(args: &ArgMatches) -> Result<Output, CliError> {
+    let mut config = Config::load()?;
+    if let Some(mut daemon) = connect(args) {
+        let report = daemon.cycle_profile(direction(args), &apply_options(args))?;
+        return Ok(profile_output(&config, &report));
+    }
+    let mut monitors = enumerate(&mut config);
+    let report = cycle_profiles(
+        &mut monitors,
+        &config,
+        direction(args),
+        &apply_options(args),
+    )
+    .ok_or_else(|| CliError::Usage("There are no profiles to cycle through".to_string()))?;
+    Ok(profile_output(&config, &report))
+}
+
+fn apply_options(args: &ArgMatches) -> ApplyOptions {
+    ApplyOptions {
+        delay: Duration::from_millis(*args.get_one::<u64>("delay").unwrap_or(&0)),
+        rollback: !args.get_flag("no-rollback"),
+        wake: wake(args),
+        ..ApplyOptions::default()
+    }
+}
+
+fn profile_output(config: &Config, report: &ProfileReport) -> Output {
+    let mut text = Vec::new();
+    let mut entries = Vec::new();
+    for target in &report.monitors {
+        let input = input_text(config, &target.monitor_id, target.input);
+        let (status, line) = match &target.outcome {
+            TargetOutcome::Switched => ("switched", format!("switched to {}", input)),
+            TargetOutcome::NotFound => ("not_found", "not connected".to_string()),
+            TargetOutcome::Failed(e) => ("failed", format!("could not switch to {}: {}", input, e)),
+            TargetOutcome::Skipped => ("skipped", "skipped".to_string()),
+            TargetOutcome::RolledBack => ("rolled_back", "switched back".to_string()),
+            TargetOutcome::RollbackFailed(e) => {
+                ("rollback_failed", format!("could not switch back: {}", e))
+            }
+        };
+        text.push(format!("{}: {}", target.monitor_id, line));
+        if let Some(Err(e)) = &target.brightness_result {
+            text.push(format!(
+                "{}: could not set brightness: {}",
+                target.monitor_id, e
+            ));
+        }
+
+        entries.push(json!({
+            "monitor": target.monitor_id,
+            "input": input_json(config, &target.monitor_id, target.input),
+            "previous_input": target.previous_input.map(|i| i.to_vcp_value()),
+            "status": status,
+            "error": target.error().map(|e| e.to_string()),
+            "brightness_error": match &target.brightness_result {
+                Some(Err(e)) => Some(e.to_string()),
+                _ => None,
+            },
+        }));
+    }
+
+    let mut output = Output::new(
+        text.join("\n"),
+        json!({
+            "profile": report.profile,
+            "ok": report.is_ok(),
+            "rolled_back": report.rolled_back,
+            "monitors": entries,
+        }),
+    );
+    if let Some(failure) = report.failures().next() {
+        let code = failure
+            .error()
+            .map_or(MonitorErrorCode::NotFound, MonitorError::code);
+        output.exit_code = monitor_exit_code(code);
+    }
+    output
+}
+
+/// Runs the daemon until it is killed.
+fn serve() -> Result<Output, CliError> {
+    let path = socket_path().ok_or(DaemonError::NoSocketPath)?;
+    let daemon = Daemon::bind(&path, DdcHiBackend, DaemonOptions::default())?;
+    eprintln!("Listening on {}", path.display());
+    daemon.serve();
+    Ok(Output::new(String::new(), Value::Null))
+}
+
+/// The running daemon, unless `--no-daemon` was given.
+fn connect(args: &ArgMatches) -> Option<DaemonClient> {
+    if args.get_flag("no-daemon") {
+        None
+    } else {
+        DaemonClient::try_connect()
+    }
+}
+
+/// The history only makes `toggle` smarter, so failing to save it doesn't
+/// fail the command.
+fn save_history(history: &InputHistory) {
+    if let Err(e) = history.save() {
+        eprintln!("Warning: could not save the input history: {}", e);
+    }
+}
+
+fn direction(args: &ArgMatches) -> CycleDirection {
+    if args.get_flag("backward") {
+        CycleDirection::Backward
+    } else {
+        CycleDirection::Forward
+    }
+}
+
+fn wake(args: &ArgMatches) -> bool {
+    !args.get_flag("no-wake")
+}
+
+fn arg<'a>(args: &'a ArgMatches, name: &str) -> &'a str {
+    args.get_one::<String>(name)
+        .map(String::as_str)
+        .unwrap_or_default()
+}
+
+fn display_name(config: &Config, monitor: &MonitorSnapshot) -> String {
+    config
+        .get_monitor_alias(&monitor.id)
+        .map(String::from)
+        .unwrap_or_else(|| monitor.name())
+}
+
+/// Enumerates the monitors directly, first re-keying settings stored under
+/// the ids older versions used.
+fn enumerate(config: &mut Config) -> Vec<Monitor> {
+    let monitors = Monitor::enumerate_with_config(config);
+    if config.migrate_monitor_ids(&monitors) {
+        match Config::update(|c| {
+            c.migrate_monitor_ids(&monitors);
+        }) {
+            Ok(updated) => *config = updated,
+            Err(e) => eprintln!("Warning: could not save the migrated config: {}", e),
+        }
+    }
+    monitors
+}
+
+fn find_monitor(monitors: &[Monitor], config: &Config, selector: &str) -> Result<usize, CliError> {
+    let selector: MonitorSelector = selector.parse()?;
+    Ok(selector.resolve(monitors, config)?)
+}
+
+/// The id to store settings under. An `id:` selector that matches no
+/// connected monitor is taken as is, so monitors that aren't connected right
+/// now can be configured too.
+fn monitor_id(args: &ArgMatches, config: &mut Config) -> Result<String, CliError> {
+    let selector = arg(args, "monitor");
+    let parsed: MonitorSelector = selector.parse()?;
+    let found = match connect(args) {
+        Some(mut daemon) => match daemon.find(selector) {
+            Ok(monitor) => Ok(monitor.id),
+            Err(DaemonError::NoMonitor(_)) => Err(SelectorError::NoMonitor(selector.to_string())),
+            Err(e) => return Err(e.into()),
+        },
+        None => {
+            let monitors = enumerate(config);
+            parsed
+                .resolve(&monitors, config)
+                .map(|index| monitors[index].id())
+        }
+    };
+    match found {
+        Ok(id) => Ok(id),
+        Err(SelectorError::NoMonitor(_)) => match parsed {
+            MonitorSelector::Id(id) => Ok(id),
+            _ => Err(SelectorError::NoMonitor(selector.to_string()).into()),
+        },
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn input_text(config: &Config, monitor_id: &str, input: InputSource) -> String {
+    match config.get_alias(monitor_id, input.to_vcp_value()) {
+        Some(alias) => format!("{} ({})", input.name(), alias),
+        None => input.name().to_string(),
+    }
+}
+
+fn input_json(config: &Config, monitor_id: &str, input: InputSource) -> Value {
+    json!({
+        "value": input.to_vcp_value(),
+        "name": input.name(),
+        "alias": config.get_alias(monitor_id, input.to_vcp_value()),
+    })
+}
@@ -0,0 +1,324 @@
+mod commands;
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{value_parser, Arg, ArgAction, Command};
+use monitor_core::{
+    Config, ConfigError, DaemonError, MonitorError, MonitorErrorCode, SelectorError,
+};
+use serde_json::json;
+use thiserror::Error;
+
+/// Exit codes other than 0. A `MonitorError` exits with
+/// `MONITOR_ERROR_BASE` plus its `MonitorErrorCode`.
+const EXIT_USAGE: u8 = 2;
+const EXIT_CONFIG: u8 = 3;
+const EXIT_DAEMON: u8 = 4;
+const MONITOR_ERROR_BASE: u8 = 10;
+
+const EXIT_CODES: &str = "\
+Exit codes:
+   0  Success
+   2  Invalid arguments, an unknown input or profile, or a selector
+      matching several monitors or inputs
+   3  The config file could not be read or written
+   4  The daemon could not be reached or failed
+  11  DDC/CI communication failed
+  12  Monitor not found
+  13  Not supported by the monitor
+  14  The monitor did not switch input
+  15  Permission denied on the I2C device
+  16  No I2C devices
+  17  The monitor did not reply in time
+  18  The monitor sent a corrupted reply
+  19  The monitor does not respond to DDC/CI
+  20  No earlier input to toggle back to
+  21  No favorite inputs to cycle through
+  22  Invalid argument";
+
+#[derive(Error, Debug)]
+pub enum CliError {
+    #[error(transparent)]
+    Monitor(#[from] MonitorError),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error(transparent)]
+    Selector(#[from] SelectorError),
+    #[error(transparent)]
+    Daemon(#[from] DaemonError),
+    #[error("{0}")]
+    Usage(String),
+}
+
+impl CliError {
+    fn exit_code(&self) -> u8 {
+        match self {
+            CliError::Monitor(e) => monitor_exit_code(e.code()),
+            CliError::Config(_) => EXIT_CONFIG,
+            CliError::Selector(SelectorError::NoMonitor(_)) => {
+                monitor_exit_code(MonitorErrorCode::NotFound)
+            }
+            CliError::Selector(_) => EXIT_USAGE,
+            CliError::Daemon(DaemonError::Monitor(e)) => monitor_exit_code(e.code()),
+            CliError::Daemon(DaemonError::NoMonitor(_)) => {
+                monitor_exit_code(MonitorErrorCode::NotFound)
+            }
+            CliError::Daemon(DaemonError::InvalidParams(_)) => EXIT_USAGE,
+            CliError::Daemon(_) => EXIT_DAEMON,
+            CliError::Usage(_) => EXIT_USAGE,
+        }
+    }
+
+    fn hint(&self) -> Option<&'static str> {
+        match self {
+            CliError::Monitor(e) | CliError::Daemon(DaemonError::Monitor(e)) => e.hint(),
+            _ => None,
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let code = match self {
+            CliError::Monitor(e) | CliError::Daemon(DaemonError::Monitor(e)) => json!(e.code()),
+            CliError::Config(_) => json!("config"),
+            CliError::Selector(SelectorError::NoMonitor(_))
+            | CliError::Daemon(DaemonError::NoMonitor(_)) => json!(MonitorErrorCode::NotFound),
+            CliError::Selector(_)
+            | CliError::Daemon(DaemonError::InvalidParams(_))
+            | CliError::Usage(_) => json!("usage"),
+            CliError::Daemon(_) => json!("daemon"),
+        };
+        let hint = self.hint();
+        json!({ "error": { "code": code, "message": self.to_string(), "hint": hint } })
+    }
+}
+
+fn main() -> ExitCode {
+    env_logger::init();
+
+    let matches = cli().get_matches();
+    let as_json = matches.get_flag("json");
+    if let Some(path) = matches.get_one::<PathBuf>("config") {
+        Config::set_config_path(Some(path.clone()));
+    }
+
+    match commands::run(&matches) {
+        Ok(output) => {
+            if as_json {
+                println!("{:#}", output.json);
+            } else if !output.text.is_empty() {
+                println!("{}", output.text);
+            }
+            ExitCode::from(output.exit_code)
+        }
+        Err(e) => {
+            if as_json {
+                println!("{:#}", e.to_json());
+            } else {
+                match e.hint() {
+                    Some(hint) => eprintln!("Error: {}. {}", e, hint),
+                    None => eprintln!("Error: {}", e),
+                }
+            }
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+fn monitor_exit_code(code: MonitorErrorCode) -> u8 {
+    MONITOR_ERROR_BASE + code as u8
+}
+
+fn cli() -> Command {
+    let monitor = || {
+        Arg::new("monitor").required(true).help(
+            "Monitor number as shown by `list`, id, name, serial or part of the model \
+                 name; prefix with id:, alias:, serial: or model: to match only that",
+        )
+    };
+    let input = || {
+        Arg::new("input")
+            .required(true)
+            .help("Input value (17 or 0x11), name (\"HDMI 1\", hdmi1, dp-1) or alias")
+    };
+
+    let toggle_with = || {
+        Arg::new("toggle-with")
+            .long("toggle-with")
+            .value_name("INPUT")
+            .help("Make it a favorite that toggles between the input and this one")
+    };
+
+    let backward = || {
+        Arg::new("backward")
+            .long("backward")
+            .short('b')
+            .action(ArgAction::SetTrue)
+            .help("Go to the previous one instead")
+    };
+    let no_wake = || {
+        Arg::new("no-wake")
+            .long("no-wake")
+            .action(ArgAction::SetTrue)
+            .help("Don't wake monitors in standby before switching them")
+    };
+    let apply_options = || {
+        [
+            Arg::new("delay")
+                .long("delay")
+                .value_name("MS")
+                .value_parser(value_parser!(u64))
+                .default_value("0")
+                .help("Pause between monitors"),
+            Arg::new("no-rollback")
+                .long("no-rollback")
+                .action(ArgAction::SetTrue)
+                .help("Keep the monitors that switched if another one fails"),
+            no_wake(),
+        ]
+    };
+
+    Command::new("monitor-switch-cli")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Switch monitor inputs via DDC/CI")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .after_help(EXIT_CODES)
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Print JSON instead of text"),
+        )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .global(true)
+                .value_name("FILE")
+                .value_parser(value_parser!(PathBuf))
+                .help("Read and write settings in FILE instead of the default config"),
+        )
+        .arg(
+            Arg::new("no-daemon")
+                .long("no-daemon")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Talk to the monitors directly even if the daemon is running"),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("List monitors and their inputs")
+                .arg(
+                    Arg::new("probe")
+                        .long("probe")
+                        .action(ArgAction::SetTrue)
+                        .help("If no monitor is found, ask each display bus whether one answers"),
+                ),
+        )
+        .subcommand(
+            Command::new("get")
+                .about("Show the current input or another setting of a monitor")
+                .arg(monitor())
+                .arg(
+                    Arg::new("setting")
+                        .value_parser(["input", "brightness", "contrast", "volume", "power"])
+                        .default_value("input"),
+                ),
+        )
+        .subcommand(
+            Command::new("set")
+                .about("Switch a monitor to an input")
+                .arg(monitor())
+                .arg(input())
+                .arg(no_wake()),
+        )
+        .subcommand(
+            Command::new("toggle")
+                .about("Switch a monitor between two inputs, or back to its previous input")
+                .arg(monitor())
+                .arg(
+                    Arg::new("inputs")
+                        .num_args(2)
+                        .value_names(["INPUT", "OTHER"])
+                        .help("The two inputs to switch between"),
+                )
+                .arg(no_wake()),
+        )
+        .subcommand(
+            Command::new("cycle")
+                .about("Switch a monitor to its next favorite input")
+                .arg(monitor())
+                .arg(backward())
+                .arg(no_wake()),
+        )
+        .subcommand(
+            Command::new("alias")
+                .about("Show, set or remove the name of an input")
+                .arg(monitor())
+                .arg(input())
+                .arg(Arg::new("name").help("New name for the input"))
+                .arg(
+                    Arg::new("remove")
+                        .long("remove")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("name")
+                        .help("Remove the name"),
+                ),
+        )
+        .subcommand(
+            Command::new("name")
+                .about("Show, set or remove the name of a monitor")
+                .arg(monitor())
+                .arg(Arg::new("name").help("New name for the monitor"))
+                .arg(
+                    Arg::new("remove")
+                        .long("remove")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("name")
+                        .help("Remove the name"),
+                ),
+        )
+        .subcommand(
+            Command::new("favorite")
+                .about("Manage Quick Switch favorites")
+                .subcommand_required(true)
+                .subcommand(Command::new("list").about("List favorites"))
+                .subcommand(
+                    Command::new("add")
+                        .about("Add an input to the favorites")
+                        .arg(monitor())
+                        .arg(input())
+                        .arg(toggle_with()),
+                )
+                .subcommand(
+                    Command::new("remove")
+                        .about("Remove an input from the favorites")
+                        .arg(monitor())
+                        .arg(input())
+                        .arg(toggle_with()),
+                ),
+        )
+        .subcommand(
+            Command::new("profile")
+                .about("List or apply multi-monitor profiles")
+                .subcommand_required(true)
+                .subcommand(Command::new("list").about("List profiles"))
+                .subcommand(
+                    Command::new("apply")
+                        .about("Switch every monitor in a profile")
+                        .arg(Arg::new("name").required(true))
+                        .args(apply_options()),
+                )
+                .subcommand(
+                    Command::new("cycle")
+                        .about("Apply the profile after the one the monitors are on")
+                        .arg(backward())
+                        .args(apply_options()),
+                ),
+        )
+        .subcommand(Command::new("daemon").about(
+            "Keep the monitors open and serve the other commands, the app and status bars \
+                 over a socket",
+        ))
+}
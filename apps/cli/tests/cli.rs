@@ -0,0 +1,165 @@
+use std::path::Path;
+use std::process::{Command, Output};
+use std::thread;
+
+use monitor_core::{Daemon, DaemonOptions, SimulatedBackend, SimulatedMonitor, SOCKET_PATH_ENV};
+use serde_json::Value;
+use tempfile::TempDir;
+
+/// Runs the CLI with the config at `config` and the daemon socket next to
+/// it, so a daemon running on the machine is never used.
+fn cli(config: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_monitor-switch-cli"))
+        .env(SOCKET_PATH_ENV, config.with_file_name("daemon.sock"))
+        .arg("--config")
+        .arg(config)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn json(output: &Output) -> Value {
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn aliases_and_favorites_are_saved_for_disconnected_monitors() {
+    let dir = TempDir::new().unwrap();
+    let config = dir.path().join("config.json");
+
+    let output = cli(
+        &config,
+        &["alias", "id:DEL-A0F4-ABC123", "hdmi1", "Work laptop"],
+    );
+    assert!(output.status.success(), "{:?}", output);
+
+    // Aliases work as input names from then on.
+    let output = cli(
+        &config,
+        &["favorite", "add", "id:DEL-A0F4-ABC123", "work laptop"],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    let output = cli(&config, &["favorite", "add", "id:DEL-A0F4-ABC123", "0x0f"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let output = cli(&config, &["favorite", "list", "--json"]);
+    let favorites = json(&output);
+    assert_eq!(favorites[0]["monitor"], "DEL-A0F4-ABC123");
+    assert_eq!(favorites[0]["input"]["value"], 0x11);
+    assert_eq!(favorites[0]["input"]["alias"], "Work laptop");
+    assert_eq!(favorites[1]["input"]["name"], "DisplayPort 1");
+
+    let output = cli(&config, &["favorite", "list"]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "DEL-A0F4-ABC123: HDMI 1 (Work laptop)\nDEL-A0F4-ABC123: DisplayPort 1\n"
+    );
+
+    cli(&config, &["alias", "id:DEL-A0F4-ABC123", "17", "--remove"]);
+    let output = cli(&config, &["alias", "id:DEL-A0F4-ABC123", "17", "--json"]);
+    assert_eq!(json(&output)["alias"], Value::Null);
+}
+
+#[test]
+fn errors_have_stable_exit_codes() {
+    let dir = TempDir::new().unwrap();
+    let config = dir.path().join("config.json");
+
+    let output = cli(
+        &config,
+        &["alias", "id:DEL-A0F4-ABC123", "composite 9", "TV"],
+    );
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown input"));
+    // Only an explicit id configures a monitor that isn't connected.
+    let output = cli(&config, &["alias", "deks", "hdmi1", "Foo"]);
+    assert_eq!(output.status.code(), Some(12));
+    assert!(!config.exists());
+
+    let output = cli(&config, &["profile", "apply", "Nope", "--json"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(json(&output)["error"]["code"], "usage");
+
+    std::fs::write(&config, "{ not json").unwrap();
+    let output = cli(&config, &["profile", "list"]);
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn toggle_favorites_list_both_inputs() {
+    let dir = TempDir::new().unwrap();
+    let config = dir.path().join("config.json");
+
+    let args = [
+        "favorite",
+        "add",
+        "id:DEL-A0F4-ABC123",
+        "hdmi1",
+        "--toggle-with",
+        "dp1",
+    ];
+    let output = cli(&config, &args);
+    assert!(output.status.success(), "{:?}", output);
+
+    let output = cli(&config, &["favorite", "list"]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "DEL-A0F4-ABC123: HDMI 1 ⇄ DisplayPort 1\n"
+    );
+    let output = cli(&config, &["favorite", "list", "--json"]);
+    assert_eq!(json(&output)[0]["toggle_with"]["value"], 0x0F);
+
+    // Removing the plain favorite leaves the toggle alone.
+    cli(
+        &config,
+        &["favorite", "remove", "id:DEL-A0F4-ABC123", "hdmi1"],
+    );
+    let output = cli(&config, &["favorite", "list", "--json"]);
+    assert_eq!(json(&output).as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn commands_go_through_a_running_daemon() {
+    let dir = TempDir::new().unwrap();
+    let config = dir.path().join("config.json");
+    let backend = SimulatedBackend::new();
+    let desk = backend.add_monitor(
+        SimulatedMonitor::new("ACM", "Desk")
+            .serial_number("D")
+            .inputs(&[0x0F, 0x11])
+            .current_input(0x11),
+    );
+    let options = DaemonOptions {
+        config_path: Some(config.clone()),
+        history_path: Some(dir.path().join("config.state.json")),
+    };
+    let daemon = Daemon::bind(&dir.path().join("daemon.sock"), backend, options).unwrap();
+    thread::spawn(move || daemon.serve());
+
+    let output = cli(&config, &["set", "desk", "dp1"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Switched Desk to DisplayPort 1\n"
+    );
+    assert_eq!(desk.input(), Some(0x0F));
+
+    let output = cli(&config, &["list", "--json"]);
+    let monitors = json(&output);
+    assert_eq!(monitors[0]["id"], "sim-desk-D");
+    assert_eq!(monitors[0]["current_input"]["value"], 0x0F);
+    assert_eq!(monitors[0]["inputs"].as_array().unwrap().len(), 2);
+
+    let output = cli(&config, &["toggle", "1", "--json"]);
+    assert_eq!(json(&output)["input"]["name"], "HDMI 1");
+    let output = cli(&config, &["name", "desk", "Main", "--json"]);
+    assert_eq!(json(&output)["monitor"], "sim-desk-D");
+    let output = cli(&config, &["get", "desk"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "HDMI 1\n");
+
+    let output = cli(&config, &["cycle", "desk", "--json"]);
+    assert_eq!(output.status.code(), Some(21));
+    assert_eq!(json(&output)["error"]["code"], "no_favorites");
+    let output = cli(&config, &["set", "nope", "hdmi1"]);
+    assert_eq!(output.status.code(), Some(12));
+}
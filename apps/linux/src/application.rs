@@ -1,7 +1,11 @@
 mod imp {
     use gtk4::glib;
+    use gtk4::prelude::*;
     use gtk4::subclass::prelude::*;
+    use libadwaita as adw;
     use libadwaita::subclass::prelude::*;
+    use monitor_core::Config;
+    use std::path::PathBuf;
 
     use crate::window::MonitorSwitchWindow;
 
@@ -18,9 +22,16 @@ mod imp {
     impl ObjectImpl for MonitorSwitchApplication {}
 
     impl ApplicationImpl for MonitorSwitchApplication {
+        fn handle_local_options(&self, options: &glib::VariantDict) -> glib::ExitCode {
+            if let Ok(Some(path)) = options.lookup::<PathBuf>("config") {
+                Config::set_config_path(Some(path));
+            }
+            self.parent_handle_local_options(options)
+        }
+
         fn activate(&self) {
             let app = self.obj();
-            let window = MonitorSwitchWindow::new(&app);
+            let window = MonitorSwitchWindow::new(app.upcast_ref());
             window.present();
         }
     }
@@ -30,6 +41,7 @@ mod imp {
 }
 
 use gtk4::gio;
+use gtk4::gio::prelude::*;
 use gtk4::glib;
 use libadwaita as adw;
 
@@ -41,10 +53,19 @@ glib::wrapper! {
 
 impl MonitorSwitchApplication {
     pub fn new() -> Self {
-        glib::Object::builder()
+        let app: Self = glib::Object::builder()
             .property("application-id", crate::APP_ID)
             .property("flags", gio::ApplicationFlags::FLAGS_NONE)
-            .build()
+            .build();
+        app.add_main_option(
+            "config",
+            glib::Char::from(b'c'),
+            glib::OptionFlags::NONE,
+            glib::OptionArg::Filename,
+            "Read and write settings in FILE instead of the default config",
+            Some("FILE"),
+        );
+        app
     }
 }
 
@@ -53,4 +74,3 @@ impl Default for MonitorSwitchApplication {
         Self::new()
     }
 }
-
@@ -14,6 +14,8 @@ mod imp {
 
         pub monitor_index: Cell<usize>,
         pub input_value: Cell<u16>,
+        /// The other input of a toggle favorite, 0 for a plain input.
+        pub toggle_with: Cell<u16>,
     }
 
     #[glib::object_subclass]
@@ -37,6 +39,7 @@ mod imp {
 }
 
 use gtk4::glib;
+use gtk4::prelude::*;
 use gtk4::subclass::prelude::ObjectSubclassIsExt;
 use monitor_core::InputSource;
 
@@ -59,6 +62,18 @@ impl MonitorSwitchInputRow {
         row
     }
 
+    /// A row that toggles between `inputs`, for toggle favorites.
+    pub fn new_toggle(
+        label: &str,
+        is_current: bool,
+        monitor_index: usize,
+        inputs: (InputSource, InputSource),
+    ) -> Self {
+        let row = Self::new(label, is_current, monitor_index, inputs.0);
+        row.imp().toggle_with.set(inputs.1.to_vcp_value());
+        row
+    }
+
     pub fn monitor_index(&self) -> usize {
         self.imp().monitor_index.get()
     }
@@ -66,5 +81,12 @@ impl MonitorSwitchInputRow {
     pub fn input(&self) -> InputSource {
         InputSource::from_vcp_value(self.imp().input_value.get())
     }
+
+    pub fn toggle_pair(&self) -> Option<(InputSource, InputSource)> {
+        match self.imp().toggle_with.get() {
+            0 => None,
+            other => Some((self.input(), InputSource::from_vcp_value(other))),
+        }
+    }
 }
 
@@ -1,32 +1,43 @@
 use gtk4::prelude::*;
 use libadwaita as adw;
 use libadwaita::prelude::*;
-use monitor_core::{InputSource, Monitor};
+use monitor_core::{InputSource, MonitorSnapshot, Profile, ProfileTarget};
 use std::cell::RefCell;
 use std::rc::Rc;
 
 use crate::window::MonitorSwitchWindow;
 
-#[derive(Clone)]
-struct MonitorData {
-    id: String,
-    name: String,
-    available_inputs: Vec<InputSource>,
-    current_input: Option<InputSource>,
-}
-
 struct InputRowWidgets {
     input: InputSource,
     row: adw::EntryRow,
     favorite_switch: gtk4::Switch,
 }
 
+struct ProfileWidgets {
+    /// Name the profile is saved under, so it can be found after a rename.
+    saved_name: String,
+    group: adw::PreferencesGroup,
+    name_row: adw::EntryRow,
+    targets: Vec<TargetWidgets>,
+}
+
+/// Input and brightness choices for one connected monitor. The first entry
+/// of both rows means the profile leaves it unchanged.
+struct TargetWidgets {
+    monitor_id: String,
+    inputs: Vec<InputSource>,
+    input_row: adw::ComboRow,
+    brightness_levels: Vec<u8>,
+    brightness_row: adw::ComboRow,
+}
+
 pub struct PreferencesWindow {
     window: adw::PreferencesWindow,
     main_window: MonitorSwitchWindow,
-    monitors: Vec<MonitorData>,
+    monitors: Vec<MonitorSnapshot>,
     input_rows: Rc<RefCell<Vec<InputRowWidgets>>>,
     current_monitor_idx: Rc<RefCell<usize>>,
+    profile_rows: Rc<RefCell<Vec<ProfileWidgets>>>,
 }
 
 impl PreferencesWindow {
@@ -39,9 +50,12 @@ impl PreferencesWindow {
             .transient_for(parent)
             .build();
 
-        let monitors = load_monitors();
+        // Those the main window shows, so the monitors aren't read again
+        // behind the daemon's back.
+        let monitors = parent.monitors();
         let input_rows = Rc::new(RefCell::new(Vec::new()));
         let current_monitor_idx = Rc::new(RefCell::new(0));
+        let profile_rows = Rc::new(RefCell::new(Vec::new()));
 
         let prefs = Self {
             window,
@@ -49,6 +63,7 @@ impl PreferencesWindow {
             monitors,
             input_rows,
             current_monitor_idx,
+            profile_rows,
         };
 
         prefs.build_ui();
@@ -63,7 +78,8 @@ impl PreferencesWindow {
         let monitor_group = adw::PreferencesGroup::new();
         monitor_group.set_title("Monitor");
 
-        let monitor_names: Vec<&str> = self.monitors.iter().map(|m| m.name.as_str()).collect();
+        let monitor_names: Vec<String> = self.monitors.iter().map(|m| m.name()).collect();
+        let monitor_names: Vec<&str> = monitor_names.iter().map(String::as_str).collect();
         let monitor_list = gtk4::StringList::new(&monitor_names);
 
         let monitor_row = adw::ComboRow::builder()
@@ -84,12 +100,7 @@ impl PreferencesWindow {
         }
 
         let inputs_group_rc = Rc::new(inputs_group);
-        let prefs_clone = PreferencesWindowRef {
-            main_window: self.main_window.clone(),
-            monitors: self.monitors.clone(),
-            input_rows: self.input_rows.clone(),
-            current_monitor_idx: self.current_monitor_idx.clone(),
-        };
+        let prefs_clone = self.to_ref();
         let inputs_group_for_callback = inputs_group_rc.clone();
 
         monitor_row.connect_selected_notify(move |row| {
@@ -102,29 +113,179 @@ impl PreferencesWindow {
         });
 
         self.window.add(&page);
+        self.window.add(&self.build_profiles_page());
+        self.window.add(&self.build_details_page());
 
-        let prefs_clone = PreferencesWindowRef {
-            main_window: self.main_window.clone(),
-            monitors: self.monitors.clone(),
-            input_rows: self.input_rows.clone(),
-            current_monitor_idx: self.current_monitor_idx.clone(),
-        };
+        let prefs_clone = self.to_ref();
 
         self.window.connect_close_request(move |_| {
             prefs_clone.save_current_monitor();
+            prefs_clone.save_profiles();
             prefs_clone.main_window.refresh();
             glib::Propagation::Proceed
         });
     }
 
+    fn build_profiles_page(&self) -> adw::PreferencesPage {
+        let page = adw::PreferencesPage::new();
+        page.set_icon_name(Some("view-grid-symbolic"));
+        page.set_title("Profiles");
+
+        let new_group = adw::PreferencesGroup::new();
+        new_group.set_title("New Profile");
+        new_group.set_description(Some(
+            "Save the current input of every monitor, to switch them all back in one click",
+        ));
+        let name_row = adw::EntryRow::builder()
+            .title("Profile Name")
+            .show_apply_button(true)
+            .build();
+        new_group.add(&name_row);
+        page.add(&new_group);
+
+        let prefs = self.to_ref();
+        let profiles = self.main_window.config().profiles.clone();
+        for profile in &profiles {
+            prefs.add_profile_group(&page, profile);
+        }
+
+        let page_for_callback = page.clone();
+        name_row.connect_apply(move |row| {
+            let name = row.text().trim().to_string();
+            if name.is_empty() || prefs.main_window.config().get_profile(&name).is_some() {
+                row.add_css_class("error");
+                return;
+            }
+            row.remove_css_class("error");
+
+            let profile = Profile {
+                name,
+                monitors: prefs
+                    .monitors
+                    .iter()
+                    .filter_map(|monitor| {
+                        Some(ProfileTarget {
+                            monitor_id: monitor.id.clone(),
+                            input_value: monitor.current_input?.to_vcp_value(),
+                            brightness: None,
+                        })
+                    })
+                    .collect(),
+            };
+            prefs
+                .main_window
+                .update_config(|config| config.set_profile(profile.clone()));
+            prefs.add_profile_group(&page_for_callback, &profile);
+            row.set_text("");
+        });
+
+        page
+    }
+
+    fn build_details_page(&self) -> adw::PreferencesPage {
+        let page = adw::PreferencesPage::new();
+        page.set_icon_name(Some("video-display-symbolic"));
+        page.set_title("Details");
+
+        for monitor in &self.monitors {
+            let group = adw::PreferencesGroup::new();
+            group.set_title(&monitor.name());
+            group.add(&create_detail_row("Identifier", &monitor.id));
+
+            let Some(edid) = &monitor.edid else {
+                group.set_description(Some("The monitor did not provide an EDID"));
+                page.add(&group);
+                continue;
+            };
+
+            group.add(&create_detail_row("Manufacturer", &edid.manufacturer_id));
+            group.add(&create_detail_row(
+                "Product Code",
+                &format!("0x{:04X}", edid.product_code),
+            ));
+            let serial = edid
+                .serial_number
+                .clone()
+                .or(edid.serial.map(|s| s.to_string()));
+            if let Some(serial) = serial {
+                group.add(&create_detail_row("Serial Number", &serial));
+            }
+            let manufactured = match edid.manufacture_week {
+                Some(week) => format!("Week {}, {}", week, edid.manufacture_year),
+                None => edid.manufacture_year.to_string(),
+            };
+            group.add(&create_detail_row("Manufactured", &manufactured));
+            group.add(&create_detail_row(
+                "EDID Version",
+                &edid.version.to_string(),
+            ));
+            if let Some(resolution) = edid.native_resolution {
+                group.add(&create_detail_row(
+                    "Native Resolution",
+                    &format!("{} × {}", resolution.width, resolution.height),
+                ));
+            }
+            if let Some(size) = edid.physical_size {
+                let diagonal_in =
+                    ((size.width_mm as f64).powi(2) + (size.height_mm as f64).powi(2)).sqrt()
+                        / 25.4;
+                group.add(&create_detail_row(
+                    "Physical Size",
+                    &format!(
+                        "{} × {} mm ({:.1}\")",
+                        size.width_mm, size.height_mm, diagonal_in
+                    ),
+                ));
+            }
+            let interfaces: Vec<String> = edid
+                .video_interfaces
+                .iter()
+                .map(|i| i.to_string())
+                .collect();
+            group.add(&create_detail_row(
+                "Video Interfaces",
+                &interfaces.join(", "),
+            ));
+
+            let raw: Vec<String> = edid.raw.iter().map(|b| format!("{:02x}", b)).collect();
+            let raw_row = adw::ExpanderRow::builder().title("Raw EDID").build();
+            let raw_label = gtk4::Label::builder()
+                .label(
+                    raw.chunks(16)
+                        .map(|line| line.join(" "))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                )
+                .css_classes(["monospace", "dim-label"])
+                .selectable(true)
+                .xalign(0.0)
+                .margin_top(6)
+                .margin_bottom(6)
+                .margin_start(12)
+                .margin_end(12)
+                .build();
+            raw_row.add_row(&raw_label);
+            group.add(&raw_row);
+
+            page.add(&group);
+        }
+
+        page
+    }
+
     fn populate_inputs(&self, group: &adw::PreferencesGroup, monitor_idx: usize) {
-        let ref_data = PreferencesWindowRef {
+        let ref_data = self.to_ref();
+        ref_data.populate_inputs(group, monitor_idx);
+    }
+
+    fn to_ref(&self) -> PreferencesWindowRef {
+        PreferencesWindowRef {
             main_window: self.main_window.clone(),
             monitors: self.monitors.clone(),
             input_rows: self.input_rows.clone(),
             current_monitor_idx: self.current_monitor_idx.clone(),
-        };
-        ref_data.populate_inputs(group, monitor_idx);
+            profile_rows: self.profile_rows.clone(),
+        }
     }
 
     pub fn present(&self) {
@@ -135,9 +296,10 @@ impl PreferencesWindow {
 #[derive(Clone)]
 struct PreferencesWindowRef {
     main_window: MonitorSwitchWindow,
-    monitors: Vec<MonitorData>,
+    monitors: Vec<MonitorSnapshot>,
     input_rows: Rc<RefCell<Vec<InputRowWidgets>>>,
     current_monitor_idx: Rc<RefCell<usize>>,
+    profile_rows: Rc<RefCell<Vec<ProfileWidgets>>>,
 }
 
 impl PreferencesWindowRef {
@@ -153,7 +315,7 @@ impl PreferencesWindowRef {
 
         let config = self.main_window.config();
 
-        for &input in &monitor.available_inputs {
+        for &input in &monitor.inputs {
             let alias = config
                 .get_alias(&monitor.id, input.to_vcp_value())
                 .map(|s| s.to_string())
@@ -191,6 +353,178 @@ impl PreferencesWindowRef {
         }
     }
 
+    fn add_profile_group(&self, page: &adw::PreferencesPage, profile: &Profile) {
+        let group = adw::PreferencesGroup::new();
+        group.set_title(&profile.name);
+
+        let delete_button = gtk4::Button::builder()
+            .icon_name("user-trash-symbolic")
+            .tooltip_text("Delete Profile")
+            .valign(gtk4::Align::Center)
+            .css_classes(["flat"])
+            .build();
+        group.set_header_suffix(Some(&delete_button));
+
+        let name_row = adw::EntryRow::builder()
+            .title("Name")
+            .text(&profile.name)
+            .build();
+        group.add(&name_row);
+
+        let config = self.main_window.config();
+        let mut targets = Vec::new();
+        for monitor in &self.monitors {
+            let target = profile.monitors.iter().find(|t| t.monitor_id == monitor.id);
+
+            let mut inputs = monitor.inputs.clone();
+            if let Some(target) = target {
+                let input = InputSource::from_vcp_value(target.input_value);
+                if !inputs.contains(&input) {
+                    inputs.push(input);
+                }
+            }
+            let mut labels = vec!["Unchanged".to_string()];
+            labels.extend(inputs.iter().map(|&input| {
+                config
+                    .get_alias(&monitor.id, input.to_vcp_value())
+                    .map(String::from)
+                    .unwrap_or_else(|| input.name().to_string())
+            }));
+            let input_row = create_choice_row(&monitor.name(), "Input", &labels);
+            let selected = target
+                .and_then(|t| {
+                    inputs
+                        .iter()
+                        .position(|i| i.to_vcp_value() == t.input_value)
+                })
+                .map_or(0, |position| position + 1);
+            input_row.set_selected(selected as u32);
+
+            let mut brightness_levels: Vec<u8> = (0..=100).step_by(10).collect();
+            let brightness = target.and_then(|t| t.brightness);
+            if let Some(level) = brightness {
+                if !brightness_levels.contains(&level) {
+                    brightness_levels.push(level);
+                    brightness_levels.sort_unstable();
+                }
+            }
+            let mut labels = vec!["Unchanged".to_string()];
+            labels.extend(brightness_levels.iter().map(|level| format!("{}%", level)));
+            let brightness_row = create_choice_row(&monitor.name(), "Brightness", &labels);
+            let selected = brightness
+                .and_then(|b| brightness_levels.iter().position(|&level| level == b))
+                .map_or(0, |position| position + 1);
+            brightness_row.set_selected(selected as u32);
+
+            group.add(&input_row);
+            group.add(&brightness_row);
+            targets.push(TargetWidgets {
+                monitor_id: monitor.id.clone(),
+                inputs,
+                input_row,
+                brightness_levels,
+                brightness_row,
+            });
+        }
+        drop(config);
+
+        let disconnected = profile
+            .monitors
+            .iter()
+            .filter(|t| !self.monitors.iter().any(|m| m.id == t.monitor_id))
+            .count();
+        if disconnected > 0 {
+            group.set_description(Some(&format!(
+                "Also switches {} monitor(s) that are not connected",
+                disconnected
+            )));
+        }
+
+        page.add(&group);
+        self.profile_rows.borrow_mut().push(ProfileWidgets {
+            saved_name: profile.name.clone(),
+            group: group.clone(),
+            name_row,
+            targets,
+        });
+
+        let prefs = self.clone();
+        let page = page.clone();
+        delete_button.connect_clicked(move |_| {
+            let mut profile_rows = prefs.profile_rows.borrow_mut();
+            let Some(position) = profile_rows.iter().position(|w| w.group == group) else {
+                return;
+            };
+            let widgets = profile_rows.remove(position);
+            drop(profile_rows);
+
+            prefs
+                .main_window
+                .update_config(|config| config.remove_profile(&widgets.saved_name));
+            page.remove(&widgets.group);
+        });
+    }
+
+    fn save_profiles(&self) {
+        let profile_rows = self.profile_rows.borrow();
+        if profile_rows.is_empty() {
+            return;
+        }
+
+        self.main_window.update_config(|config| {
+            for widgets in profile_rows.iter() {
+                // Deleted elsewhere while this window was open: keep it
+                // deleted rather than bringing it back.
+                let Some(mut profile) = config.get_profile(&widgets.saved_name).cloned() else {
+                    continue;
+                };
+
+                // Only touch connected monitors; targets for the others stay
+                // as they were.
+                for target_widgets in &widgets.targets {
+                    let target = (target_widgets.input_row.selected() as usize)
+                        .checked_sub(1)
+                        .and_then(|i| target_widgets.inputs.get(i))
+                        .map(|input| ProfileTarget {
+                            monitor_id: target_widgets.monitor_id.clone(),
+                            input_value: input.to_vcp_value(),
+                            brightness: (target_widgets.brightness_row.selected() as usize)
+                                .checked_sub(1)
+                                .and_then(|i| target_widgets.brightness_levels.get(i))
+                                .copied(),
+                        });
+                    let existing = profile
+                        .monitors
+                        .iter()
+                        .position(|t| t.monitor_id == target_widgets.monitor_id);
+                    match (existing, target) {
+                        (Some(i), Some(target)) => profile.monitors[i] = target,
+                        (Some(i), None) => {
+                            profile.monitors.remove(i);
+                        }
+                        (None, Some(target)) => profile.monitors.push(target),
+                        (None, None) => {}
+                    }
+                }
+
+                let name = widgets.name_row.text().trim().to_string();
+                let taken = config.profiles.iter().any(|p| p.name == name);
+                if !name.is_empty() && !taken {
+                    // Rename in place so the Quick Switch order is kept.
+                    let saved = config
+                        .profiles
+                        .iter_mut()
+                        .find(|p| p.name == widgets.saved_name);
+                    if let Some(saved) = saved {
+                        saved.name = name.clone();
+                    }
+                    profile.name = name;
+                }
+                config.set_profile(profile);
+            }
+        });
+    }
+
     fn save_current_monitor(&self) {
         let idx = *self.current_monitor_idx.borrow();
         let Some(monitor) = self.monitors.get(idx) else {
@@ -215,29 +549,26 @@ impl PreferencesWindowRef {
                     config.remove_favorite(&monitor.id, input_value);
                 }
             }
-            config.save();
         });
     }
 }
 
-fn load_monitors() -> Vec<MonitorData> {
-    let mut monitors = Monitor::enumerate();
-    monitors
-        .iter_mut()
-        .enumerate()
-        .map(|(index, monitor)| {
-            let id = monitor.id();
-            let name = monitor
-                .model_name()
-                .or_else(|| monitor.manufacturer_id())
-                .unwrap_or_else(|| format!("Monitor {}", index + 1));
-            let available_inputs = monitor.get_available_inputs().unwrap_or_default();
-            let current_input = monitor.get_current_input().ok();
-
-            MonitorData { id, name, available_inputs, current_input }
-        })
-        .collect()
-}
-
 use gtk4::glib;
 
+fn create_detail_row(title: &str, value: &str) -> adw::ActionRow {
+    adw::ActionRow::builder()
+        .title(title)
+        .subtitle(value)
+        .subtitle_selectable(true)
+        .css_classes(["property"])
+        .build()
+}
+
+fn create_choice_row(title: &str, subtitle: &str, choices: &[String]) -> adw::ComboRow {
+    let choices: Vec<&str> = choices.iter().map(String::as_str).collect();
+    adw::ComboRow::builder()
+        .title(title)
+        .subtitle(subtitle)
+        .model(&gtk4::StringList::new(&choices))
+        .build()
+}
@@ -2,14 +2,19 @@ mod imp {
     use gtk4::glib;
     use gtk4::subclass::prelude::*;
     use gtk4::{Button, CheckButton, CompositeTemplate, ListBox, TemplateChild};
+    use libadwaita as adw;
     use libadwaita::subclass::prelude::*;
-    use std::cell::RefCell;
+    use std::cell::{Cell, RefCell};
 
-    use monitor_core::Config;
+    use monitor_core::{Config, ConfigWatcher, Monitor, MonitorSnapshot};
 
     #[derive(Default, CompositeTemplate)]
     #[template(resource = "/com/github/samneirinck/MonitorSwitch/window.ui")]
     pub struct MonitorSwitchWindow {
+        #[template_child]
+        pub toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub config_banner: TemplateChild<adw::Banner>,
         #[template_child]
         pub list_box: TemplateChild<ListBox>,
         #[template_child]
@@ -20,6 +25,13 @@ mod imp {
         pub autostart_check: TemplateChild<CheckButton>,
 
         pub config: RefCell<Config>,
+        pub config_watcher: RefCell<Option<ConfigWatcher>>,
+        pub monitors: RefCell<Vec<MonitorSnapshot>>,
+        /// The monitors as enumerated when not going through the daemon,
+        /// kept so changes don't have to enumerate again.
+        pub devices: RefCell<Vec<Monitor>>,
+        /// The user asked to check each display bus for a monitor.
+        pub probe_buses: Cell<bool>,
     }
 
     #[glib::object_subclass]
@@ -40,7 +52,7 @@ mod imp {
     impl ObjectImpl for MonitorSwitchWindow {
         fn constructed(&self) {
             self.parent_constructed();
-            self.config.replace(Config::load());
+            self.obj().reload_config();
         }
     }
 
@@ -53,9 +65,18 @@ mod imp {
 use gtk4::glib;
 use gtk4::prelude::*;
 use gtk4::subclass::prelude::ObjectSubclassIsExt;
-use gtk4::{Align, Label, ListBoxRow, Orientation, Separator};
+use gtk4::{Align, Button, Label, ListBoxRow, Orientation, Scale, Separator, ToggleButton};
 use libadwaita as adw;
-use monitor_core::{Config, InputSource, Monitor};
+use libadwaita::prelude::ActionRowExt;
+use monitor_core::{
+    apply_profile_with, toggle_input, ApplyOptions, Config, ConfigWatcher, DaemonClient, DdcStatus,
+    Diagnostics, DiagnosticsReport, InputHistory, InputListSource, InputSource, Monitor,
+    MonitorError, MonitorSnapshot, PowerMode, Profile, Setting, SwitchOptions, TargetOutcome,
+    PERCENT_STEP,
+};
+use std::cell::{RefCell, RefMut};
+use std::rc::Rc;
+use std::time::Duration;
 
 use crate::autostart::Autostart;
 use crate::input_row::MonitorSwitchInputRow;
@@ -68,13 +89,9 @@ glib::wrapper! {
                     gtk4::Root, gtk4::ShortcutManager;
 }
 
-struct MonitorData {
-    id: String,
-    name: String,
-    index: usize,
-    current_input: Option<InputSource>,
-    available_inputs: Vec<InputSource>,
-}
+const SLIDER_DEBOUNCE: Duration = Duration::from_millis(200);
+/// How often to check for config changes made outside the app.
+const CONFIG_POLL: Duration = Duration::from_millis(500);
 
 impl MonitorSwitchWindow {
     pub fn new(app: &adw::Application) -> Self {
@@ -108,23 +125,87 @@ impl MonitorSwitchWindow {
 
         let window = self.clone();
         imp.refresh_button.connect_clicked(move |_| {
-            window.refresh();
+            window.rescan();
         });
 
+        match ConfigWatcher::new() {
+            Ok(watcher) => {
+                imp.config_watcher.replace(Some(watcher));
+                let window = self.downgrade();
+                glib::timeout_add_local(CONFIG_POLL, move || {
+                    let Some(window) = window.upgrade() else {
+                        return glib::ControlFlow::Break;
+                    };
+                    window.reload_if_config_changed();
+                    glib::ControlFlow::Continue
+                });
+            }
+            Err(e) => glib::g_warning!("monitor-switch", "Not watching the config: {}", e),
+        }
+
         let window = self.clone();
         imp.list_box.connect_row_activated(move |_, row| {
             if let Some(input_row) = row.downcast_ref::<MonitorSwitchInputRow>() {
-                window.switch_input(input_row.monitor_index(), input_row.input());
+                match input_row.toggle_pair() {
+                    Some(pair) => window.toggle_input(input_row.monitor_index(), pair),
+                    None => window.switch_input(input_row.monitor_index(), input_row.input()),
+                }
             }
         });
     }
 
     pub fn refresh(&self) {
-        self.imp().config.replace(Config::load());
+        self.reload_config();
         self.clear_list();
         self.populate_list();
     }
 
+    /// Like `refresh`, but looks for monitors again, through the daemon if
+    /// it's running, instead of reading the ones already known.
+    fn rescan(&self) {
+        if let Some(mut daemon) = DaemonClient::try_connect() {
+            if let Err(e) = daemon.refresh() {
+                self.show_error("Could not refresh", &e.into());
+            }
+        }
+        self.imp().devices.borrow_mut().clear();
+        self.refresh();
+    }
+
+    /// Re-renders with the file's contents if it was edited by hand or by
+    /// another program.
+    fn reload_if_config_changed(&self) {
+        let changed = self
+            .imp()
+            .config_watcher
+            .borrow()
+            .as_ref()
+            .is_some_and(|watcher| watcher.try_iter().count() > 0);
+        if changed {
+            self.refresh();
+        }
+    }
+
+    /// Loads the config, falling back to the defaults with a banner if the
+    /// file is broken. Saving then backs the broken file up first.
+    fn reload_config(&self) {
+        let imp = self.imp();
+        match Config::load() {
+            Ok(config) => {
+                imp.config.replace(config);
+                imp.config_banner.set_revealed(false);
+            }
+            Err(e) => {
+                imp.config.replace(Config::default());
+                imp.config_banner.set_title(&format!(
+                    "Could not load settings: {}. Using defaults until the file is fixed.",
+                    e
+                ));
+                imp.config_banner.set_revealed(true);
+            }
+        }
+    }
+
     fn clear_list(&self) {
         let list_box = &self.imp().list_box;
         while let Some(child) = list_box.first_child() {
@@ -134,32 +215,137 @@ impl MonitorSwitchWindow {
 
     fn populate_list(&self) {
         let imp = self.imp();
+        let monitors = load_monitors(&mut imp.config.borrow_mut(), &mut imp.devices.borrow_mut());
         let config = imp.config.borrow();
-        let monitors = load_monitors();
+
+        if monitors.is_empty() {
+            if imp.probe_buses.get() {
+                append_diagnostics_rows(&imp.list_box, &Diagnostics::new().probe_buses().run());
+            } else {
+                append_diagnostics_rows(&imp.list_box, &Diagnostics::new().run());
+                imp.list_box.append(&self.create_probe_row());
+            }
+        }
 
         let favorites = config.get_favorites();
-        if !favorites.is_empty() {
+        if !favorites.is_empty() || !config.profiles.is_empty() {
             imp.list_box.append(&create_header_row("⭐ Quick Switch"));
 
-            for fav in &favorites {
-                if let Some(monitor) = monitors.iter().find(|m| m.id == fav.monitor_id) {
-                    let input = InputSource::from_vcp_value(fav.input_value);
-                    let is_current = monitor.current_input == Some(input);
-                    let display_name = get_input_display_name(&config, &monitor.id, input);
-                    let label = format!("{} → {}", display_name, monitor.name);
+            if !config.profiles.is_empty() {
+                imp.list_box
+                    .append(&self.create_profiles_row(&config.profiles));
+            }
 
-                    let row = MonitorSwitchInputRow::new(&label, is_current, monitor.index, input);
-                    imp.list_box.append(&row);
-                }
+            for fav in favorites {
+                let Some(monitor) = monitors.iter().find(|m| m.id == fav.monitor_id) else {
+                    continue;
+                };
+                let input = InputSource::from_vcp_value(fav.input_value);
+                let display_name = get_input_display_name(&config, &monitor.id, input);
+                let row = match fav.toggle_with.map(InputSource::from_vcp_value) {
+                    Some(other) => {
+                        let other_name = get_input_display_name(&config, &monitor.id, other);
+                        let label =
+                            format!("{} ⇄ {} → {}", display_name, other_name, monitor.name());
+                        let is_current = monitor
+                            .current_input
+                            .is_some_and(|current| current == input || current == other);
+                        MonitorSwitchInputRow::new_toggle(
+                            &label,
+                            is_current,
+                            monitor.index,
+                            (input, other),
+                        )
+                    }
+                    None => {
+                        let label = format!("{} → {}", display_name, monitor.name());
+                        let is_current = monitor.current_input == Some(input);
+                        MonitorSwitchInputRow::new(&label, is_current, monitor.index, input)
+                    }
+                };
+                imp.list_box.append(&row);
             }
 
             imp.list_box.append(&create_separator_row());
         }
 
         for monitor in &monitors {
-            imp.list_box.append(&create_header_row(&monitor.name));
+            let header = create_header_row(&monitor.name());
+            if monitor.input_list_source == Some(InputListSource::Fallback) {
+                header.set_tooltip_text(Some(
+                    "This monitor did not report its inputs, showing common inputs instead",
+                ));
+            }
+            imp.list_box.append(&header);
 
-            for &input in &monitor.available_inputs {
+            if let Some(power_mode) = monitor.power_mode {
+                let row = self.create_power_row(monitor.index, power_mode);
+                imp.list_box.append(&row);
+            }
+
+            if let Some(brightness) = monitor.brightness {
+                let (row, scale) = create_slider_row("Brightness", brightness, None);
+                let monitor_index = monitor.index;
+                let window = self.clone();
+                connect_percent_changed(&scale, move |percent| {
+                    window.try_set(
+                        monitor_index,
+                        "Could not set brightness",
+                        Setting::Brightness(percent),
+                    );
+                });
+                imp.list_box.append(&row);
+            }
+
+            if let Some(contrast) = monitor.contrast {
+                let (row, scale) = create_slider_row("Contrast", contrast, None);
+                let monitor_index = monitor.index;
+                let window = self.clone();
+                connect_percent_changed(&scale, move |percent| {
+                    window.try_set(
+                        monitor_index,
+                        "Could not set contrast",
+                        Setting::Contrast(percent),
+                    );
+                });
+                imp.list_box.append(&row);
+            }
+
+            if let Some(volume) = monitor.volume {
+                let mute_button = monitor.muted.map(|muted| {
+                    let button = ToggleButton::builder()
+                        .icon_name("audio-volume-muted-symbolic")
+                        .tooltip_text("Mute")
+                        .active(muted)
+                        .valign(Align::Center)
+                        .build();
+                    let monitor_index = monitor.index;
+                    let window = self.clone();
+                    button.connect_toggled(move |button| {
+                        let muted = button.is_active();
+                        window.try_set(monitor_index, "Could not mute", Setting::Muted(muted));
+                    });
+                    button
+                });
+
+                let (row, scale) = create_slider_row(
+                    "Volume",
+                    volume,
+                    mute_button.as_ref().map(|b| b.upcast_ref()),
+                );
+                let monitor_index = monitor.index;
+                let window = self.clone();
+                connect_percent_changed(&scale, move |percent| {
+                    window.try_set(
+                        monitor_index,
+                        "Could not set volume",
+                        Setting::Volume(percent),
+                    );
+                });
+                imp.list_box.append(&row);
+            }
+
+            for &input in &monitor.inputs {
                 let is_current = monitor.current_input == Some(input);
                 let is_favorite = config.is_favorite(&monitor.id, input.to_vcp_value());
                 let display_name = get_input_display_name(&config, &monitor.id, input);
@@ -175,53 +361,328 @@ impl MonitorSwitchWindow {
 
             imp.list_box.append(&create_separator_row());
         }
+
+        imp.monitors.replace(monitors);
     }
 
     fn switch_input(&self, monitor_index: usize, input: InputSource) {
-        let mut monitors = Monitor::enumerate();
-        if let Some(monitor) = monitors.get_mut(monitor_index) {
-            let _ = monitor.set_input(input);
+        let result = match self.daemon(monitor_index) {
+            Some((mut daemon, monitor)) => daemon
+                .set_input(&monitor, &input.to_vcp_value().to_string(), true)
+                .map(drop)
+                .map_err(MonitorError::from),
+            None => {
+                let mut history = InputHistory::load();
+                let result = self
+                    .with_monitor(monitor_index, |monitor| {
+                        let options = SwitchOptions {
+                            ensure_powered_on: true,
+                            ..monitor.switch_options().clone()
+                        };
+                        monitor_core::switch_input(monitor, input, &options, &mut history)
+                    })
+                    .unwrap_or(Err(MonitorError::NotFound));
+                save_history(&history);
+                result
+            }
+        };
+        self.refresh();
+
+        if let Err(e) = result {
+            self.show_error(&format!("Could not switch to {}", input), &e);
         }
+    }
+
+    fn toggle_input(&self, monitor_index: usize, pair: (InputSource, InputSource)) {
+        let result = match self.daemon(monitor_index) {
+            Some((mut daemon, monitor)) => {
+                let first = pair.0.to_vcp_value().to_string();
+                let second = pair.1.to_vcp_value().to_string();
+                daemon
+                    .toggle_input(&monitor, Some((&first, &second)), true)
+                    .map(drop)
+                    .map_err(MonitorError::from)
+            }
+            None => {
+                let mut history = InputHistory::load();
+                let result = self
+                    .with_monitor(monitor_index, |monitor| {
+                        toggle_input(monitor, Some(pair), true, &mut history)
+                    })
+                    .unwrap_or(Err(MonitorError::NotFound));
+                save_history(&history);
+                result.map(drop)
+            }
+        };
         self.refresh();
+
+        if let Err(e) = result {
+            self.show_error(&format!("Could not switch to {} or {}", pair.0, pair.1), &e);
+        }
+    }
+
+    fn apply_profile(&self, name: &str) {
+        // Rather all monitors stay put than half of the desk switching.
+        let options = ApplyOptions {
+            rollback: true,
+            wake: true,
+            ..ApplyOptions::default()
+        };
+        let report = match DaemonClient::try_connect() {
+            Some(mut daemon) => daemon.apply_profile(name, &options),
+            None => {
+                let Some(profile) = self.imp().config.borrow().get_profile(name).cloned() else {
+                    return;
+                };
+                Ok(apply_profile_with(&mut self.devices(), &profile, &options))
+            }
+        };
+        self.refresh();
+
+        let report = match report {
+            Ok(report) => report,
+            Err(e) => {
+                self.show_error(&format!("Could not apply {}", name), &e.into());
+                return;
+            }
+        };
+        if report.rolled_back {
+            self.show_toast(&format!("{}: switched the monitors back", report.profile));
+        }
+        let monitors = self.imp().monitors.borrow();
+        for failure in report.failures() {
+            let Some(error) = failure.error() else {
+                continue;
+            };
+            let name = monitors
+                .iter()
+                .find(|m| m.id == failure.monitor_id)
+                .map(|m| m.name())
+                .unwrap_or_else(|| failure.monitor_id.clone());
+            let context = match failure.outcome {
+                TargetOutcome::RollbackFailed(_) => format!("Could not switch {} back", name),
+                _ => format!("{}: could not switch {}", report.profile, name),
+            };
+            self.show_error(&context, error);
+        }
+    }
+
+    pub fn show_toast(&self, message: &str) {
+        self.imp().toast_overlay.add_toast(adw::Toast::new(message));
+    }
+
+    fn show_error(&self, context: &str, error: &MonitorError) {
+        let toast = match error.hint() {
+            Some(hint) => {
+                let toast = adw::Toast::new(&format!("{}: {}. {}", context, error, hint));
+                toast.set_timeout(10);
+                toast
+            }
+            None => adw::Toast::new(&format!("{}: {}", context, error)),
+        };
+        self.imp().toast_overlay.add_toast(toast);
+    }
+
+    fn create_power_row(&self, monitor_index: usize, current: PowerMode) -> ListBoxRow {
+        let content = gtk4::Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(8)
+            .margin_start(8)
+            .margin_end(8)
+            .margin_top(4)
+            .margin_bottom(4)
+            .build();
+
+        content.append(
+            &Label::builder()
+                .label("Power")
+                .halign(Align::Start)
+                .hexpand(true)
+                .build(),
+        );
+
+        for mode in [PowerMode::On, PowerMode::Standby, PowerMode::Off] {
+            let button = Button::with_label(mode.name());
+            if mode == current {
+                button.add_css_class("suggested-action");
+            }
+
+            let window = self.clone();
+            button.connect_clicked(move |_| {
+                window.try_set(
+                    monitor_index,
+                    "Could not change power mode",
+                    Setting::PowerMode(mode),
+                );
+                window.refresh();
+            });
+            content.append(&button);
+        }
+
+        ListBoxRow::builder()
+            .activatable(false)
+            .selectable(false)
+            .child(&content)
+            .build()
+    }
+
+    /// Offers to ask each display bus for a monitor, which writes to them.
+    fn create_probe_row(&self) -> ListBoxRow {
+        let button = Button::builder()
+            .label("Check Display Buses")
+            .halign(Align::Center)
+            .margin_top(4)
+            .margin_bottom(4)
+            .build();
+        let window = self.clone();
+        button.connect_clicked(move |_| {
+            window.imp().probe_buses.set(true);
+            window.refresh();
+        });
+
+        ListBoxRow::builder()
+            .activatable(false)
+            .selectable(false)
+            .child(&button)
+            .build()
+    }
+
+    fn create_profiles_row(&self, profiles: &[Profile]) -> ListBoxRow {
+        let content = gtk4::FlowBox::builder()
+            .selection_mode(gtk4::SelectionMode::None)
+            .column_spacing(6)
+            .row_spacing(6)
+            .margin_start(8)
+            .margin_end(8)
+            .margin_top(4)
+            .margin_bottom(4)
+            .build();
+
+        for profile in profiles {
+            let button = Button::with_label(&profile.name);
+            let name = profile.name.clone();
+            let window = self.clone();
+            button.connect_clicked(move |_| window.apply_profile(&name));
+            content.append(&button);
+        }
+
+        ListBoxRow::builder()
+            .activatable(false)
+            .selectable(false)
+            .child(&content)
+            .build()
+    }
+
+    /// The monitors to talk to directly, enumerated the first time.
+    fn devices(&self) -> RefMut<'_, Vec<Monitor>> {
+        let imp = self.imp();
+        let mut devices = imp.devices.borrow_mut();
+        if devices.is_empty() {
+            *devices = enumerate(&mut imp.config.borrow_mut());
+        }
+        devices
     }
 
-    pub fn config(&self) -> std::cell::Ref<Config> {
+    /// Runs `f` on the monitor shown at `monitor_index`, found by its id in
+    /// case the monitors were enumerated in a different order since.
+    fn with_monitor<F, R>(&self, monitor_index: usize, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut Monitor) -> R,
+    {
+        let id = self.imp().monitors.borrow().get(monitor_index)?.id.clone();
+        self.devices().iter_mut().find(|m| m.id() == id).map(f)
+    }
+
+    /// A connection to the daemon, if it's running, and how to name the
+    /// monitor at `monitor_index` to it.
+    fn daemon(&self, monitor_index: usize) -> Option<(DaemonClient, String)> {
+        let id = self.imp().monitors.borrow().get(monitor_index)?.id.clone();
+        Some((DaemonClient::try_connect()?, format!("id:{}", id)))
+    }
+
+    /// Changes a setting, reporting failures in a toast.
+    fn try_set(&self, monitor_index: usize, context: &str, setting: Setting) {
+        let result = match self.daemon(monitor_index) {
+            Some((mut daemon, monitor)) => daemon
+                .set(&monitor, setting)
+                .map(drop)
+                .map_err(MonitorError::from),
+            None => self
+                .with_monitor(monitor_index, |monitor| setting.apply(monitor))
+                .unwrap_or(Err(MonitorError::NotFound)),
+        };
+        if let Err(e) = result {
+            self.show_error(context, &e);
+        }
+    }
+
+    /// The monitors as last shown.
+    pub fn monitors(&self) -> Vec<MonitorSnapshot> {
+        self.imp().monitors.borrow().clone()
+    }
+
+    pub fn config(&self) -> std::cell::Ref<'_, Config> {
         self.imp().config.borrow()
     }
 
+    /// Applies `f` to the config on disk and saves it, keeping changes made
+    /// by other instances in the meantime.
     pub fn update_config<F>(&self, f: F)
     where
         F: FnOnce(&mut Config),
     {
-        f(&mut self.imp().config.borrow_mut());
+        match Config::update(f) {
+            Ok(config) => {
+                self.imp().config.replace(config);
+            }
+            Err(e) => self.show_toast(&format!("Could not save settings: {}", e)),
+        }
     }
 }
 
-fn load_monitors() -> Vec<MonitorData> {
-    let mut monitors = Monitor::enumerate();
-    monitors
+/// The daemon's monitors if it's running, else reads `devices`, enumerating
+/// them first if there are none yet. The daemon migrates legacy monitor ids
+/// in the config itself.
+fn load_monitors(config: &mut Config, devices: &mut Vec<Monitor>) -> Vec<MonitorSnapshot> {
+    if let Some(mut daemon) = DaemonClient::try_connect() {
+        match daemon.list() {
+            Ok(monitors) => return monitors,
+            Err(e) => glib::g_warning!("monitor-switch", "Not using the daemon: {}", e),
+        }
+    }
+
+    if devices.is_empty() {
+        *devices = enumerate(config);
+    } else {
+        for device in devices.iter_mut() {
+            device.configure(config);
+        }
+    }
+    devices
         .iter_mut()
         .enumerate()
-        .map(|(index, monitor)| {
-            let id = monitor.id();
-            let name = monitor
-                .model_name()
-                .or_else(|| monitor.manufacturer_id())
-                .unwrap_or_else(|| format!("Monitor {}", index + 1));
-            let current_input = monitor.get_current_input().ok();
-            let available_inputs = monitor.get_available_inputs().unwrap_or_default();
-
-            MonitorData {
-                id,
-                name,
-                index,
-                current_input,
-                available_inputs,
-            }
-        })
+        .map(|(index, monitor)| MonitorSnapshot::read(monitor, index))
         .collect()
 }
 
+fn enumerate(config: &mut Config) -> Vec<Monitor> {
+    let monitors = Monitor::enumerate_with_config(config);
+    if config.migrate_monitor_ids(&monitors) {
+        if let Ok(updated) = Config::update(|c| {
+            c.migrate_monitor_ids(&monitors);
+        }) {
+            *config = updated;
+        }
+    }
+    monitors
+}
+
+fn save_history(history: &InputHistory) {
+    if let Err(e) = history.save() {
+        glib::g_warning!("monitor-switch", "Could not save the input history: {}", e);
+    }
+}
+
 fn get_input_display_name(config: &Config, monitor_id: &str, input: InputSource) -> String {
     config
         .get_alias(monitor_id, input.to_vcp_value())
@@ -243,6 +704,79 @@ fn create_header_row(text: &str) -> ListBoxRow {
         .build()
 }
 
+/// Explains an empty monitor list with the DDC/CI access checks.
+fn append_diagnostics_rows(list_box: &gtk4::ListBox, report: &DiagnosticsReport) {
+    list_box.append(&create_header_row("No monitors found"));
+
+    let (title, subtitle) = match report.problem() {
+        Some(problem) => (
+            problem.to_string(),
+            problem.hint().unwrap_or_default().to_string(),
+        ),
+        None => (
+            "A monitor answered but could not be identified".to_string(),
+            "Try refreshing the list".to_string(),
+        ),
+    };
+    list_box.append(&create_check_row(&title, &subtitle, false));
+
+    let module_status = if report.i2c_dev_loaded {
+        "Loaded"
+    } else {
+        "Not loaded"
+    };
+    list_box.append(&create_check_row(
+        "i2c-dev module",
+        module_status,
+        report.i2c_dev_loaded,
+    ));
+
+    for group in &report.groups {
+        let name = group.name.clone().unwrap_or_else(|| group.gid.to_string());
+        let status = match (group.active, group.listed) {
+            (true, _) => "You are a member",
+            (false, true) => "Added, log out and back in to apply",
+            (false, false) => "You are not a member",
+        };
+        list_box.append(&create_check_row(
+            &format!("Group {}", name),
+            status,
+            group.active,
+        ));
+    }
+
+    for bus in &report.buses {
+        let title = match &bus.adapter {
+            Some(adapter) => format!("{} ({})", bus.path.display(), adapter),
+            None => bus.path.display().to_string(),
+        };
+        let (status, ok) = match &bus.ddc {
+            DdcStatus::Responding => ("Monitor responding".to_string(), true),
+            DdcStatus::NoResponse(e) => (format!("No DDC/CI reply: {}", e), false),
+            DdcStatus::NotProbed => ("No read/write access".to_string(), false),
+            DdcStatus::Skipped => ("Not checked".to_string(), false),
+            DdcStatus::NotDisplay => continue,
+        };
+        list_box.append(&create_check_row(&title, &status, ok));
+    }
+}
+
+fn create_check_row(title: &str, subtitle: &str, ok: bool) -> adw::ActionRow {
+    let icon = if ok {
+        "emblem-ok-symbolic"
+    } else {
+        "dialog-warning-symbolic"
+    };
+    let row = adw::ActionRow::builder()
+        .title(title)
+        .subtitle(subtitle)
+        .activatable(false)
+        .selectable(false)
+        .build();
+    row.add_prefix(&gtk4::Image::from_icon_name(icon));
+    row
+}
+
 fn create_separator_row() -> ListBoxRow {
     ListBoxRow::builder()
         .activatable(false)
@@ -251,3 +785,63 @@ fn create_separator_row() -> ListBoxRow {
         .build()
 }
 
+fn create_slider_row(
+    title: &str,
+    percent: u8,
+    suffix: Option<&gtk4::Widget>,
+) -> (ListBoxRow, Scale) {
+    let label = Label::builder()
+        .label(title)
+        .halign(Align::Start)
+        .width_chars(10)
+        .xalign(0.0)
+        .build();
+
+    let scale = Scale::with_range(Orientation::Horizontal, 0.0, 100.0, PERCENT_STEP as f64);
+    scale.set_value(percent as f64);
+    scale.set_hexpand(true);
+    scale.set_draw_value(false);
+
+    let content = gtk4::Box::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(8)
+        .margin_start(8)
+        .margin_end(8)
+        .build();
+    content.append(&label);
+    content.append(&scale);
+    if let Some(suffix) = suffix {
+        content.append(suffix);
+    }
+
+    let row = ListBoxRow::builder()
+        .activatable(false)
+        .selectable(false)
+        .child(&content)
+        .build();
+
+    (row, scale)
+}
+
+fn connect_percent_changed<F>(scale: &Scale, f: F)
+where
+    F: Fn(u8) + 'static,
+{
+    let pending: Rc<RefCell<Option<glib::SourceId>>> = Rc::default();
+    let f = Rc::new(f);
+
+    scale.connect_value_changed(move |scale| {
+        if let Some(source) = pending.borrow_mut().take() {
+            source.remove();
+        }
+
+        let percent = scale.value().round() as u8;
+        let f = f.clone();
+        let fired = pending.clone();
+        let source = glib::timeout_add_local_once(SLIDER_DEBOUNCE, move || {
+            fired.borrow_mut().take();
+            f(percent);
+        });
+        pending.borrow_mut().replace(source);
+    });
+}
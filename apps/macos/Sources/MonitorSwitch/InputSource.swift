@@ -1,9 +1,13 @@
-import CMonitorCore
+import Foundation
 
-typealias InputSource = CMonitorCore.InputSource
+struct InputSource: Hashable {
+    let rawValue: UInt16
 
-extension InputSource {
-    private static let names: [UInt32: String] = [
+    init(rawValue: UInt16) {
+        self.rawValue = rawValue
+    }
+
+    private static let names: [UInt16: String] = [
         1: "VGA 1", 2: "VGA 2",
         3: "DVI 1", 4: "DVI 2",
         5: "Composite 1", 6: "Composite 2",
@@ -16,7 +20,6 @@ extension InputSource {
     ]
 
     var displayName: String {
-        Self.names[rawValue] ?? "Unknown"
+        Self.names[rawValue] ?? String(format: "Input 0x%02X", rawValue)
     }
 }
-
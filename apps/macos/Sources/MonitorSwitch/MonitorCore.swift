@@ -24,10 +24,24 @@ final class MonitorCore {
 
     private(set) var monitors: [MonitorInfo] = []
     private(set) var refreshTrigger = false
+    private(set) var lastError: String?
+    private(set) var configError: String?
 
     private init() {
-        monitor_core_init()
+        // `-configPath <file>` on the command line, or the matching default.
+        monitor_core_init_with_config(UserDefaults.standard.string(forKey: "configPath"))
+        configError = takeConfigError()
         refreshMonitors()
+        watchConfig()
+    }
+
+    /// Picks up edits made to the config file outside the app.
+    private func watchConfig() {
+        Timer.scheduledTimer(withTimeInterval: 1, repeats: true) { [weak self] _ in
+            guard let self, config_poll_changes() else { return }
+            configError = takeConfigError()
+            refreshTrigger.toggle()
+        }
     }
 
     func refreshMonitors() {
@@ -55,24 +69,52 @@ final class MonitorCore {
     }
 
     func getCurrentInput(monitorIndex: Int) -> InputSource {
-        monitor_get_current_input(UInt(monitorIndex))
+        InputSource(rawValue: monitor_get_current_input(UInt(monitorIndex)))
     }
 
     func setInput(monitorIndex: Int, input: InputSource) {
-        _ = monitor_set_input(UInt(monitorIndex), input)
+        if monitor_set_input(UInt(monitorIndex), input.rawValue) {
+            lastError = nil
+        } else {
+            lastError = takeLastError()
+        }
+        refreshTrigger.toggle()
+    }
+
+    /// Switches between the two inputs, or back to the previous input if
+    /// `inputs` is nil.
+    func toggleInput(monitorIndex: Int, inputs: (InputSource, InputSource)? = nil) {
+        let first = inputs?.0.rawValue ?? 0
+        let second = inputs?.1.rawValue ?? 0
+        if monitor_toggle_input(UInt(monitorIndex), first, second) != 0 {
+            lastError = nil
+        } else {
+            lastError = takeLastError()
+        }
         refreshTrigger.toggle()
     }
 
+    private func takeLastError() -> String? {
+        guard let messagePtr = monitor_last_error_message() else { return nil }
+        var message = String(cString: messagePtr)
+        string_free(messagePtr)
+        if let hintPtr = monitor_last_error_hint() {
+            message += ". " + String(cString: hintPtr)
+            string_free(hintPtr)
+        }
+        return message
+    }
+
     func getAvailableInputs(monitorIndex: Int) -> [InputSource] {
         let list = monitor_get_available_inputs(UInt(monitorIndex))
         defer { input_source_list_free(list) }
 
         guard let inputsPtr = list.inputs else { return [] }
-        return (0..<Int(list.count)).map { inputsPtr[$0] }
+        return (0..<Int(list.count)).map { InputSource(rawValue: inputsPtr[$0]) }
     }
 
     func getAlias(monitorId: String, input: InputSource) -> String? {
-        guard let ptr = config_get_alias(monitorId, UInt16(input.rawValue)) else {
+        guard let ptr = config_get_alias(monitorId, input.rawValue) else {
             return nil
         }
         let alias = String(cString: ptr)
@@ -81,38 +123,49 @@ final class MonitorCore {
     }
 
     func setAlias(monitorId: String, input: InputSource, alias: String) {
-        _ = config_set_alias(monitorId, UInt16(input.rawValue), alias)
+        _ = config_set_alias(monitorId, input.rawValue, alias)
     }
 
     func removeAlias(monitorId: String, input: InputSource) {
-        _ = config_remove_alias(monitorId, UInt16(input.rawValue))
+        _ = config_remove_alias(monitorId, input.rawValue)
     }
 
     func reloadConfig() {
         config_reload()
+        configError = takeConfigError()
         refreshTrigger.toggle()
     }
 
+    private func takeConfigError() -> String? {
+        guard let ptr = config_load_error() else { return nil }
+        let message = String(cString: ptr)
+        string_free(ptr)
+        return message
+    }
+
     func isFavorite(monitorId: String, input: InputSource) -> Bool {
-        config_is_favorite(monitorId, UInt16(input.rawValue))
+        config_is_favorite(monitorId, input.rawValue)
     }
 
     func addFavorite(monitorId: String, input: InputSource) {
-        _ = config_add_favorite(monitorId, UInt16(input.rawValue))
+        _ = config_add_favorite(monitorId, input.rawValue)
     }
 
     func removeFavorite(monitorId: String, input: InputSource) {
-        _ = config_remove_favorite(monitorId, UInt16(input.rawValue))
+        _ = config_remove_favorite(monitorId, input.rawValue)
     }
 
-    func getFavorites() -> [(monitorId: String, inputValue: UInt16)] {
+    /// `toggleWith` is the other input of a toggle favorite, nil for a
+    /// plain one.
+    func getFavorites() -> [(monitorId: String, inputValue: UInt16, toggleWith: UInt16?)] {
         let list = config_get_favorites()
         defer { favorite_list_free(list) }
 
         guard let ptr = list.favorites else { return [] }
         return (0..<Int(list.count)).compactMap { i in
             guard let monitorIdPtr = ptr[i].monitor_id else { return nil }
-            return (String(cString: monitorIdPtr), ptr[i].input_value)
+            let toggleWith = ptr[i].toggle_with == 0 ? nil : ptr[i].toggle_with
+            return (String(cString: monitorIdPtr), ptr[i].input_value, toggleWith)
         }
     }
 }
@@ -7,9 +7,19 @@ struct MonitorMenu: View {
     var body: some View {
         let _ = monitorCore.refreshTrigger
 
+        if let error = monitorCore.configError {
+            Text("⚠️ Settings not loaded: \(error)")
+            Divider()
+        }
+
         if monitorCore.monitors.isEmpty {
             Text("No monitors found")
         } else {
+            if let error = monitorCore.lastError {
+                Text("⚠️ \(error)")
+                Divider()
+            }
+
             FavoritesSection()
 
             ForEach(monitorCore.monitors) { monitor in
@@ -55,15 +65,23 @@ struct FavoritesSection: View {
 
         if !favorites.isEmpty {
             Section("⭐ Quick Switch") {
-                ForEach(favorites, id: \.inputValue) { favorite in
+                ForEach(favorites.indices, id: \.self) { i in
+                    let favorite = favorites[i]
                     if let monitor = monitorCore.monitors.first(where: { $0.id == favorite.monitorId }) {
-                        let input = InputSource(rawValue: UInt32(favorite.inputValue))
-                        let inputName = monitorCore.getInputDisplayName(monitorId: monitor.id, input: input)
+                        let input = InputSource(rawValue: favorite.inputValue)
+                        let other = favorite.toggleWith.map { InputSource(rawValue: $0) }
+                        let inputName = [input, other].compactMap { $0 }
+                            .map { monitorCore.getInputDisplayName(monitorId: monitor.id, input: $0) }
+                            .joined(separator: " ⇄ ")
                         let currentInput = monitorCore.getCurrentInput(monitorIndex: monitor.index)
-                        let isSelected = input == currentInput
+                        let isSelected = input == currentInput || (other != nil && other == currentInput)
 
                         Button {
-                            monitorCore.setInput(monitorIndex: monitor.index, input: input)
+                            if let other {
+                                monitorCore.toggleInput(monitorIndex: monitor.index, inputs: (input, other))
+                            } else {
+                                monitorCore.setInput(monitorIndex: monitor.index, input: input)
+                            }
                         } label: {
                             HStack {
                                 Text("\(inputName) → \(monitor.displayName)")
@@ -6,7 +6,7 @@ struct PreferencesView: View {
     @State private var inputRows: [InputRow] = []
 
     struct InputRow: Identifiable {
-        let id: UInt32
+        let id: UInt16
         let input: InputSource
         var alias: String
         var isFavorite: Bool
@@ -0,0 +1,301 @@
+use std::error::Error;
+use std::io;
+
+use ddc::ErrorCode;
+use ddc_hi::{Ddc, Display};
+
+use crate::{MonitorError, VcpFeature};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub id: String,
+    pub manufacturer_id: Option<String>,
+    pub model_name: Option<String>,
+    pub serial_number: Option<String>,
+    pub serial: Option<u32>,
+    /// EDID product code.
+    pub model_id: Option<u16>,
+    pub manufacture_week: Option<u8>,
+    /// Years since 1990, as stored in the EDID.
+    pub manufacture_year: Option<u8>,
+    pub edid: Option<Vec<u8>>,
+}
+
+impl DeviceInfo {
+    /// An identifier derived from the EDID that stays the same across
+    /// reboots, ports and enumeration order. `None` when the backend
+    /// couldn't read the manufacturer and product code.
+    pub fn stable_id(&self) -> Option<String> {
+        let manufacturer = self.manufacturer_id.as_deref()?.trim();
+        let product = self.model_id?;
+        let mut id = format!("{}-{:04X}", manufacturer, product);
+
+        let serial_number = self.serial_number.as_deref().map(str::trim);
+        if let Some(serial) = serial_number.filter(|s| !s.is_empty()) {
+            id.push_str(&format!("-{}", serial));
+        } else if let Some(serial) = self.serial.filter(|&s| s != 0) {
+            id.push_str(&format!("-{}", serial));
+        } else {
+            // No serial: fall back to what else tells two units apart.
+            if let (Some(week), Some(year)) = (self.manufacture_week, self.manufacture_year) {
+                id.push_str(&format!("-{}W{:02}", 1990 + year as u16, week));
+            }
+            if let Some(edid) = &self.edid {
+                id.push_str(&format!("-{:08x}", edid_hash(edid)));
+            }
+        }
+        Some(id)
+    }
+
+    /// The identifier used before EDID-based ids, kept to migrate config
+    /// entries written by older versions.
+    pub fn legacy_id(&self, index: usize) -> String {
+        if let Some(serial) = &self.serial_number {
+            format!("{}-{}", self.id, serial)
+        } else if let Some(serial) = self.serial {
+            format!("{}-{}", self.id, serial)
+        } else {
+            format!("{}-{}", self.id, index)
+        }
+    }
+}
+
+/// A connected display that can be talked to over DDC/CI.
+pub trait Device {
+    fn info(&self) -> &DeviceInfo;
+    fn get_vcp_feature(&mut self, code: u8) -> Result<VcpFeature, MonitorError>;
+    fn set_vcp_feature(&mut self, code: u8, value: u16) -> Result<(), MonitorError>;
+    fn capabilities_string(&mut self) -> Result<Vec<u8>, MonitorError>;
+}
+
+/// Discovers connected displays. `Monitor::enumerate` uses [`DdcHiBackend`].
+pub trait Backend {
+    fn enumerate(&self) -> Vec<Box<dyn Device>>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DdcHiBackend;
+
+impl Backend for DdcHiBackend {
+    fn enumerate(&self) -> Vec<Box<dyn Device>> {
+        Display::enumerate()
+            .into_iter()
+            .map(|display| Box::new(DdcHiDevice::new(display)) as Box<dyn Device>)
+            .collect()
+    }
+}
+
+struct DdcHiDevice {
+    display: Display,
+    info: DeviceInfo,
+    /// What to name in errors about access to the device.
+    device: String,
+}
+
+impl DdcHiDevice {
+    fn new(display: Display) -> Self {
+        let info = DeviceInfo {
+            id: display.info.id.clone(),
+            manufacturer_id: display.info.manufacturer_id.clone(),
+            model_name: display.info.model_name.clone(),
+            serial_number: display.info.serial_number.clone(),
+            serial: display.info.serial,
+            model_id: display.info.model_id,
+            manufacture_week: display.info.manufacture_week,
+            manufacture_year: display.info.manufacture_year,
+            edid: display.info.edid_data.clone(),
+        };
+        let device = device_path(&info.id);
+        DdcHiDevice {
+            display,
+            info,
+            device,
+        }
+    }
+}
+
+impl Device for DdcHiDevice {
+    fn info(&self) -> &DeviceInfo {
+        &self.info
+    }
+
+    fn get_vcp_feature(&mut self, code: u8) -> Result<VcpFeature, MonitorError> {
+        let value = self
+            .display
+            .handle
+            .get_vcp_feature(code)
+            .map_err(|e| ddc_error(&self.device, &e))?;
+
+        Ok(VcpFeature {
+            current: value.value(),
+            maximum: value.maximum(),
+        })
+    }
+
+    fn set_vcp_feature(&mut self, code: u8, value: u16) -> Result<(), MonitorError> {
+        self.display
+            .handle
+            .set_vcp_feature(code, value)
+            .map_err(|e| ddc_error(&self.device, &e))
+    }
+
+    fn capabilities_string(&mut self) -> Result<Vec<u8>, MonitorError> {
+        self.display
+            .handle
+            .capabilities_string()
+            .map_err(|e| ddc_error(&self.device, &e))
+    }
+}
+
+/// Maps a ddc-hi error on `device` to a `MonitorError` class, going by the
+/// I/O and DDC/CI errors in its chain.
+pub(crate) fn ddc_error(device: &str, e: &anyhow::Error) -> MonitorError {
+    e.chain()
+        .find_map(|cause| classify(device, cause))
+        .unwrap_or_else(|| MonitorError::DdcError(format!("{:#}", e)))
+}
+
+fn classify(device: &str, cause: &(dyn Error + 'static)) -> Option<MonitorError> {
+    if let Some(e) = cause.downcast_ref::<io::Error>() {
+        return io_error(device, e);
+    }
+    #[cfg(target_os = "linux")]
+    if let Some(e) = cause.downcast_ref::<ddc_i2c::Error<io::Error>>() {
+        return match e {
+            ddc_i2c::Error::I2c(e) => io_error(device, e),
+            ddc_i2c::Error::Ddc(code) => ddc_error_code(code),
+        };
+    }
+    cause.downcast_ref::<ErrorCode>().and_then(ddc_error_code)
+}
+
+/// Classifies an error from the I2C device itself.
+pub(crate) fn io_error(device: &str, e: &io::Error) -> Option<MonitorError> {
+    #[cfg(target_os = "linux")]
+    match e.raw_os_error() {
+        Some(libc::ETIMEDOUT) => return Some(MonitorError::Timeout),
+        // Nothing acknowledged at the DDC/CI address.
+        Some(libc::EREMOTEIO | libc::ENXIO) => return Some(MonitorError::DdcUnsupported),
+        _ => {}
+    }
+    match e.kind() {
+        io::ErrorKind::PermissionDenied => Some(MonitorError::PermissionDenied {
+            device: device.to_string(),
+        }),
+        io::ErrorKind::TimedOut => Some(MonitorError::Timeout),
+        _ => None,
+    }
+}
+
+fn ddc_error_code(code: &ErrorCode) -> Option<MonitorError> {
+    match code {
+        ErrorCode::InvalidChecksum => Some(MonitorError::ChecksumMismatch),
+        // How the monitor says it doesn't have the VCP code.
+        ErrorCode::Invalid(message) if message == "Unsupported VCP code" => {
+            Some(MonitorError::NotSupported)
+        }
+        _ => None,
+    }
+}
+
+/// The `/dev/i2c-N` node behind a ddc-hi id, which for I2C devices is the
+/// node's device number.
+#[cfg(target_os = "linux")]
+fn device_path(id: &str) -> String {
+    match id.parse::<libc::dev_t>() {
+        Ok(rdev) => format!("/dev/i2c-{}", libc::minor(rdev)),
+        Err(_) => id.to_string(),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn device_path(id: &str) -> String {
+    id.to_string()
+}
+
+/// FNV-1a over the 128-byte base block, so the hash doesn't depend on
+/// whether a backend also returned extension blocks.
+fn edid_hash(edid: &[u8]) -> u32 {
+    edid.iter().take(128).fold(0x811c9dc5, |hash, &byte| {
+        (hash ^ byte as u32).wrapping_mul(0x01000193)
+    })
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    const DEVICE: &str = "/dev/i2c-4";
+
+    fn i2c(errno: i32) -> anyhow::Error {
+        let e = ddc_i2c::Error::I2c(io::Error::from_raw_os_error(errno));
+        anyhow::Error::new(e).context("Failed to read VCP feature")
+    }
+
+    fn ddc(code: ErrorCode) -> anyhow::Error {
+        ddc_i2c::Error::<io::Error>::Ddc(code).into()
+    }
+
+    #[test]
+    fn permission_errors_name_the_device() {
+        let e = io::Error::from_raw_os_error(libc::EACCES).into();
+        match ddc_error(DEVICE, &e) {
+            MonitorError::PermissionDenied { device } => assert_eq!(device, DEVICE),
+            other => panic!("{:?}", other),
+        }
+        assert!(matches!(
+            ddc_error(DEVICE, &i2c(libc::EPERM)),
+            MonitorError::PermissionDenied { .. }
+        ));
+    }
+
+    #[test]
+    fn timeouts() {
+        assert!(matches!(
+            ddc_error(DEVICE, &i2c(libc::ETIMEDOUT)),
+            MonitorError::Timeout
+        ));
+    }
+
+    #[test]
+    fn unanswered_writes_mean_no_ddc_ci() {
+        for errno in [libc::EREMOTEIO, libc::ENXIO] {
+            assert!(matches!(
+                ddc_error(DEVICE, &i2c(errno)),
+                MonitorError::DdcUnsupported
+            ));
+        }
+    }
+
+    #[test]
+    fn checksum_mismatches() {
+        assert!(matches!(
+            ddc_error(DEVICE, &ddc(ErrorCode::InvalidChecksum)),
+            MonitorError::ChecksumMismatch
+        ));
+    }
+
+    #[test]
+    fn unsupported_vcp_codes() {
+        let e = ddc(ErrorCode::Invalid("Unsupported VCP code".into()));
+        assert!(matches!(ddc_error(DEVICE, &e), MonitorError::NotSupported));
+    }
+
+    #[test]
+    fn other_errors_keep_their_message() {
+        let e = ddc(ErrorCode::InvalidOpcode).context("Failed to set VCP feature");
+        match ddc_error(DEVICE, &e) {
+            MonitorError::DdcError(message) => {
+                assert!(message.starts_with("Failed to set VCP feature: "))
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn device_paths_come_from_the_device_number() {
+        let rdev = libc::makedev(89, 4);
+        assert_eq!(device_path(&rdev.to_string()), DEVICE);
+        assert_eq!(device_path("not a number"), "not a number");
+    }
+}
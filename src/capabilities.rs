@@ -0,0 +1,31 @@
+use std::collections::BTreeMap;
+
+use crate::MonitorError;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    vcp_features: BTreeMap<u8, Vec<u8>>,
+}
+
+impl Capabilities {
+    pub fn parse(raw: &[u8]) -> Result<Self, MonitorError> {
+        let caps = mccs_caps::parse_capabilities(raw)
+            .map_err(|e| MonitorError::DdcError(format!("Invalid capabilities string: {}", e)))?;
+
+        let vcp_features = caps
+            .vcp_features
+            .iter()
+            .map(|(&code, descriptor)| (code, descriptor.values.keys().copied().collect()))
+            .collect();
+
+        Ok(Capabilities { vcp_features })
+    }
+
+    pub fn supports_vcp(&self, code: u8) -> bool {
+        self.vcp_features.contains_key(&code)
+    }
+
+    pub fn vcp_values(&self, code: u8) -> Option<&[u8]> {
+        self.vcp_features.get(&code).map(|v| v.as_slice())
+    }
+}
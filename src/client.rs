@@ -0,0 +1,278 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use log::warn;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::daemon::{
+    socket_path, ApplyParams, RpcError, INVALID_PARAMS, MONITOR_ERROR, NO_MONITOR,
+};
+use crate::{
+    ApplyOptions, CycleDirection, DaemonError, MonitorError, MonitorSnapshot, ProfileReport,
+    Setting,
+};
+
+/// A connection to a running [`Daemon`](crate::Daemon). Monitors and inputs
+/// are named by selectors, as on the command line: `"1"`, `"id:DEL-A0F4"`,
+/// `"hdmi1"`, an input alias. Requests from all clients are carried out one
+/// at a time.
+pub struct DaemonClient {
+    path: PathBuf,
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+    next_id: u64,
+    timeout: Duration,
+}
+
+/// How long a request may take before `DaemonError::Timeout` unless changed
+/// with `set_timeout`, long enough for a profile that wakes and verifies
+/// several monitors.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Deserialize)]
+struct Response {
+    #[serde(default)]
+    id: Value,
+    #[serde(default)]
+    result: Option<Value>,
+    #[serde(default)]
+    error: Option<RpcError>,
+}
+
+impl DaemonClient {
+    /// Connects to the daemon at `socket_path`, or `None` if it isn't
+    /// running.
+    pub fn try_connect() -> Option<Self> {
+        let path = socket_path()?;
+        match Self::connect(&path) {
+            Ok(client) => Some(client),
+            Err(DaemonError::Io { source, .. })
+                if matches!(
+                    source.kind(),
+                    io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused
+                ) =>
+            {
+                None
+            }
+            Err(e) => {
+                warn!("Not using the daemon: {}", e);
+                None
+            }
+        }
+    }
+
+    pub fn connect(path: &Path) -> Result<Self, DaemonError> {
+        let io_error = |source| DaemonError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        let writer = UnixStream::connect(path).map_err(io_error)?;
+        let reader = BufReader::new(writer.try_clone().map_err(io_error)?);
+        let mut client = DaemonClient {
+            path: path.to_path_buf(),
+            reader,
+            writer,
+            next_id: 0,
+            timeout: DEFAULT_TIMEOUT,
+        };
+        client.set_timeout(DEFAULT_TIMEOUT)?;
+        Ok(client)
+    }
+
+    /// Gives up on requests the daemon doesn't answer within `timeout`, e.g.
+    /// because it is stuck talking to a monitor. A reply that arrives later is
+    /// discarded, so the client can still be used.
+    pub fn set_timeout(&mut self, timeout: Duration) -> Result<(), DaemonError> {
+        let io_error = |source| DaemonError::Io {
+            path: self.path.clone(),
+            source,
+        };
+        self.writer
+            .set_read_timeout(Some(timeout))
+            .map_err(io_error)?;
+        self.writer
+            .set_write_timeout(Some(timeout))
+            .map_err(io_error)?;
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    /// The cached state of every monitor, without talking to them.
+    pub fn list(&mut self) -> Result<Vec<MonitorSnapshot>, DaemonError> {
+        self.request("list", Value::Null)
+    }
+
+    /// Enumerates and reads every monitor again, e.g. after plugging one in.
+    pub fn refresh(&mut self) -> Result<Vec<MonitorSnapshot>, DaemonError> {
+        self.request("refresh", Value::Null)
+    }
+
+    /// The cached state of one monitor, without talking to it.
+    pub fn find(&mut self, monitor: &str) -> Result<MonitorSnapshot, DaemonError> {
+        self.request("find", json!({ "monitor": monitor }))
+    }
+
+    /// Reads one monitor again.
+    pub fn get(&mut self, monitor: &str) -> Result<MonitorSnapshot, DaemonError> {
+        self.request("get", json!({ "monitor": monitor }))
+    }
+
+    /// Like `switch_input` with the monitor's own switch options, waking it
+    /// first if `wake`. Returns the monitor with its new input.
+    pub fn set_input(
+        &mut self,
+        monitor: &str,
+        input: &str,
+        wake: bool,
+    ) -> Result<MonitorSnapshot, DaemonError> {
+        self.request(
+            "set_input",
+            json!({ "monitor": monitor, "input": input, "wake": wake }),
+        )
+    }
+
+    /// Like `toggle_input`.
+    pub fn toggle_input(
+        &mut self,
+        monitor: &str,
+        inputs: Option<(&str, &str)>,
+        wake: bool,
+    ) -> Result<MonitorSnapshot, DaemonError> {
+        let inputs = inputs.map(|(first, second)| [first, second]);
+        self.request(
+            "toggle_input",
+            json!({ "monitor": monitor, "inputs": inputs, "wake": wake }),
+        )
+    }
+
+    /// Like `cycle_favorites`.
+    pub fn cycle_input(
+        &mut self,
+        monitor: &str,
+        direction: CycleDirection,
+        wake: bool,
+    ) -> Result<MonitorSnapshot, DaemonError> {
+        let backward = direction == CycleDirection::Backward;
+        self.request(
+            "cycle_input",
+            json!({ "monitor": monitor, "backward": backward, "wake": wake }),
+        )
+    }
+
+    pub fn set(&mut self, monitor: &str, setting: Setting) -> Result<MonitorSnapshot, DaemonError> {
+        let mut params = serde_json::to_value(setting).expect("settings serialise to JSON");
+        params["monitor"] = json!(monitor);
+        self.request("set", params)
+    }
+
+    /// Like `apply_profile_with`, with a profile from the daemon's config.
+    pub fn apply_profile(
+        &mut self,
+        name: &str,
+        options: &ApplyOptions,
+    ) -> Result<ProfileReport, DaemonError> {
+        let mut params = apply_params(options);
+        params["name"] = json!(name);
+        self.request("apply_profile", params)
+    }
+
+    /// Like `cycle_profiles`.
+    pub fn cycle_profile(
+        &mut self,
+        direction: CycleDirection,
+        options: &ApplyOptions,
+    ) -> Result<ProfileReport, DaemonError> {
+        let mut params = apply_params(options);
+        params["backward"] = json!(direction == CycleDirection::Backward);
+        self.request("cycle_profile", params)
+    }
+
+    /// Sends a request and waits for its result.
+    pub fn call(&mut self, method: &str, params: Value) -> Result<Value, DaemonError> {
+        self.next_id += 1;
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": self.next_id,
+            "method": method,
+            "params": params,
+        });
+        let timeout = self.timeout;
+        let io_error = |source: io::Error| match source.kind() {
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => DaemonError::Timeout(timeout),
+            _ => DaemonError::Io {
+                path: self.path.clone(),
+                source,
+            },
+        };
+        writeln!(self.writer, "{}", request).map_err(io_error)?;
+        let response = loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line).map_err(io_error)? == 0 {
+                return Err(DaemonError::Protocol("the daemon hung up".to_string()));
+            }
+            let response: Response =
+                serde_json::from_str(&line).map_err(|e| DaemonError::Protocol(e.to_string()))?;
+            // Replies to requests that timed out earlier.
+            if response.id.as_u64().is_some_and(|id| id < self.next_id) {
+                continue;
+            }
+            break response;
+        };
+        if response.id != json!(self.next_id) {
+            return Err(DaemonError::Protocol(format!(
+                "expected the reply to request {}, got {}",
+                self.next_id, response.id
+            )));
+        }
+        match (response.result, response.error) {
+            (_, Some(error)) => Err(error.into()),
+            (Some(result), None) => Ok(result),
+            (None, None) => Err(DaemonError::Protocol("no result".to_string())),
+        }
+    }
+
+    fn request<T: DeserializeOwned>(
+        &mut self,
+        method: &str,
+        params: Value,
+    ) -> Result<T, DaemonError> {
+        let result = self.call(method, params)?;
+        serde_json::from_value(result).map_err(|e| DaemonError::Protocol(e.to_string()))
+    }
+}
+
+impl From<RpcError> for DaemonError {
+    fn from(error: RpcError) -> Self {
+        match error.code {
+            MONITOR_ERROR => match error.data.map(serde_json::from_value) {
+                Some(Ok(e)) => DaemonError::Monitor(e),
+                _ => DaemonError::Protocol(format!("unknown monitor error: {}", error.message)),
+            },
+            NO_MONITOR => DaemonError::NoMonitor(error.message),
+            INVALID_PARAMS => DaemonError::InvalidParams(error.message),
+            code => DaemonError::Remote {
+                code,
+                message: error.message,
+            },
+        }
+    }
+}
+
+/// For callers that report everything as a `MonitorError`.
+impl From<DaemonError> for MonitorError {
+    fn from(e: DaemonError) -> Self {
+        match e {
+            DaemonError::Monitor(e) => e,
+            DaemonError::NoMonitor(_) => MonitorError::NotFound,
+            e => MonitorError::DdcError(e.to_string()),
+        }
+    }
+}
+
+fn apply_params(options: &ApplyOptions) -> Value {
+    serde_json::to_value(ApplyParams::from(options)).expect("options serialise to JSON")
+}
@@ -1,49 +1,288 @@
+use log::warn;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::collections::HashMap;
-use std::fs;
-use std::path::PathBuf;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+use crate::{Monitor, QuirkEntry, VerifyPolicy};
+
+/// Version of the config file layout written by this build. Older files are
+/// run through `MIGRATIONS` on load and get the current version on the next
+/// save; newer ones are refused.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Environment variable overriding the config file location.
+pub const CONFIG_PATH_ENV: &str = "MONITOR_SWITCH_CONFIG";
+
+static PATH_OVERRIDE: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// What this process last wrote to each config file, so `ConfigWatcher`
+/// can tell its own saves from changes made elsewhere.
+static WRITTEN: Mutex<Vec<(PathBuf, String)>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    #[serde(default)]
+    pub version: u32,
     #[serde(default)]
     pub monitors: HashMap<String, MonitorConfig>,
     #[serde(default)]
     pub favorites: Vec<Favorite>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub quirks: Vec<QuirkEntry>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<Profile>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            version: CONFIG_VERSION,
+            monitors: HashMap::new(),
+            favorites: Vec::new(),
+            quirks: Vec::new(),
+            profiles: Vec::new(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct MonitorConfig {
+    /// A name for the monitor itself, usable as a `MonitorSelector`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
     #[serde(default)]
     pub input_aliases: HashMap<u16, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verify: Option<VerifyPolicy>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct Favorite {
     pub monitor_id: String,
     pub input_value: u16,
+    /// Makes this a toggle between `input_value` and this input, see
+    /// `toggle_input`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub toggle_with: Option<u16>,
+}
+
+/// A named set of inputs for several monitors, switched together with
+/// `apply_profile`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Profile {
+    pub name: String,
+    pub monitors: Vec<ProfileTarget>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProfileTarget {
+    pub monitor_id: String,
+    pub input_value: u16,
+    /// Brightness in percent to set along with the input.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub brightness: Option<u8>,
+}
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Could not determine config directory")]
+    NoConfigDir,
+    #[error("Could not access {}: {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+    #[error("{} line {line}, column {column}: {message}", path.display())]
+    Parse {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    #[error("{} was written by a newer version (config version {version})", path.display())]
+    UnsupportedVersion { path: PathBuf, version: u32 },
 }
 
 impl Config {
+    /// Where the config lives, in order of precedence: the path passed to
+    /// `set_config_path`, `$MONITOR_SWITCH_CONFIG`,
+    /// `$XDG_CONFIG_HOME/monitor-switch/config.json`, then
+    /// `~/.config/monitor-switch/config.json`.
     pub fn config_path() -> Option<PathBuf> {
-        dirs::home_dir().map(|p| p.join(".config").join("monitor-switch").join("config.json"))
+        if let Some(path) = PATH_OVERRIDE
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+        {
+            return Some(path);
+        }
+        if let Some(path) = env::var_os(CONFIG_PATH_ENV).filter(|p| !p.is_empty()) {
+            return Some(PathBuf::from(path));
+        }
+        // The XDG spec says relative paths are invalid and should be ignored.
+        let config_home = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+            .or_else(|| dirs::home_dir().map(|home| home.join(".config")))?;
+        Some(config_home.join("monitor-switch").join("config.json"))
     }
 
-    pub fn load() -> Self {
-        Self::config_path()
-            .and_then(|path| fs::read_to_string(&path).ok())
-            .and_then(|content| serde_json::from_str(&content).ok())
-            .unwrap_or_default()
+    /// Makes `config_path`, and so `load`, `save` and `update`, use `path`
+    /// for the rest of the process. `None` goes back to the environment.
+    pub fn set_config_path(path: Option<PathBuf>) {
+        *PATH_OVERRIDE.write().unwrap_or_else(|e| e.into_inner()) = path;
     }
 
-    pub fn save(&self) -> Result<(), String> {
-        let path = Self::config_path().ok_or("Could not determine config directory")?;
-        
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    /// Loads the user's config. A missing file gives the default config; an
+    /// unreadable or invalid one is an error, so callers don't end up
+    /// saving defaults over it.
+    pub fn load() -> Result<Self, ConfigError> {
+        let path = Self::config_path().ok_or(ConfigError::NoConfigDir)?;
+        Self::load_from(&path)
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self, ConfigError> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(source) => {
+                return Err(ConfigError::Io {
+                    path: path.to_path_buf(),
+                    source,
+                })
+            }
+        };
+        Self::parse(path, &content)
+    }
+
+    fn parse(path: &Path, content: &str) -> Result<Self, ConfigError> {
+        let mut value: Value = serde_json::from_str(content).map_err(|e| parse_error(path, e))?;
+        let version = file_version(&value);
+        if version > CONFIG_VERSION {
+            return Err(ConfigError::UnsupportedVersion {
+                path: path.to_path_buf(),
+                version,
+            });
+        }
+        if version == CONFIG_VERSION {
+            // Parse the text again so shape errors keep their location.
+            return serde_json::from_str(content).map_err(|e| parse_error(path, e));
         }
-        
-        let content = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
-        fs::write(&path, content).map_err(|e| e.to_string())
+
+        if let Some(config) = value.as_object_mut() {
+            for from in version..CONFIG_VERSION {
+                MIGRATIONS[from as usize](config);
+                config.insert("version".to_string(), Value::from(from + 1));
+            }
+        }
+        serde_json::from_value(value).map_err(|e| parse_error(path, e))
+    }
+
+    /// Overwrites the config file with this config. Prefer [`Config::update`]
+    /// for changes, which doesn't lose edits made by other processes.
+    pub fn save(&self) -> Result<(), ConfigError> {
+        let path = Self::config_path().ok_or(ConfigError::NoConfigDir)?;
+        self.save_to(&path)
+    }
+
+    /// Writes the config to `path` atomically while holding the config lock.
+    /// If the file there can't be parsed, it is first copied aside (see
+    /// [`Config::backup_path`]) so hand edits with a typo aren't lost.
+    pub fn save_to(&self, path: &Path) -> Result<(), ConfigError> {
+        let _lock = lock(path)?;
+        self.write_locked(path)
+    }
+
+    /// Applies `f` to the config currently on disk and saves the result, all
+    /// under the config lock, so concurrent instances each keep the other's
+    /// changes. Returns the saved config to replace any in-memory copy. A file
+    /// that can't be parsed is left alone and its error returned, so a change
+    /// made while it's broken doesn't replace it with defaults.
+    pub fn update<F: FnOnce(&mut Config)>(f: F) -> Result<Config, ConfigError> {
+        let path = Self::config_path().ok_or(ConfigError::NoConfigDir)?;
+        Self::update_at(&path, f)
+    }
+
+    pub fn update_at<F: FnOnce(&mut Config)>(path: &Path, f: F) -> Result<Config, ConfigError> {
+        let _lock = lock(path)?;
+        let mut config = Self::load_from(path)?;
+        f(&mut config);
+        config.write_locked(path)?;
+        Ok(config)
+    }
+
+    fn write_locked(&self, path: &Path) -> Result<(), ConfigError> {
+        let io_error = |source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+
+        if let Ok(existing) = fs::read_to_string(path) {
+            match Self::parse(path, &existing) {
+                Err(ConfigError::Parse { .. }) => {
+                    let backup = Self::backup_path(path);
+                    warn!("Backing up invalid config to {}", backup.display());
+                    fs::copy(path, &backup).map_err(io_error)?;
+                }
+                Err(e) => return Err(e),
+                Ok(_) => {
+                    // Keep the file as the older version wrote it, once.
+                    let version = serde_json::from_str(&existing).map_or(0, |v| file_version(&v));
+                    let backup = Self::version_backup_path(path, version);
+                    if version < CONFIG_VERSION && !backup.exists() {
+                        fs::copy(path, &backup).map_err(io_error)?;
+                    }
+                }
+            }
+        }
+
+        // Write a sibling file and rename it over the config, so readers
+        // never see a partially written file.
+        let current = Config {
+            version: CONFIG_VERSION,
+            ..self.clone()
+        };
+        let content = serde_json::to_string_pretty(&current).expect("config serialises to JSON");
+        let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+        tmp_name.push(format!(".tmp-{}", std::process::id()));
+        let tmp_path = path.with_file_name(tmp_name);
+
+        let result = File::create(&tmp_path)
+            .and_then(|mut file| {
+                file.write_all(content.as_bytes())?;
+                file.sync_all()
+            })
+            .and_then(|()| {
+                record_write(path, &content);
+                fs::rename(&tmp_path, path)
+            });
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+        result.map_err(io_error)
+    }
+
+    /// Where `save_to` keeps an unparseable config: next to it, with a
+    /// timestamp so earlier backups are kept.
+    pub fn backup_path(path: &Path) -> PathBuf {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".broken-{}", timestamp));
+        path.with_file_name(name)
+    }
+
+    /// Where the first save after a migration keeps the original file.
+    pub fn version_backup_path(path: &Path, version: u32) -> PathBuf {
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".v{}.bak", version));
+        path.with_file_name(name)
     }
 
     pub fn get_alias(&self, monitor_id: &str, input_value: u16) -> Option<&str> {
@@ -67,26 +306,203 @@ impl Config {
         }
     }
 
+    pub fn get_monitor_alias(&self, monitor_id: &str) -> Option<&str> {
+        self.monitors
+            .get(monitor_id)
+            .and_then(|m| m.alias.as_deref())
+    }
+
+    /// Names the monitor itself; `None` removes the name.
+    pub fn set_monitor_alias(&mut self, monitor_id: &str, alias: Option<String>) {
+        match alias {
+            Some(alias) => {
+                self.monitors
+                    .entry(monitor_id.to_string())
+                    .or_default()
+                    .alias = Some(alias);
+            }
+            None => {
+                if let Some(monitor) = self.monitors.get_mut(monitor_id) {
+                    monitor.alias = None;
+                }
+            }
+        }
+    }
+
     pub fn is_favorite(&self, monitor_id: &str, input_value: u16) -> bool {
-        self.favorites.iter().any(|f| f.monitor_id == monitor_id && f.input_value == input_value)
+        self.is_toggle_favorite(monitor_id, input_value, None)
     }
 
     pub fn add_favorite(&mut self, monitor_id: &str, input_value: u16) {
-        let fav = Favorite {
+        self.add_toggle_favorite(monitor_id, input_value, None);
+    }
+
+    pub fn remove_favorite(&mut self, monitor_id: &str, input_value: u16) {
+        self.remove_toggle_favorite(monitor_id, input_value, None);
+    }
+
+    /// Like `is_favorite`, for a favorite that toggles between `input_value`
+    /// and `toggle_with`. `None` means a plain favorite.
+    pub fn is_toggle_favorite(
+        &self,
+        monitor_id: &str,
+        input_value: u16,
+        toggle_with: Option<u16>,
+    ) -> bool {
+        self.favorites.contains(&Favorite {
             monitor_id: monitor_id.to_string(),
             input_value,
-        };
-        if !self.favorites.contains(&fav) {
-            self.favorites.push(fav);
+            toggle_with,
+        })
+    }
+
+    pub fn add_toggle_favorite(
+        &mut self,
+        monitor_id: &str,
+        input_value: u16,
+        toggle_with: Option<u16>,
+    ) {
+        if !self.is_toggle_favorite(monitor_id, input_value, toggle_with) {
+            self.favorites.push(Favorite {
+                monitor_id: monitor_id.to_string(),
+                input_value,
+                toggle_with,
+            });
         }
     }
 
-    pub fn remove_favorite(&mut self, monitor_id: &str, input_value: u16) {
-        self.favorites.retain(|f| !(f.monitor_id == monitor_id && f.input_value == input_value));
+    pub fn remove_toggle_favorite(
+        &mut self,
+        monitor_id: &str,
+        input_value: u16,
+        toggle_with: Option<u16>,
+    ) {
+        self.favorites.retain(|f| {
+            !(f.monitor_id == monitor_id
+                && f.input_value == input_value
+                && f.toggle_with == toggle_with)
+        });
     }
 
     pub fn get_favorites(&self) -> &[Favorite] {
         &self.favorites
     }
+
+    pub fn get_profile(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.name == name)
+    }
+
+    /// Adds the profile, or replaces the one with the same name in place.
+    pub fn set_profile(&mut self, profile: Profile) {
+        match self.profiles.iter_mut().find(|p| p.name == profile.name) {
+            Some(existing) => *existing = profile,
+            None => self.profiles.push(profile),
+        }
+    }
+
+    pub fn remove_profile(&mut self, name: &str) {
+        self.profiles.retain(|p| p.name != name);
+    }
+
+    /// Re-keys settings stored under the ids older versions derived from
+    /// enumeration order. Returns whether anything changed, so callers know
+    /// to save.
+    pub fn migrate_monitor_ids(&mut self, monitors: &[Monitor]) -> bool {
+        let mut changed = false;
+        for monitor in monitors {
+            let (old_id, new_id) = (monitor.legacy_id(), monitor.id());
+            if old_id == new_id {
+                continue;
+            }
+
+            if let Some(settings) = self.monitors.remove(old_id) {
+                self.monitors.entry(new_id.clone()).or_insert(settings);
+                changed = true;
+            }
+            for favorite in self.favorites.iter_mut().filter(|f| f.monitor_id == old_id) {
+                favorite.monitor_id = new_id.clone();
+                changed = true;
+            }
+            let targets = self.profiles.iter_mut().flat_map(|p| p.monitors.iter_mut());
+            for target in targets.filter(|t| t.monitor_id == old_id) {
+                target.monitor_id = new_id.clone();
+                changed = true;
+            }
+        }
+
+        if changed {
+            let mut seen = std::collections::HashSet::new();
+            self.favorites.retain(|f| seen.insert(f.clone()));
+        }
+        changed
+    }
 }
 
+/// Upgrades the config from each historical layout to the next, indexed by
+/// the version it upgrades from.
+const MIGRATIONS: [fn(&mut Map<String, Value>); CONFIG_VERSION as usize] = [migrate_v0_to_v1];
+
+/// Only a version bump: 0.2.x files have no version field but already use
+/// the v1 layout of `monitors` and `favorites`, so nothing is rewritten. Their
+/// legacy monitor ids can only be re-keyed once the monitors are seen, which
+/// `Config::migrate_monitor_ids` does.
+fn migrate_v0_to_v1(_config: &mut Map<String, Value>) {}
+
+/// Files without a version field predate versioning (0.2.x and earlier).
+fn file_version(value: &Value) -> u32 {
+    value
+        .get("version")
+        .and_then(Value::as_u64)
+        .map_or(0, |v| v.try_into().unwrap_or(u32::MAX))
+}
+
+fn record_write(path: &Path, content: &str) {
+    let mut written = WRITTEN.lock().unwrap_or_else(|e| e.into_inner());
+    written.retain(|(p, _)| p != path);
+    written.push((path.to_path_buf(), content.to_string()));
+}
+
+pub(crate) fn written_by_us(path: &Path, content: &[u8]) -> bool {
+    let written = WRITTEN.lock().unwrap_or_else(|e| e.into_inner());
+    written
+        .iter()
+        .any(|(p, c)| p == path && c.as_bytes() == content)
+}
+
+/// Takes the advisory lock on `<config>.lock`, released when the returned
+/// file is dropped. A separate file is used because the config itself is
+/// replaced on every save.
+fn lock(path: &Path) -> Result<File, ConfigError> {
+    let mut lock_name = path.file_name().unwrap_or_default().to_os_string();
+    lock_name.push(".lock");
+    let lock_path = path.with_file_name(lock_name);
+
+    let io_error = |source| ConfigError::Io {
+        path: lock_path.clone(),
+        source,
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(io_error)?;
+    }
+    let file = File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .map_err(io_error)?;
+    file.lock().map_err(io_error)?;
+    Ok(file)
+}
+
+fn parse_error(path: &Path, e: serde_json::Error) -> ConfigError {
+    let (line, column) = (e.line(), e.column());
+    let message = e.to_string();
+    let suffix = format!(" at line {} column {}", line, column);
+    let message = message.strip_suffix(&suffix).unwrap_or(&message);
+    ConfigError::Parse {
+        path: path.to_path_buf(),
+        line,
+        column,
+        message: message.to_string(),
+    }
+}
@@ -0,0 +1,104 @@
+use crate::{
+    apply_profile_with, switch_input, ApplyOptions, Config, InputHistory, InputSource, Monitor,
+    MonitorError, Profile, ProfileReport, SwitchOptions,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CycleDirection {
+    #[default]
+    Forward,
+    Backward,
+}
+
+/// Switches `monitor` to the favorite input after its current one, in the
+/// order of `Config::get_favorites`, wrapping around at the end. Both inputs
+/// of a toggle favorite count. A monitor on an input that isn't a favorite
+/// goes to the first favorite, or the last one going backward. With `wake` a
+/// monitor in standby is woken first; otherwise its `switch_options` decide.
+pub fn cycle_favorites(
+    monitor: &mut Monitor,
+    config: &Config,
+    direction: CycleDirection,
+    wake: bool,
+    history: &mut InputHistory,
+) -> Result<InputSource, MonitorError> {
+    let id = monitor.id();
+    let mut inputs: Vec<InputSource> = Vec::new();
+    let values = config
+        .get_favorites()
+        .iter()
+        .filter(|f| f.monitor_id == id)
+        .flat_map(|f| [Some(f.input_value), f.toggle_with])
+        .flatten();
+    for input in values.map(InputSource::from_vcp_value) {
+        if !inputs.contains(&input) {
+            inputs.push(input);
+        }
+    }
+    if inputs.is_empty() {
+        return Err(MonitorError::NoFavorites);
+    }
+
+    let current = monitor.get_current_input().ok();
+    let position = inputs.iter().position(|&input| Some(input) == current);
+    let target = inputs[step(inputs.len(), position, direction)];
+
+    let options = SwitchOptions {
+        ensure_powered_on: wake || monitor.switch_options().ensure_powered_on,
+        ..monitor.switch_options().clone()
+    };
+    switch_input(monitor, target, &options, history)?;
+    Ok(target)
+}
+
+/// Applies the profile after the active one, wrapping around at the end.
+/// Without an active profile it starts at the first, or the last one going
+/// backward. `None` if the config has no profiles.
+pub fn cycle_profiles(
+    monitors: &mut [Monitor],
+    config: &Config,
+    direction: CycleDirection,
+    options: &ApplyOptions,
+) -> Option<ProfileReport> {
+    if config.profiles.is_empty() {
+        return None;
+    }
+    let active = active_profile(monitors, &config.profiles);
+    let next = &config.profiles[step(config.profiles.len(), active, direction)];
+    Some(apply_profile_with(monitors, next, options))
+}
+
+/// The first of `profiles` whose connected monitors are all on the profile's
+/// inputs. Profiles without any connected monitor never count as active.
+pub fn active_profile(monitors: &mut [Monitor], profiles: &[Profile]) -> Option<usize> {
+    let mut current: Vec<(String, Option<InputSource>)> = Vec::new();
+    profiles.iter().position(|profile| {
+        let mut connected = false;
+        let matches = profile.monitors.iter().all(|target| {
+            let Some(monitor) = monitors.iter_mut().find(|m| m.id() == target.monitor_id) else {
+                return true;
+            };
+            connected = true;
+            // Read each monitor once, however many profiles mention it.
+            let input = match current.iter().find(|(id, _)| *id == target.monitor_id) {
+                Some((_, input)) => *input,
+                None => {
+                    let input = monitor.get_current_input().ok();
+                    current.push((target.monitor_id.clone(), input));
+                    input
+                }
+            };
+            input == Some(InputSource::from_vcp_value(target.input_value))
+        });
+        connected && matches
+    })
+}
+
+fn step(len: usize, position: Option<usize>, direction: CycleDirection) -> usize {
+    match (position, direction) {
+        (Some(i), CycleDirection::Forward) => (i + 1) % len,
+        (Some(i), CycleDirection::Backward) => (i + len - 1) % len,
+        (None, CycleDirection::Forward) => 0,
+        (None, CycleDirection::Backward) => len - 1,
+    }
+}
@@ -0,0 +1,655 @@
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::Duration;
+
+use log::{debug, info, warn};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{json, Value};
+use thiserror::Error;
+
+use crate::{
+    apply_profile_with, cycle_favorites, cycle_profiles, resolve_input, switch_input, toggle_input,
+    ApplyOptions, Backend, Config, ConfigWatcher, CycleDirection, InputHistory, InputSource,
+    Monitor, MonitorError, MonitorSelector, MonitorSnapshot, ProfileReport, SelectorError, Setting,
+    SwitchOptions, TargetOutcome,
+};
+
+/// Environment variable overriding where the daemon listens.
+pub const SOCKET_PATH_ENV: &str = "MONITOR_SWITCH_SOCKET";
+
+// JSON-RPC 2.0 error codes. Codes from -32000 down are ours.
+pub(crate) const PARSE_ERROR: i64 = -32700;
+pub(crate) const INVALID_REQUEST: i64 = -32600;
+pub(crate) const METHOD_NOT_FOUND: i64 = -32601;
+pub(crate) const INVALID_PARAMS: i64 = -32602;
+pub(crate) const INTERNAL_ERROR: i64 = -32603;
+/// `data` is the serialised `MonitorError`.
+pub(crate) const MONITOR_ERROR: i64 = -32000;
+/// A monitor selector matched no connected monitor.
+pub(crate) const NO_MONITOR: i64 = -32001;
+
+#[derive(Error, Debug)]
+pub enum DaemonError {
+    #[error("Could not determine where the daemon socket lives")]
+    NoSocketPath,
+    #[error("A daemon is already listening on {}", .0.display())]
+    AlreadyRunning(PathBuf),
+    #[error("Could not access {}: {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+    #[error("Invalid reply from the daemon: {0}")]
+    Protocol(String),
+    #[error("The daemon did not answer within {} seconds", .0.as_secs_f32())]
+    Timeout(Duration),
+    #[error(transparent)]
+    Monitor(#[from] MonitorError),
+    #[error("{0}")]
+    NoMonitor(String),
+    #[error("{0}")]
+    InvalidParams(String),
+    #[error("The daemon failed: {message} (code {code})")]
+    Remote { code: i64, message: String },
+}
+
+/// Where the daemon listens, in order of precedence: `$MONITOR_SWITCH_SOCKET`,
+/// `monitor-switch.sock` in `$XDG_RUNTIME_DIR`, then next to the config file.
+pub fn socket_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os(SOCKET_PATH_ENV).filter(|p| !p.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    dirs::runtime_dir()
+        .map(|dir| dir.join("monitor-switch.sock"))
+        .or_else(|| Config::config_path().map(|path| path.with_file_name("monitor-switch.sock")))
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DaemonOptions {
+    /// The config to use, reloaded whenever the file changes.
+    /// `Config::config_path` if `None`.
+    pub config_path: Option<PathBuf>,
+    /// Where to keep the input history. `InputHistory::path` if `None`.
+    pub history_path: Option<PathBuf>,
+}
+
+/// Owns the monitors on behalf of other processes. It enumerates once,
+/// caches what it read as [`MonitorSnapshot`]s and talks to one monitor at
+/// a time, so clients neither wait for enumeration nor fight over the bus.
+///
+/// Clients connect to a Unix socket and send JSON-RPC 2.0 requests, one per
+/// line; see [`DaemonClient`](crate::DaemonClient) for the methods.
+pub struct Daemon {
+    listener: UnixListener,
+    path: PathBuf,
+    jobs: Sender<Job>,
+}
+
+struct Job {
+    method: String,
+    params: Value,
+    reply: Sender<Result<Value, RpcError>>,
+}
+
+impl Daemon {
+    /// Listens on `path` and starts enumerating with `backend` in the
+    /// background; requests wait until that is done. A socket left behind by
+    /// a daemon that didn't shut down cleanly is replaced.
+    pub fn bind(
+        path: &Path,
+        backend: impl Backend + Send + 'static,
+        options: DaemonOptions,
+    ) -> Result<Self, DaemonError> {
+        let io_error = |source| DaemonError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        if UnixStream::connect(path).is_ok() {
+            return Err(DaemonError::AlreadyRunning(path.to_path_buf()));
+        }
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(io_error(e)),
+            _ => {}
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(io_error)?;
+        }
+        // Anyone who can connect can switch the monitors, so the socket must
+        // not be reachable by other users even briefly before a chmod.
+        let umask = unsafe { libc::umask(0o177) };
+        let listener = UnixListener::bind(path);
+        unsafe { libc::umask(umask) };
+        let listener = listener.map_err(io_error)?;
+
+        let (jobs, queue) = mpsc::channel::<Job>();
+        thread::Builder::new()
+            .name("monitor-daemon".into())
+            .spawn(move || {
+                let mut state = State::new(Box::new(backend), options);
+                for job in queue {
+                    debug!("{} {}", job.method, job.params);
+                    let result = state.handle(&job.method, job.params);
+                    let _ = job.reply.send(result);
+                }
+            })
+            .map_err(io_error)?;
+
+        Ok(Daemon {
+            listener,
+            path: path.to_path_buf(),
+            jobs,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Serves clients, each on its own thread, until the process exits.
+    pub fn serve(self) {
+        info!("Listening on {}", self.path.display());
+        for stream in self.listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    warn!("Could not accept a client: {}", e);
+                    // Out of file descriptors, most likely; give clients
+                    // time to go away.
+                    thread::sleep(Duration::from_millis(100));
+                    continue;
+                }
+            };
+            let jobs = self.jobs.clone();
+            let spawned = thread::Builder::new()
+                .name("daemon-client".into())
+                .spawn(move || serve_client(stream, jobs));
+            if let Err(e) = spawned {
+                warn!("Could not serve a client: {}", e);
+            }
+        }
+    }
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Everything the daemon knows, owned by the one thread that talks to the
+/// monitors.
+struct State {
+    backend: Box<dyn Backend + Send>,
+    config_path: Option<PathBuf>,
+    config: Config,
+    watcher: Option<ConfigWatcher>,
+    history_path: Option<PathBuf>,
+    history: InputHistory,
+    monitors: Vec<Monitor>,
+    snapshots: Vec<MonitorSnapshot>,
+}
+
+impl State {
+    fn new(backend: Box<dyn Backend + Send>, options: DaemonOptions) -> Self {
+        let config_path = options.config_path.or_else(Config::config_path);
+        let config = config_path.as_deref().map(load_config).unwrap_or_default();
+        let watcher = config_path.as_deref().and_then(|path| {
+            ConfigWatcher::watch(path)
+                .map_err(|e| warn!("Not watching the config: {}", e))
+                .ok()
+        });
+        let history_path = options.history_path.or_else(InputHistory::path);
+        let history = history_path
+            .as_deref()
+            .map(InputHistory::load_from)
+            .unwrap_or_default();
+
+        let mut state = State {
+            backend,
+            config_path,
+            config,
+            watcher,
+            history_path,
+            history,
+            monitors: Vec::new(),
+            snapshots: Vec::new(),
+        };
+        state.refresh();
+        state
+    }
+
+    fn handle(&mut self, method: &str, params: Value) -> Result<Value, RpcError> {
+        self.reload_config_if_changed();
+        match method {
+            "list" => reply(&self.snapshots),
+            "refresh" => {
+                self.refresh();
+                reply(&self.snapshots)
+            }
+            "find" => {
+                let params: MonitorParams = parse(params)?;
+                let index = self.find(&params.monitor.0, false)?;
+                reply(&self.snapshots[index])
+            }
+            "get" => {
+                let params: MonitorParams = parse(params)?;
+                let index = self.find(&params.monitor.0, true)?;
+                self.snapshots[index] = MonitorSnapshot::read(&mut self.monitors[index], index);
+                reply(&self.snapshots[index])
+            }
+            "set_input" => {
+                let params: InputParams = parse(params)?;
+                let index = self.find(&params.monitor.0, true)?;
+                let input = self.resolve_input(index, &params.input)?;
+                let monitor = &mut self.monitors[index];
+                let options = SwitchOptions {
+                    ensure_powered_on: params.wake || monitor.switch_options().ensure_powered_on,
+                    ..monitor.switch_options().clone()
+                };
+                let result = switch_input(monitor, input, &options, &mut self.history);
+                self.switched(index, result.map(|()| input))
+            }
+            "toggle_input" => {
+                let params: ToggleParams = parse(params)?;
+                let index = self.find(&params.monitor.0, true)?;
+                let pair = match &params.inputs {
+                    Some([first, second]) => Some((
+                        self.resolve_input(index, first)?,
+                        self.resolve_input(index, second)?,
+                    )),
+                    None => None,
+                };
+                let result = toggle_input(
+                    &mut self.monitors[index],
+                    pair,
+                    params.wake,
+                    &mut self.history,
+                );
+                self.switched(index, result)
+            }
+            "cycle_input" => {
+                let params: CycleParams = parse(params)?;
+                let index = self.find(&params.monitor.0, true)?;
+                let result = cycle_favorites(
+                    &mut self.monitors[index],
+                    &self.config,
+                    direction(params.backward),
+                    params.wake,
+                    &mut self.history,
+                );
+                self.switched(index, result)
+            }
+            "set" => {
+                let params: SettingParams = parse(params)?;
+                let index = self.find(&params.monitor.0, true)?;
+                params.setting.apply(&mut self.monitors[index])?;
+                self.snapshots[index].update(params.setting);
+                reply(&self.snapshots[index])
+            }
+            "apply_profile" => {
+                let params: ProfileParams = parse(params)?;
+                let profile = self
+                    .config
+                    .get_profile(&params.name)
+                    .cloned()
+                    .ok_or_else(|| {
+                        RpcError::new(
+                            INVALID_PARAMS,
+                            format!("No profile named \"{}\"", params.name),
+                        )
+                    })?;
+                let options = params.options.into();
+                let report = apply_profile_with(&mut self.monitors, &profile, &options);
+                self.profile_applied(report)
+            }
+            "cycle_profile" => {
+                let params: CycleProfileParams = parse(params)?;
+                let report = cycle_profiles(
+                    &mut self.monitors,
+                    &self.config,
+                    direction(params.backward),
+                    &params.options.into(),
+                )
+                .ok_or_else(|| {
+                    RpcError::new(INVALID_PARAMS, "There are no profiles to cycle through")
+                })?;
+                self.profile_applied(report)
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method \"{}\"", method),
+            )),
+        }
+    }
+
+    fn refresh(&mut self) {
+        self.monitors = Monitor::enumerate_with_backend(self.backend.as_ref(), &self.config);
+        if self.config.migrate_monitor_ids(&self.monitors) {
+            if let Some(path) = &self.config_path {
+                let monitors = &self.monitors;
+                match Config::update_at(path, |c| {
+                    c.migrate_monitor_ids(monitors);
+                }) {
+                    Ok(updated) => self.config = updated,
+                    Err(e) => warn!("Could not save the migrated config: {}", e),
+                }
+            }
+        }
+        self.snapshots = self
+            .monitors
+            .iter_mut()
+            .enumerate()
+            .map(|(index, monitor)| MonitorSnapshot::read(monitor, index))
+            .collect();
+        info!("Found {} monitor(s)", self.monitors.len());
+    }
+
+    fn reload_config_if_changed(&mut self) {
+        let changed = self
+            .watcher
+            .as_ref()
+            .is_some_and(|watcher| watcher.try_iter().count() > 0);
+        let Some(path) = self.config_path.as_deref().filter(|_| changed) else {
+            return;
+        };
+        info!("Reloading {}", path.display());
+        self.config = load_config(path);
+        for monitor in &mut self.monitors {
+            monitor.configure(&self.config);
+        }
+    }
+
+    /// Resolves a monitor selector. When nothing matches and `rescan` is
+    /// set, enumerates again first in case the monitor was just plugged in.
+    fn find(&mut self, selector: &str, rescan: bool) -> Result<usize, RpcError> {
+        let selector: MonitorSelector = selector.parse()?;
+        match selector.resolve(&self.monitors, &self.config) {
+            Err(SelectorError::NoMonitor(_)) if rescan => {
+                self.refresh();
+                Ok(selector.resolve(&self.monitors, &self.config)?)
+            }
+            result => Ok(result?),
+        }
+    }
+
+    fn resolve_input(&self, index: usize, selector: &Selector) -> Result<InputSource, RpcError> {
+        let id = &self.snapshots[index].id;
+        Ok(resolve_input(&self.config, id, &selector.0)?)
+    }
+
+    /// Saves the history, which may have changed even if the switch failed,
+    /// and caches the new input.
+    fn switched(
+        &mut self,
+        index: usize,
+        result: Result<InputSource, MonitorError>,
+    ) -> Result<Value, RpcError> {
+        if let Some(path) = &self.history_path {
+            if let Err(e) = self.history.save_to(path) {
+                warn!("Could not save the input history: {}", e);
+            }
+        }
+        self.snapshots[index].current_input = Some(result?);
+        reply(&self.snapshots[index])
+    }
+
+    fn profile_applied(&mut self, report: ProfileReport) -> Result<Value, RpcError> {
+        for target in &report.monitors {
+            let input = match target.outcome {
+                TargetOutcome::Switched | TargetOutcome::RollbackFailed(_) => Some(target.input),
+                TargetOutcome::RolledBack => target.previous_input,
+                _ => continue,
+            };
+            if let Some(snapshot) = self
+                .snapshots
+                .iter_mut()
+                .find(|s| s.id == target.monitor_id)
+            {
+                snapshot.current_input = input;
+            }
+        }
+        reply(&report)
+    }
+}
+
+/// A JSON-RPC error object.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+impl From<MonitorError> for RpcError {
+    fn from(e: MonitorError) -> Self {
+        RpcError {
+            code: MONITOR_ERROR,
+            message: e.to_string(),
+            data: Some(serde_json::to_value(&e).expect("errors serialise to JSON")),
+        }
+    }
+}
+
+impl From<SelectorError> for RpcError {
+    fn from(e: SelectorError) -> Self {
+        let code = match e {
+            SelectorError::NoMonitor(_) => NO_MONITOR,
+            _ => INVALID_PARAMS,
+        };
+        RpcError::new(code, e.to_string())
+    }
+}
+
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// A monitor or input selector, given as a string or a number.
+struct Selector(String);
+
+impl<'de> Deserialize<'de> for Selector {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Value::deserialize(deserializer)? {
+            Value::String(selector) => Ok(Selector(selector)),
+            Value::Number(number) => Ok(Selector(number.to_string())),
+            _ => Err(serde::de::Error::custom("expected a string or a number")),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct MonitorParams {
+    monitor: Selector,
+}
+
+#[derive(Deserialize)]
+struct InputParams {
+    monitor: Selector,
+    input: Selector,
+    #[serde(default)]
+    wake: bool,
+}
+
+#[derive(Deserialize)]
+struct ToggleParams {
+    monitor: Selector,
+    #[serde(default)]
+    inputs: Option<[Selector; 2]>,
+    #[serde(default)]
+    wake: bool,
+}
+
+#[derive(Deserialize)]
+struct CycleParams {
+    monitor: Selector,
+    #[serde(default)]
+    backward: bool,
+    #[serde(default)]
+    wake: bool,
+}
+
+#[derive(Deserialize)]
+struct SettingParams {
+    monitor: Selector,
+    #[serde(flatten)]
+    setting: Setting,
+}
+
+#[derive(Deserialize)]
+struct ProfileParams {
+    name: String,
+    #[serde(flatten)]
+    options: ApplyParams,
+}
+
+#[derive(Deserialize)]
+struct CycleProfileParams {
+    #[serde(default)]
+    backward: bool,
+    #[serde(flatten)]
+    options: ApplyParams,
+}
+
+/// `ApplyOptions` as sent by clients.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct ApplyParams {
+    pub order: Vec<String>,
+    pub delay_ms: u64,
+    pub rollback: bool,
+    pub wake: bool,
+}
+
+impl From<&ApplyOptions> for ApplyParams {
+    fn from(options: &ApplyOptions) -> Self {
+        ApplyParams {
+            order: options.order.clone(),
+            delay_ms: options.delay.as_millis().try_into().unwrap_or(u64::MAX),
+            rollback: options.rollback,
+            wake: options.wake,
+        }
+    }
+}
+
+impl From<ApplyParams> for ApplyOptions {
+    fn from(params: ApplyParams) -> Self {
+        ApplyOptions {
+            order: params.order,
+            delay: Duration::from_millis(params.delay_ms),
+            rollback: params.rollback,
+            wake: params.wake,
+        }
+    }
+}
+
+fn serve_client(stream: UnixStream, jobs: Sender<Job>) {
+    let Ok(reader) = stream.try_clone() else {
+        return;
+    };
+    let mut writer = stream;
+    for line in BufReader::new(reader).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = respond(&line, &jobs) {
+            if writeln!(writer, "{}", response).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// Runs one request line, returning the response to send back; `None` for
+/// notifications, which get no response.
+fn respond(line: &str, jobs: &Sender<Job>) -> Option<Value> {
+    let message: Value = match serde_json::from_str(line) {
+        Ok(message) => message,
+        Err(e) => {
+            return Some(response(
+                Value::Null,
+                Err(RpcError::new(PARSE_ERROR, e.to_string())),
+            ))
+        }
+    };
+    let request: Request = match Request::deserialize(&message) {
+        Ok(request) => request,
+        Err(e) => {
+            let id = message.get("id").cloned().unwrap_or_default();
+            let error = RpcError::new(INVALID_REQUEST, format!("Invalid request: {}", e));
+            return Some(response(id, Err(error)));
+        }
+    };
+
+    let (reply, result) = mpsc::channel();
+    let job = Job {
+        method: request.method,
+        params: request.params,
+        reply,
+    };
+    let result = match jobs.send(job) {
+        Ok(()) => result.recv().ok(),
+        Err(_) => None,
+    };
+    let result = result.unwrap_or_else(|| {
+        Err(RpcError::new(
+            INTERNAL_ERROR,
+            "The daemon stopped talking to the monitors",
+        ))
+    });
+    request.id.map(|id| response(id, result))
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+    }
+}
+
+fn parse<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params)
+        .map_err(|e| RpcError::new(INVALID_PARAMS, format!("Invalid parameters: {}", e)))
+}
+
+fn reply<T: Serialize + ?Sized>(value: &T) -> Result<Value, RpcError> {
+    Ok(serde_json::to_value(value).expect("replies serialise to JSON"))
+}
+
+fn direction(backward: bool) -> CycleDirection {
+    if backward {
+        CycleDirection::Backward
+    } else {
+        CycleDirection::Forward
+    }
+}
+
+/// A broken config shouldn't take the daemon down; carry on with defaults
+/// until the file is fixed.
+fn load_config(path: &Path) -> Config {
+    Config::load_from(path).unwrap_or_else(|e| {
+        warn!("Using the default settings: {}", e);
+        Config::default()
+    })
+}
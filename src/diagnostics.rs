@@ -0,0 +1,358 @@
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::MonitorError;
+
+type Probe = Box<dyn Fn(&Path) -> Result<(), MonitorError>>;
+
+/// Checks why DDC/CI might not work on Linux: the `i2c-dev` module, the
+/// `/dev/i2c-*` nodes and their permissions and, when asked to, whether
+/// anything answers on each display bus. The filesystem roots and the bus
+/// probe can be replaced to run the checks against a fake system.
+pub struct Diagnostics {
+    dev_root: PathBuf,
+    sys_root: PathBuf,
+    proc_root: PathBuf,
+    etc_root: PathBuf,
+    probe: Option<Probe>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiagnosticsReport {
+    /// `i2c-dev` is loaded as a module or built into the kernel.
+    pub i2c_dev_loaded: bool,
+    pub uid: Option<u32>,
+    pub buses: Vec<BusReport>,
+    /// Groups owning the device nodes and whether the user is in them.
+    pub groups: Vec<GroupMembership>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BusReport {
+    pub path: PathBuf,
+    /// Adapter name from sysfs, e.g. "AMDGPU DM i2c hw bus 1".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub adapter: Option<String>,
+    pub readable: bool,
+    pub writable: bool,
+    pub ddc: DdcStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "status", content = "error")]
+pub enum DdcStatus {
+    Responding,
+    NoResponse(String),
+    /// Not probed because the node can't be opened for reading and writing.
+    NotProbed,
+    /// Not probed because the adapter doesn't belong to a graphics card,
+    /// e.g. SMBus or a sensor, where a DDC/CI write could do harm.
+    NotDisplay,
+    /// Probing is off; see [`Diagnostics::probe_buses`].
+    Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GroupMembership {
+    pub gid: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The running session has the group.
+    pub active: bool,
+    /// The user is listed in the group file, e.g. added but not logged in
+    /// again yet.
+    pub listed: bool,
+}
+
+impl Default for Diagnostics {
+    fn default() -> Self {
+        Diagnostics {
+            dev_root: PathBuf::from("/dev"),
+            sys_root: PathBuf::from("/sys"),
+            proc_root: PathBuf::from("/proc"),
+            etc_root: PathBuf::from("/etc"),
+            probe: None,
+        }
+    }
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn dev_root(mut self, path: impl Into<PathBuf>) -> Self {
+        self.dev_root = path.into();
+        self
+    }
+
+    pub fn sys_root(mut self, path: impl Into<PathBuf>) -> Self {
+        self.sys_root = path.into();
+        self
+    }
+
+    pub fn proc_root(mut self, path: impl Into<PathBuf>) -> Self {
+        self.proc_root = path.into();
+        self
+    }
+
+    pub fn etc_root(mut self, path: impl Into<PathBuf>) -> Self {
+        self.etc_root = path.into();
+        self
+    }
+
+    /// Also asks each accessible display bus whether a monitor answers.
+    /// This writes a DDC/CI request to the bus, so it is off by default.
+    pub fn probe_buses(self) -> Self {
+        self.probe(probe_bus)
+    }
+
+    /// Like `probe_buses`, with `probe` instead of a DDC/CI request.
+    pub fn probe(mut self, probe: impl Fn(&Path) -> Result<(), MonitorError> + 'static) -> Self {
+        self.probe = Some(Box::new(probe));
+        self
+    }
+
+    pub fn run(&self) -> DiagnosticsReport {
+        let i2c_dev_loaded = self.sys_root.join("module/i2c_dev").exists()
+            || self.sys_root.join("class/i2c-dev").exists();
+        let (uid, gids) = self.credentials();
+        let user_name = uid.and_then(|uid| self.user_name(uid));
+
+        let mut buses = Vec::new();
+        let mut groups: Vec<GroupMembership> = Vec::new();
+        for path in self.i2c_nodes() {
+            let Ok(metadata) = fs::metadata(&path) else {
+                continue;
+            };
+            let (readable, writable) = access(&metadata, uid, &gids);
+
+            if let Some(gid) = file_gid(&metadata) {
+                if !groups.iter().any(|g| g.gid == gid) {
+                    let entry = self.group_entry(gid);
+                    groups.push(GroupMembership {
+                        gid,
+                        name: entry.as_ref().map(|(name, _)| name.clone()),
+                        active: gids.contains(&gid),
+                        listed: match (&entry, &user_name) {
+                            (Some((_, members)), Some(user)) => members.contains(user),
+                            _ => false,
+                        },
+                    });
+                }
+            }
+
+            let class = path
+                .file_name()
+                .map(|name| self.sys_root.join("class/i2c-dev").join(name));
+            let adapter = class
+                .as_ref()
+                .and_then(|class| fs::read_to_string(class.join("name")).ok())
+                .map(|name| name.trim().to_string());
+
+            let ddc = if !(readable && writable) {
+                DdcStatus::NotProbed
+            } else if !is_display_bus(class.as_deref(), adapter.as_deref()) {
+                DdcStatus::NotDisplay
+            } else {
+                match &self.probe {
+                    // Refusing the VCP code still means a monitor answered.
+                    Some(probe) => match probe(&path) {
+                        Ok(()) | Err(MonitorError::NotSupported) => DdcStatus::Responding,
+                        Err(e) => DdcStatus::NoResponse(e.to_string()),
+                    },
+                    None => DdcStatus::Skipped,
+                }
+            };
+
+            buses.push(BusReport {
+                path,
+                adapter,
+                readable,
+                writable,
+                ddc,
+            });
+        }
+
+        DiagnosticsReport {
+            i2c_dev_loaded,
+            uid,
+            buses,
+            groups,
+        }
+    }
+
+    fn i2c_nodes(&self) -> Vec<PathBuf> {
+        let mut nodes: Vec<PathBuf> = fs::read_dir(&self.dev_root)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.file_name().to_string_lossy().starts_with("i2c-"))
+                    .map(|entry| entry.path())
+                    .collect()
+            })
+            .unwrap_or_default();
+        nodes.sort_by_key(|path| bus_number(path));
+        nodes
+    }
+
+    /// Real uid and all group ids of the current process.
+    fn credentials(&self) -> (Option<u32>, Vec<u32>) {
+        let status = fs::read_to_string(self.proc_root.join("self/status")).unwrap_or_default();
+        let field = |name: &str| -> Vec<u32> {
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix(name))
+                .map(|rest| {
+                    rest.split_whitespace()
+                        .filter_map(|v| v.parse().ok())
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        let uid = field("Uid:").first().copied();
+        let mut gids = field("Groups:");
+        gids.extend(field("Gid:").first());
+        (uid, gids)
+    }
+
+    fn user_name(&self, uid: u32) -> Option<String> {
+        let passwd = fs::read_to_string(self.etc_root.join("passwd")).ok()?;
+        passwd.lines().find_map(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            (fields.get(2)?.parse::<u32>().ok()? == uid).then(|| fields[0].to_string())
+        })
+    }
+
+    fn group_entry(&self, gid: u32) -> Option<(String, Vec<String>)> {
+        let groups = fs::read_to_string(self.etc_root.join("group")).ok()?;
+        groups.lines().find_map(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            if fields.get(2)?.parse::<u32>().ok()? != gid {
+                return None;
+            }
+            let members = fields
+                .get(3)
+                .map(|m| {
+                    m.split(',')
+                        .filter(|s| !s.is_empty())
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default();
+            Some((fields[0].to_string(), members))
+        })
+    }
+}
+
+impl DiagnosticsReport {
+    pub fn responding_buses(&self) -> impl Iterator<Item = &BusReport> {
+        self.buses
+            .iter()
+            .filter(|bus| bus.ddc == DdcStatus::Responding)
+    }
+
+    /// The most likely reason no monitor could be reached, or `None` if at
+    /// least one bus answered. Use `MonitorError::hint` for the fix.
+    pub fn problem(&self) -> Option<MonitorError> {
+        if self.responding_buses().next().is_some() {
+            return None;
+        }
+        let Some(first) = self.buses.first() else {
+            return Some(MonitorError::I2cUnavailable);
+        };
+        if !self.buses.iter().any(|bus| bus.readable && bus.writable) {
+            return Some(MonitorError::PermissionDenied {
+                device: first.path.display().to_string(),
+            });
+        }
+        Some(MonitorError::DdcUnsupported)
+    }
+}
+
+/// Adapter names of graphics drivers whose buses reach a display.
+const DISPLAY_ADAPTERS: &[&str] = &[
+    "i915 gmbus",
+    "amdgpu dm",
+    "radeon",
+    "nvidia",
+    "nouveau",
+    "dpddc",
+    "dpmst",
+    "hdmi",
+    "ddc",
+];
+
+/// Whether the adapter behind `class` (its `/sys/class/i2c-dev` entry) is a
+/// display bus: a known graphics driver's adapter, or one that hangs off a
+/// DRM connector, like DisplayPort AUX channels do.
+fn is_display_bus(class: Option<&Path>, adapter: Option<&str>) -> bool {
+    let adapter = adapter.unwrap_or_default().to_lowercase();
+    if DISPLAY_ADAPTERS.iter().any(|name| adapter.contains(name)) {
+        return true;
+    }
+    class
+        .and_then(|class| fs::canonicalize(class.join("device")).ok())
+        .is_some_and(|device| device.components().any(|c| c.as_os_str() == "drm"))
+}
+
+fn bus_number(path: &Path) -> u32 {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.strip_prefix("i2c-"))
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(u32::MAX)
+}
+
+#[cfg(unix)]
+fn file_gid(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.gid())
+}
+
+#[cfg(not(unix))]
+fn file_gid(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+/// Read/write access from the mode bits, so the answer is about the user
+/// in the report rather than whoever runs the check.
+#[cfg(unix)]
+fn access(metadata: &fs::Metadata, uid: Option<u32>, gids: &[u32]) -> (bool, bool) {
+    use std::os::unix::fs::MetadataExt;
+
+    let mode = metadata.mode();
+    let bits = match uid {
+        Some(0) => return (true, true),
+        Some(uid) if uid == metadata.uid() => mode >> 6,
+        _ if gids.contains(&metadata.gid()) => mode >> 3,
+        _ => mode,
+    };
+    (bits & 0o4 != 0, bits & 0o2 != 0)
+}
+
+#[cfg(not(unix))]
+fn access(_metadata: &fs::Metadata, _uid: Option<u32>, _gids: &[u32]) -> (bool, bool) {
+    (false, false)
+}
+
+#[cfg(target_os = "linux")]
+fn probe_bus(path: &Path) -> Result<(), MonitorError> {
+    use ddc_hi::Ddc;
+
+    const VCP_BRIGHTNESS: u8 = 0x10;
+    let device = path.display().to_string();
+    let mut ddc = ddc_i2c::from_i2c_device(path).map_err(|e| {
+        crate::backend::io_error(&device, &e).unwrap_or(MonitorError::DdcError(e.to_string()))
+    })?;
+    ddc.get_vcp_feature(VCP_BRIGHTNESS)
+        .map(|_| ())
+        .map_err(|e| crate::backend::ddc_error(&device, &e.into()))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn probe_bus(_path: &Path) -> Result<(), MonitorError> {
+    Err(MonitorError::DdcUnsupported)
+}
@@ -0,0 +1,232 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+use crate::MonitorError;
+
+const EDID_HEADER: [u8; 8] = [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00];
+const BLOCK_LEN: usize = 128;
+const CEA_EXTENSION_TAG: u8 = 0x02;
+const HDMI_IEEE_OUI: [u8; 3] = [0x03, 0x0C, 0x00];
+
+/// Decoded base EDID block, plus the HDMI flag from a CEA-861 extension.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EdidInfo {
+    pub manufacturer_id: String,
+    pub product_code: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial_number: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manufacture_week: Option<u8>,
+    /// Year of manufacture, or the model year when the EDID gives no week.
+    pub manufacture_year: u16,
+    pub version: EdidVersion,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub native_resolution: Option<Resolution>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub physical_size: Option<PhysicalSize>,
+    pub video_interfaces: Vec<VideoInterface>,
+    #[serde(serialize_with = "to_hex", deserialize_with = "from_hex")]
+    pub raw: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct EdidVersion {
+    pub major: u8,
+    pub minor: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Resolution {
+    pub width: u16,
+    pub height: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhysicalSize {
+    pub width_mm: u16,
+    pub height_mm: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VideoInterface {
+    Analog,
+    /// Digital input of an unspecified type (EDID 1.3 and earlier).
+    Digital,
+    Dvi,
+    Hdmi,
+    Mddi,
+    DisplayPort,
+}
+
+impl EdidInfo {
+    pub fn parse(raw: &[u8]) -> Result<Self, MonitorError> {
+        let invalid = |reason: &str| MonitorError::DdcError(format!("Invalid EDID: {}", reason));
+
+        if raw.len() < BLOCK_LEN {
+            return Err(invalid("shorter than one block"));
+        }
+        if raw[..8] != EDID_HEADER {
+            return Err(invalid("missing header"));
+        }
+        let base = &raw[..BLOCK_LEN];
+
+        let vendor = u16::from_be_bytes([base[8], base[9]]);
+        let manufacturer_id: String = [10, 5, 0]
+            .iter()
+            .map(|shift| (b'A' - 1 + ((vendor >> shift) & 0x1F) as u8) as char)
+            .collect();
+
+        let version = EdidVersion {
+            major: base[18],
+            minor: base[19],
+        };
+
+        let mut info = EdidInfo {
+            manufacturer_id,
+            product_code: u16::from_le_bytes([base[10], base[11]]),
+            serial: Some(u32::from_le_bytes([base[12], base[13], base[14], base[15]]))
+                .filter(|&s| s != 0),
+            serial_number: None,
+            model_name: None,
+            manufacture_week: Some(base[16]).filter(|&w| (1..=54).contains(&w)),
+            manufacture_year: 1990 + base[17] as u16,
+            version,
+            native_resolution: None,
+            physical_size: None,
+            video_interfaces: video_interfaces(base[20], version),
+            raw: raw.to_vec(),
+        };
+
+        for descriptor in base[54..126].chunks_exact(18) {
+            let pixel_clock = u16::from_le_bytes([descriptor[0], descriptor[1]]);
+            if pixel_clock != 0 {
+                // The first detailed timing is the preferred (native) mode.
+                if info.native_resolution.is_none() {
+                    info.native_resolution = Some(Resolution {
+                        width: descriptor[2] as u16 | ((descriptor[4] as u16 & 0xF0) << 4),
+                        height: descriptor[5] as u16 | ((descriptor[7] as u16 & 0xF0) << 4),
+                    });
+                    let width_mm = descriptor[12] as u16 | ((descriptor[14] as u16 & 0xF0) << 4);
+                    let height_mm = descriptor[13] as u16 | ((descriptor[14] as u16 & 0x0F) << 8);
+                    if width_mm > 0 && height_mm > 0 {
+                        info.physical_size = Some(PhysicalSize {
+                            width_mm,
+                            height_mm,
+                        });
+                    }
+                }
+                continue;
+            }
+
+            match descriptor[3] {
+                0xFF => info.serial_number = descriptor_text(&descriptor[5..]),
+                0xFC => info.model_name = descriptor_text(&descriptor[5..]),
+                _ => {}
+            }
+        }
+
+        if info.physical_size.is_none() && base[21] > 0 && base[22] > 0 {
+            info.physical_size = Some(PhysicalSize {
+                width_mm: base[21] as u16 * 10,
+                height_mm: base[22] as u16 * 10,
+            });
+        }
+
+        let has_hdmi_block = raw[BLOCK_LEN..]
+            .chunks_exact(BLOCK_LEN)
+            .filter(|block| block[0] == CEA_EXTENSION_TAG)
+            .any(cea_has_hdmi_block);
+        if has_hdmi_block && !info.video_interfaces.contains(&VideoInterface::Hdmi) {
+            info.video_interfaces
+                .retain(|&i| i != VideoInterface::Digital);
+            info.video_interfaces.push(VideoInterface::Hdmi);
+        }
+
+        Ok(info)
+    }
+}
+
+impl fmt::Display for EdidVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+impl fmt::Display for VideoInterface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            VideoInterface::Analog => "Analog",
+            VideoInterface::Digital => "Digital",
+            VideoInterface::Dvi => "DVI",
+            VideoInterface::Hdmi => "HDMI",
+            VideoInterface::Mddi => "MDDI",
+            VideoInterface::DisplayPort => "DisplayPort",
+        };
+        f.write_str(name)
+    }
+}
+
+fn video_interfaces(input: u8, version: EdidVersion) -> Vec<VideoInterface> {
+    if input & 0x80 == 0 {
+        return vec![VideoInterface::Analog];
+    }
+    // The interface type bits were only defined in EDID 1.4.
+    if version < (EdidVersion { major: 1, minor: 4 }) {
+        return vec![VideoInterface::Digital];
+    }
+    match input & 0x0F {
+        0x1 => vec![VideoInterface::Dvi],
+        0x2 | 0x3 => vec![VideoInterface::Hdmi],
+        0x4 => vec![VideoInterface::Mddi],
+        0x5 => vec![VideoInterface::DisplayPort],
+        _ => vec![VideoInterface::Digital],
+    }
+}
+
+fn descriptor_text(bytes: &[u8]) -> Option<String> {
+    let text: String = bytes
+        .iter()
+        .take_while(|&&b| b != 0x0A)
+        .map(|&b| b as char)
+        .collect();
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+fn cea_has_hdmi_block(block: &[u8]) -> bool {
+    let dtd_offset = (block[2] as usize).clamp(4, BLOCK_LEN - 1);
+    let mut pos = 4;
+    while pos < dtd_offset {
+        let tag = block[pos] >> 5;
+        let len = (block[pos] & 0x1F) as usize;
+        // Vendor-specific data block carrying the HDMI Licensing OUI.
+        let payload = block.get(pos + 1..pos + 1 + len).unwrap_or_default();
+        if tag == 3 && payload.starts_with(&HDMI_IEEE_OUI) {
+            return true;
+        }
+        pos += len + 1;
+    }
+    false
+}
+
+fn to_hex<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    serializer.serialize_str(&hex)
+}
+
+fn from_hex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    let hex = String::deserialize(deserializer)?;
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| serde::de::Error::custom("invalid hex in EDID"))
+        })
+        .collect()
+}
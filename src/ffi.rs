@@ -1,13 +1,26 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
 use std::ffi::{CStr, CString};
 use std::os::raw::c_char;
+use std::path::PathBuf;
 use std::ptr;
-use std::cell::RefCell;
 
-use crate::{Config, InputSource, Monitor};
+use log::warn;
+
+use crate::{
+    cycle_favorites, cycle_profiles, resolve_input, switch_input, toggle_input, ApplyOptions,
+    Config, ConfigWatcher, CycleDirection, InputHistory, InputSource, Monitor, MonitorError,
+    MonitorErrorCode, MonitorSelector, PowerMode, SelectorError, SwitchOptions, TargetOutcome,
+    VcpFeature,
+};
 
 thread_local! {
-    static MONITORS: RefCell<Vec<Monitor>> = RefCell::new(Vec::new());
-    static CONFIG: RefCell<Config> = RefCell::new(Config::load());
+    static MONITORS: RefCell<Vec<Monitor>> = const { RefCell::new(Vec::new()) };
+    static CONFIG: RefCell<Config> = RefCell::new(load_config());
+    static CONFIG_ERROR: RefCell<Option<String>> = const { RefCell::new(None) };
+    static CONFIG_WATCHER: RefCell<Option<ConfigWatcher>> = const { RefCell::new(None) };
+    static LAST_ERROR: RefCell<Option<MonitorError>> = const { RefCell::new(None) };
+    static SELECTOR_ERROR: RefCell<Option<SelectorError>> = const { RefCell::new(None) };
 }
 
 #[repr(C)]
@@ -25,7 +38,7 @@ pub struct MonitorList {
 
 #[repr(C)]
 pub struct InputSourceList {
-    pub inputs: *mut InputSource,
+    pub inputs: *mut u16,
     pub count: usize,
 }
 
@@ -34,9 +47,44 @@ pub extern "C" fn monitor_core_init() {
     let _ = env_logger::try_init();
 }
 
+/// Like `monitor_core_init`, but reads and writes the config at
+/// `config_path` instead of the default location; null keeps the default.
+/// Returns false if the config could not be loaded, see `config_load_error`.
+///
+/// # Safety
+///
+/// `config_path` must be null or point to a NUL-terminated string that stays
+/// valid for the duration of the call.
+#[no_mangle]
+pub unsafe extern "C" fn monitor_core_init_with_config(config_path: *const c_char) -> bool {
+    monitor_core_init();
+
+    let path = if config_path.is_null() {
+        None
+    } else {
+        match unsafe { CStr::from_ptr(config_path) }.to_str() {
+            Ok(path) => Some(PathBuf::from(path)),
+            Err(_) => return false,
+        }
+    };
+    Config::set_config_path(path);
+    config_reload()
+}
+
 #[no_mangle]
 pub extern "C" fn monitor_enumerate() -> MonitorList {
-    let monitors = Monitor::enumerate();
+    let monitors = CONFIG.with(|c| {
+        let mut config = c.borrow_mut();
+        let monitors = Monitor::enumerate_with_config(&config);
+        if config.migrate_monitor_ids(&monitors) {
+            if let Ok(updated) = Config::update(|c| {
+                c.migrate_monitor_ids(&monitors);
+            }) {
+                *config = updated;
+            }
+        }
+        monitors
+    });
     let mut infos: Vec<MonitorInfo> = monitors
         .iter()
         .map(|m| MonitorInfo {
@@ -64,8 +112,11 @@ pub extern "C" fn monitor_enumerate() -> MonitorList {
     list
 }
 
+/// # Safety
+///
+/// `list` must come from `monitor_enumerate` and not have been freed yet.
 #[no_mangle]
-pub extern "C" fn monitor_list_free(list: MonitorList) {
+pub unsafe extern "C" fn monitor_list_free(list: MonitorList) {
     if list.monitors.is_null() {
         return;
     }
@@ -85,60 +136,253 @@ pub extern "C" fn monitor_list_free(list: MonitorList) {
     }
 }
 
+/// Returns the raw VCP 0x60 value, or 0 if the input could not be read.
 #[no_mangle]
-pub extern "C" fn monitor_get_current_input(index: usize) -> InputSource {
-    MONITORS.with(|m| {
-        let mut monitors = m.borrow_mut();
-        if index >= monitors.len() {
-            return InputSource::Unknown;
-        }
-        monitors[index]
-            .get_current_input()
-            .unwrap_or(InputSource::Unknown)
+pub extern "C" fn monitor_get_current_input(index: usize) -> u16 {
+    with_monitor(index, |m| m.get_current_input())
+        .map(|input| input.to_vcp_value())
+        .unwrap_or(0)
+}
+
+#[no_mangle]
+pub extern "C" fn monitor_set_input(index: usize, input_value: u16) -> bool {
+    with_monitor(index, |m| {
+        let options = m.switch_options().clone();
+        with_history(|h| switch_input(m, InputSource::from_vcp_value(input_value), &options, h))
     })
+    .is_some()
 }
 
+/// Like `monitor_set_input`, waking the monitor first if it is in standby.
 #[no_mangle]
-pub extern "C" fn monitor_set_input(index: usize, input: InputSource) -> bool {
-    MONITORS.with(|m| {
+pub extern "C" fn monitor_set_input_ensure_on(index: usize, input_value: u16) -> bool {
+    with_monitor(index, |m| {
+        let options = SwitchOptions {
+            ensure_powered_on: true,
+            ..m.switch_options().clone()
+        };
+        with_history(|h| switch_input(m, InputSource::from_vcp_value(input_value), &options, h))
+    })
+    .is_some()
+}
+
+/// Switches between `first` and `second`, or back to the input before the
+/// last switch if both are 0, waking the monitor first. Returns the input
+/// switched to, or 0 on failure.
+#[no_mangle]
+pub extern "C" fn monitor_toggle_input(index: usize, first: u16, second: u16) -> u16 {
+    let pair = (first != 0 || second != 0).then(|| {
+        (
+            InputSource::from_vcp_value(first),
+            InputSource::from_vcp_value(second),
+        )
+    });
+    with_monitor(index, |m| with_history(|h| toggle_input(m, pair, true, h)))
+        .map_or(0, InputSource::to_vcp_value)
+}
+
+/// Switches to the next favorite input of the monitor, or the previous one
+/// if `backward`, wrapping around and waking the monitor first. Returns the
+/// input switched to, or 0 on failure.
+#[no_mangle]
+pub extern "C" fn monitor_cycle_favorites(index: usize, backward: bool) -> u16 {
+    let direction = cycle_direction(backward);
+    with_monitor(index, |m| {
+        CONFIG.with(|c| with_history(|h| cycle_favorites(m, &c.borrow(), direction, true, h)))
+    })
+    .map_or(0, InputSource::to_vcp_value)
+}
+
+/// Applies the profile after the active one, or before it if `backward`,
+/// switching monitors back if one fails. Returns false if there are no
+/// profiles or a monitor failed; the error of the first failure is available
+/// from the `monitor_last_error_*` functions.
+#[no_mangle]
+pub extern "C" fn monitor_cycle_profiles(backward: bool) -> bool {
+    let options = ApplyOptions {
+        rollback: true,
+        wake: true,
+        ..ApplyOptions::default()
+    };
+    let direction = cycle_direction(backward);
+    let report = MONITORS.with(|m| {
         let mut monitors = m.borrow_mut();
-        if index >= monitors.len() {
+        CONFIG.with(|c| cycle_profiles(&mut monitors, &c.borrow(), direction, &options))
+    });
+    let error = match report {
+        None => Some(MonitorError::NotFound),
+        Some(report) if report.is_ok() => None,
+        Some(report) => report.monitors.into_iter().find_map(|target| {
+            match (target.outcome, target.brightness_result) {
+                (TargetOutcome::Failed(e) | TargetOutcome::RollbackFailed(e), _) => Some(e),
+                (_, Some(Err(e))) => Some(e),
+                (TargetOutcome::NotFound, _) => Some(MonitorError::NotFound),
+                _ => None,
+            }
+        }),
+    };
+    let ok = error.is_none();
+    LAST_ERROR.with(|e| *e.borrow_mut() = error);
+    ok
+}
+
+#[no_mangle]
+pub extern "C" fn monitor_get_power_mode(index: usize) -> PowerMode {
+    with_monitor(index, |m| m.power_mode()).unwrap_or(PowerMode::Unknown)
+}
+
+/// Sets VCP 0xD6 to `mode`, one of the `PowerMode` values other than
+/// `Unknown`. Anything else fails with `InvalidArgument`.
+#[no_mangle]
+pub extern "C" fn monitor_set_power_mode(index: usize, mode: u8) -> bool {
+    let mode = match PowerMode::from_vcp_value(mode.into()) {
+        PowerMode::Unknown => {
+            let error = MonitorError::InvalidArgument(format!("power mode {:#04x}", mode));
+            LAST_ERROR.with(|e| *e.borrow_mut() = Some(error));
             return false;
         }
-        monitors[index].set_input(input).is_ok()
-    })
+        mode => mode,
+    };
+    with_monitor(index, |m| m.set_power_mode(mode)).is_some()
+}
+
+#[no_mangle]
+pub extern "C" fn monitor_supports_volume(index: usize) -> bool {
+    with_monitor(index, |m| Ok(m.supports_volume())).unwrap_or(false)
+}
+
+/// # Safety
+///
+/// `out` must be null or valid for writing a `u8`.
+#[no_mangle]
+pub unsafe extern "C" fn monitor_get_volume(index: usize, out: *mut u8) -> bool {
+    if out.is_null() {
+        return false;
+    }
+
+    match with_monitor(index, |m| m.volume()) {
+        Some(percent) => {
+            unsafe { *out = percent };
+            true
+        }
+        None => false,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn monitor_set_volume(index: usize, percent: u8) -> bool {
+    with_monitor(index, |m| m.set_volume(percent)).is_some()
+}
+
+#[no_mangle]
+pub extern "C" fn monitor_supports_mute(index: usize) -> bool {
+    with_monitor(index, |m| Ok(m.supports_mute())).unwrap_or(false)
+}
+
+/// # Safety
+///
+/// `out` must be null or valid for writing a `bool`.
+#[no_mangle]
+pub unsafe extern "C" fn monitor_get_muted(index: usize, out: *mut bool) -> bool {
+    if out.is_null() {
+        return false;
+    }
+
+    match with_monitor(index, |m| m.is_muted()) {
+        Some(muted) => {
+            unsafe { *out = muted };
+            true
+        }
+        None => false,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn monitor_set_muted(index: usize, muted: bool) -> bool {
+    with_monitor(index, |m| m.set_muted(muted)).is_some()
+}
+
+/// # Safety
+///
+/// `out` must be null or valid for writing a `VcpFeature`.
+#[no_mangle]
+pub unsafe extern "C" fn monitor_get_vcp(index: usize, code: u8, out: *mut VcpFeature) -> bool {
+    if out.is_null() {
+        return false;
+    }
+
+    match with_monitor(index, |m| m.get_vcp(code)) {
+        Some(feature) => {
+            unsafe { *out = feature };
+            true
+        }
+        None => false,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn monitor_set_vcp(index: usize, code: u8, value: u16) -> bool {
+    with_monitor(index, |m| m.set_vcp(code, value)).is_some()
 }
 
 #[no_mangle]
 pub extern "C" fn monitor_get_available_inputs(index: usize) -> InputSourceList {
-    MONITORS.with(|m| {
-        let mut monitors = m.borrow_mut();
-        if index >= monitors.len() {
-            return InputSourceList {
-                inputs: ptr::null_mut(),
-                count: 0,
+    match with_monitor(index, |m| m.get_available_inputs()) {
+        Some(inputs) => {
+            let mut values: Box<[u16]> = inputs.iter().map(|i| i.to_vcp_value()).collect();
+            let list = InputSourceList {
+                inputs: values.as_mut_ptr(),
+                count: values.len(),
             };
+            std::mem::forget(values);
+            list
         }
+        None => InputSourceList {
+            inputs: ptr::null_mut(),
+            count: 0,
+        },
+    }
+}
 
-        match monitors[index].get_available_inputs() {
-            Ok(mut inputs) => {
-                let list = InputSourceList {
-                    inputs: inputs.as_mut_ptr(),
-                    count: inputs.len(),
-                };
-                std::mem::forget(inputs);
-                list
-            }
-            Err(_) => InputSourceList {
-                inputs: ptr::null_mut(),
-                count: 0,
-            },
-        }
+/// Class of the error from the last failed `monitor_*` call on this thread,
+/// or `None` if it succeeded.
+#[no_mangle]
+pub extern "C" fn monitor_last_error_code() -> MonitorErrorCode {
+    LAST_ERROR.with(|e| {
+        e.borrow()
+            .as_ref()
+            .map(|error| error.code())
+            .unwrap_or(MonitorErrorCode::None)
+    })
+}
+
+/// The returned string must be released with `string_free`. Null if the
+/// last call succeeded.
+#[no_mangle]
+pub extern "C" fn monitor_last_error_message() -> *mut c_char {
+    LAST_ERROR.with(|e| match e.borrow().as_ref() {
+        Some(error) => CString::new(error.to_string()).unwrap().into_raw(),
+        None => ptr::null_mut(),
     })
 }
 
+/// The returned string must be released with `string_free`. Null if there
+/// is no suggestion for the last error.
 #[no_mangle]
-pub extern "C" fn input_source_list_free(list: InputSourceList) {
+pub extern "C" fn monitor_last_error_hint() -> *mut c_char {
+    let hint = LAST_ERROR.with(|e| e.borrow().as_ref().and_then(|error| error.hint()));
+    match hint {
+        Some(hint) => CString::new(hint).unwrap().into_raw(),
+        None => ptr::null_mut(),
+    }
+}
+
+/// # Safety
+///
+/// `list` must come from `monitor_get_available_inputs` and not have been
+/// freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn input_source_list_free(list: InputSourceList) {
     if !list.inputs.is_null() {
         unsafe {
             drop(Vec::from_raw_parts(list.inputs, list.count, list.count));
@@ -146,14 +390,68 @@ pub extern "C" fn input_source_list_free(list: InputSourceList) {
     }
 }
 
+/// The returned string must be released with `string_free`.
+#[no_mangle]
+pub extern "C" fn input_source_name(input_value: u16) -> *mut c_char {
+    let name = InputSource::from_vcp_value(input_value).name();
+    CString::new(name.into_owned()).unwrap().into_raw()
+}
+
+/// Index of the monitor from the last `monitor_enumerate` that `selector`
+/// picks, e.g. "2", "serial:ABC123" or "dell", or -1 if none or several
+/// match, see `selector_last_error`.
+///
+/// # Safety
+///
+/// `selector` must be null or point to a NUL-terminated string that stays
+/// valid for the duration of the call.
+#[no_mangle]
+pub unsafe extern "C" fn monitor_find(selector: *const c_char) -> isize {
+    let result = unsafe { selector_str(selector) }.and_then(|s| {
+        let selector: MonitorSelector = s.parse()?;
+        MONITORS.with(|m| CONFIG.with(|c| selector.resolve(&m.borrow(), &c.borrow())))
+    });
+    remember_selector_result(result).map_or(-1, |index| index as isize)
+}
+
+/// The VCP value of the input `selector` names on the monitor at `index`,
+/// e.g. "hdmi2", "0x0F" or one of the user's aliases, or 0 if it names
+/// none, see `selector_last_error`.
+///
+/// # Safety
+///
+/// `selector` must be null or point to a NUL-terminated string that stays
+/// valid for the duration of the call.
+#[no_mangle]
+pub unsafe extern "C" fn monitor_resolve_input(index: usize, selector: *const c_char) -> u16 {
+    let result = unsafe { selector_str(selector) }.and_then(|s| {
+        let monitor_id = MONITORS.with(|m| m.borrow().get(index).map(Monitor::id));
+        let monitor_id = monitor_id.unwrap_or_default();
+        CONFIG.with(|c| resolve_input(&c.borrow(), &monitor_id, &s))
+    });
+    remember_selector_result(result).map_or(0, InputSource::to_vcp_value)
+}
+
+/// Why the last `monitor_find` or `monitor_resolve_input` call failed, or
+/// null if it succeeded. The returned string must be released with
+/// `string_free`.
 #[no_mangle]
-pub extern "C" fn input_source_name(input: InputSource) -> *const c_char {
-    let name = input.name();
-    name.as_ptr() as *const c_char
+pub extern "C" fn selector_last_error() -> *mut c_char {
+    SELECTOR_ERROR.with(|e| match e.borrow().as_ref() {
+        Some(error) => CString::new(error.to_string()).unwrap().into_raw(),
+        None => ptr::null_mut(),
+    })
 }
 
+/// # Safety
+///
+/// `monitor_id` must be null or point to a NUL-terminated string that stays
+/// valid for the duration of the call.
 #[no_mangle]
-pub extern "C" fn config_get_alias(monitor_id: *const c_char, input_value: u16) -> *mut c_char {
+pub unsafe extern "C" fn config_get_alias(
+    monitor_id: *const c_char,
+    input_value: u16,
+) -> *mut c_char {
     if monitor_id.is_null() {
         return ptr::null_mut();
     }
@@ -172,8 +470,12 @@ pub extern "C" fn config_get_alias(monitor_id: *const c_char, input_value: u16)
     })
 }
 
+/// # Safety
+///
+/// `monitor_id` and `alias` must each be null or point to a NUL-terminated
+/// string that stays valid for the duration of the call.
 #[no_mangle]
-pub extern "C" fn config_set_alias(
+pub unsafe extern "C" fn config_set_alias(
     monitor_id: *const c_char,
     input_value: u16,
     alias: *const c_char,
@@ -190,15 +492,17 @@ pub extern "C" fn config_set_alias(
         _ => return false,
     };
 
-    CONFIG.with(|c| {
-        let mut config = c.borrow_mut();
+    update_config(|config| {
         config.set_alias(monitor_id, input_value, alias.to_string());
-        config.save().is_ok()
     })
 }
 
+/// # Safety
+///
+/// `monitor_id` must be null or point to a NUL-terminated string that stays
+/// valid for the duration of the call.
 #[no_mangle]
-pub extern "C" fn config_remove_alias(monitor_id: *const c_char, input_value: u16) -> bool {
+pub unsafe extern "C" fn config_remove_alias(monitor_id: *const c_char, input_value: u16) -> bool {
     if monitor_id.is_null() {
         return false;
     }
@@ -209,22 +513,58 @@ pub extern "C" fn config_remove_alias(monitor_id: *const c_char, input_value: u1
         Err(_) => return false,
     };
 
-    CONFIG.with(|c| {
-        let mut config = c.borrow_mut();
+    update_config(|config| {
         config.remove_alias(monitor_id, input_value);
-        config.save().is_ok()
     })
 }
 
+/// Returns false if the config file could not be loaded; the defaults are
+/// used instead and `config_load_error` describes the problem.
 #[no_mangle]
-pub extern "C" fn config_reload() {
+pub extern "C" fn config_reload() -> bool {
+    let config = load_config();
     CONFIG.with(|c| {
-        *c.borrow_mut() = Config::load();
+        *c.borrow_mut() = config;
     });
+    CONFIG_ERROR.with(|e| e.borrow().is_none())
 }
 
+/// Reloads the config if the file was changed by another process or by
+/// hand since the last call. Returns true if it was reloaded, so the
+/// caller can re-render; `config_load_error` tells if the new file is
+/// broken.
 #[no_mangle]
-pub extern "C" fn config_is_favorite(monitor_id: *const c_char, input_value: u16) -> bool {
+pub extern "C" fn config_poll_changes() -> bool {
+    CONFIG.with(|_| ());
+    let changed = CONFIG_WATCHER.with(|w| {
+        w.borrow()
+            .as_ref()
+            .is_some_and(|watcher| watcher.try_iter().count() > 0)
+    });
+    if changed {
+        config_reload();
+    }
+    changed
+}
+
+/// Why the config file could not be loaded, including the line and column
+/// of a syntax error, or null if it loaded fine. The returned string must
+/// be released with `string_free`.
+#[no_mangle]
+pub extern "C" fn config_load_error() -> *mut c_char {
+    CONFIG.with(|_| ());
+    CONFIG_ERROR.with(|e| match e.borrow().as_ref() {
+        Some(error) => CString::new(error.as_str()).unwrap().into_raw(),
+        None => ptr::null_mut(),
+    })
+}
+
+/// # Safety
+///
+/// `monitor_id` must be null or point to a NUL-terminated string that stays
+/// valid for the duration of the call.
+#[no_mangle]
+pub unsafe extern "C" fn config_is_favorite(monitor_id: *const c_char, input_value: u16) -> bool {
     if monitor_id.is_null() {
         return false;
     }
@@ -238,8 +578,12 @@ pub extern "C" fn config_is_favorite(monitor_id: *const c_char, input_value: u16
     CONFIG.with(|c| c.borrow().is_favorite(monitor_id, input_value))
 }
 
+/// # Safety
+///
+/// `monitor_id` must be null or point to a NUL-terminated string that stays
+/// valid for the duration of the call.
 #[no_mangle]
-pub extern "C" fn config_add_favorite(monitor_id: *const c_char, input_value: u16) -> bool {
+pub unsafe extern "C" fn config_add_favorite(monitor_id: *const c_char, input_value: u16) -> bool {
     if monitor_id.is_null() {
         return false;
     }
@@ -250,15 +594,20 @@ pub extern "C" fn config_add_favorite(monitor_id: *const c_char, input_value: u1
         Err(_) => return false,
     };
 
-    CONFIG.with(|c| {
-        let mut config = c.borrow_mut();
+    update_config(|config| {
         config.add_favorite(monitor_id, input_value);
-        config.save().is_ok()
     })
 }
 
+/// # Safety
+///
+/// `monitor_id` must be null or point to a NUL-terminated string that stays
+/// valid for the duration of the call.
 #[no_mangle]
-pub extern "C" fn config_remove_favorite(monitor_id: *const c_char, input_value: u16) -> bool {
+pub unsafe extern "C" fn config_remove_favorite(
+    monitor_id: *const c_char,
+    input_value: u16,
+) -> bool {
     if monitor_id.is_null() {
         return false;
     }
@@ -269,10 +618,8 @@ pub extern "C" fn config_remove_favorite(monitor_id: *const c_char, input_value:
         Err(_) => return false,
     };
 
-    CONFIG.with(|c| {
-        let mut config = c.borrow_mut();
+    update_config(|config| {
         config.remove_favorite(monitor_id, input_value);
-        config.save().is_ok()
     })
 }
 
@@ -280,6 +627,8 @@ pub extern "C" fn config_remove_favorite(monitor_id: *const c_char, input_value:
 pub struct FavoriteInfo {
     pub monitor_id: *mut c_char,
     pub input_value: u16,
+    /// The other input of a toggle favorite, or 0 for a plain favorite.
+    pub toggle_with: u16,
 }
 
 #[repr(C)]
@@ -298,6 +647,7 @@ pub extern "C" fn config_get_favorites() -> FavoriteList {
             .map(|f| FavoriteInfo {
                 monitor_id: CString::new(f.monitor_id.clone()).unwrap().into_raw(),
                 input_value: f.input_value,
+                toggle_with: f.toggle_with.unwrap_or(0),
             })
             .collect();
 
@@ -310,8 +660,11 @@ pub extern "C" fn config_get_favorites() -> FavoriteList {
     })
 }
 
+/// # Safety
+///
+/// `list` must come from `config_get_favorites` and not have been freed yet.
 #[no_mangle]
-pub extern "C" fn favorite_list_free(list: FavoriteList) {
+pub unsafe extern "C" fn favorite_list_free(list: FavoriteList) {
     if list.favorites.is_null() {
         return;
     }
@@ -325,11 +678,103 @@ pub extern "C" fn favorite_list_free(list: FavoriteList) {
     }
 }
 
+/// # Safety
+///
+/// `s` must be null or a string returned by this library that hasn't been
+/// freed yet.
 #[no_mangle]
-pub extern "C" fn string_free(s: *mut c_char) {
+pub unsafe extern "C" fn string_free(s: *mut c_char) {
     if !s.is_null() {
         unsafe {
             drop(CString::from_raw(s));
         }
     }
 }
+
+/// Runs `f` on the monitor at `index`, remembering the outcome for the
+/// `monitor_last_error_*` functions.
+fn with_monitor<T>(
+    index: usize,
+    f: impl FnOnce(&mut Monitor) -> Result<T, MonitorError>,
+) -> Option<T> {
+    let result = MONITORS.with(|m| match m.borrow_mut().get_mut(index) {
+        Some(monitor) => f(monitor),
+        None => Err(MonitorError::NotFound),
+    });
+    let (value, error) = match result {
+        Ok(value) => (Some(value), None),
+        Err(error) => (None, Some(error)),
+    };
+    LAST_ERROR.with(|e| *e.borrow_mut() = error);
+    value
+}
+
+fn cycle_direction(backward: bool) -> CycleDirection {
+    if backward {
+        CycleDirection::Backward
+    } else {
+        CycleDirection::Forward
+    }
+}
+
+/// Runs `f` with the input history from disk and saves it afterwards, so
+/// other processes' switches are kept.
+fn with_history<T>(f: impl FnOnce(&mut InputHistory) -> T) -> T {
+    let loaded = InputHistory::load();
+    let mut history = loaded.clone();
+    let result = f(&mut history);
+    if history != loaded {
+        if let Err(e) = history.save() {
+            warn!("Could not save the input history: {}", e);
+        }
+    }
+    result
+}
+
+/// # Safety
+///
+/// `selector` must be null or point to a NUL-terminated string that outlives
+/// `'a`.
+unsafe fn selector_str<'a>(selector: *const c_char) -> Result<Cow<'a, str>, SelectorError> {
+    if selector.is_null() {
+        return Err(SelectorError::Empty);
+    }
+    Ok(unsafe { CStr::from_ptr(selector) }.to_string_lossy())
+}
+
+fn remember_selector_result<T>(result: Result<T, SelectorError>) -> Option<T> {
+    let (value, error) = match result {
+        Ok(value) => (Some(value), None),
+        Err(error) => (None, Some(error)),
+    };
+    SELECTOR_ERROR.with(|e| *e.borrow_mut() = error);
+    value
+}
+
+fn load_config() -> Config {
+    let (config, error) = match Config::load() {
+        Ok(config) => (config, None),
+        Err(e) => (Config::default(), Some(e.to_string())),
+    };
+    CONFIG_ERROR.with(|e| *e.borrow_mut() = error);
+    // Follow the file just loaded, which changes with `Config::set_config_path`.
+    CONFIG_WATCHER.with(|w| {
+        let mut watcher = w.borrow_mut();
+        if watcher.as_ref().map(|w| w.path()) != Config::config_path().as_deref() {
+            *watcher = ConfigWatcher::new().ok();
+        }
+    });
+    config
+}
+
+/// Applies `f` to the config on disk, so changes saved by other processes
+/// are kept, and refreshes `CONFIG` with the result.
+fn update_config(f: impl FnOnce(&mut Config)) -> bool {
+    match Config::update(f) {
+        Ok(config) => {
+            CONFIG.with(|c| *c.borrow_mut() = config);
+            true
+        }
+        Err(_) => false,
+    }
+}
@@ -1,32 +1,41 @@
+use std::borrow::Cow;
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-#[repr(C)]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::selector::{normalize, parse_value};
+use crate::SelectorError;
+
+#[derive(Debug, Clone, Copy)]
 pub enum InputSource {
-    VGA1 = 0x01,
-    VGA2 = 0x02,
-    DVI1 = 0x03,
-    DVI2 = 0x04,
-    CompositeVideo1 = 0x05,
-    CompositeVideo2 = 0x06,
-    SVideo1 = 0x07,
-    SVideo2 = 0x08,
-    Tuner1 = 0x09,
-    Tuner2 = 0x0A,
-    Tuner3 = 0x0B,
-    Component1 = 0x0C,
-    Component2 = 0x0D,
-    Component3 = 0x0E,
-    DisplayPort1 = 0x0F,
-    DisplayPort2 = 0x10,
-    HDMI1 = 0x11,
-    HDMI2 = 0x12,
-    HDMI3 = 0x13,
-    HDMI4 = 0x14,
-    USBC1 = 0x15,
-    USBC2 = 0x16,
-    USBC3 = 0x17,
-    Unknown = 0xFF,
+    VGA1,
+    VGA2,
+    DVI1,
+    DVI2,
+    CompositeVideo1,
+    CompositeVideo2,
+    SVideo1,
+    SVideo2,
+    Tuner1,
+    Tuner2,
+    Tuner3,
+    Component1,
+    Component2,
+    Component3,
+    DisplayPort1,
+    DisplayPort2,
+    HDMI1,
+    HDMI2,
+    HDMI3,
+    HDMI4,
+    USBC1,
+    USBC2,
+    USBC3,
+    /// A value outside the MCCS-defined range, typically a vendor-specific
+    /// port such as Thunderbolt or a second USB-C connector.
+    Other(u16),
 }
 
 impl InputSource {
@@ -55,16 +64,41 @@ impl InputSource {
             0x15 => InputSource::USBC1,
             0x16 => InputSource::USBC2,
             0x17 => InputSource::USBC3,
-            _ => InputSource::Unknown,
+            other => InputSource::Other(other),
         }
     }
 
     pub fn to_vcp_value(self) -> u16 {
-        self as u16
+        match self {
+            InputSource::VGA1 => 0x01,
+            InputSource::VGA2 => 0x02,
+            InputSource::DVI1 => 0x03,
+            InputSource::DVI2 => 0x04,
+            InputSource::CompositeVideo1 => 0x05,
+            InputSource::CompositeVideo2 => 0x06,
+            InputSource::SVideo1 => 0x07,
+            InputSource::SVideo2 => 0x08,
+            InputSource::Tuner1 => 0x09,
+            InputSource::Tuner2 => 0x0A,
+            InputSource::Tuner3 => 0x0B,
+            InputSource::Component1 => 0x0C,
+            InputSource::Component2 => 0x0D,
+            InputSource::Component3 => 0x0E,
+            InputSource::DisplayPort1 => 0x0F,
+            InputSource::DisplayPort2 => 0x10,
+            InputSource::HDMI1 => 0x11,
+            InputSource::HDMI2 => 0x12,
+            InputSource::HDMI3 => 0x13,
+            InputSource::HDMI4 => 0x14,
+            InputSource::USBC1 => 0x15,
+            InputSource::USBC2 => 0x16,
+            InputSource::USBC3 => 0x17,
+            InputSource::Other(value) => value,
+        }
     }
 
-    pub fn name(&self) -> &'static str {
-        match self {
+    pub fn name(&self) -> Cow<'static, str> {
+        let name = match self {
             InputSource::VGA1 => "VGA 1",
             InputSource::VGA2 => "VGA 2",
             InputSource::DVI1 => "DVI 1",
@@ -88,8 +122,24 @@ impl InputSource {
             InputSource::USBC1 => "USB-C 1",
             InputSource::USBC2 => "USB-C 2",
             InputSource::USBC3 => "USB-C 3",
-            InputSource::Unknown => "Unknown",
-        }
+            InputSource::Other(value) => return Cow::Owned(format!("Input 0x{:02X}", value)),
+        };
+        Cow::Borrowed(name)
+    }
+}
+
+// Compare by VCP value so that `Other(0x11)` and `HDMI1` are the same input.
+impl PartialEq for InputSource {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_vcp_value() == other.to_vcp_value()
+    }
+}
+
+impl Eq for InputSource {}
+
+impl Hash for InputSource {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.to_vcp_value().hash(state);
     }
 }
 
@@ -99,3 +149,51 @@ impl fmt::Display for InputSource {
     }
 }
 
+// Serialised as the VCP value, like `input_value` in the config.
+impl Serialize for InputSource {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u16(self.to_vcp_value())
+    }
+}
+
+impl<'de> Deserialize<'de> for InputSource {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        u16::deserialize(deserializer).map(InputSource::from_vcp_value)
+    }
+}
+
+/// Parses a VCP value (`17`, `0x11`) or a name, ignoring case, spaces and
+/// punctuation: "HDMI 2", "hdmi2", "dp-1", "usb-c". A name without a number
+/// means the first port of that kind.
+impl FromStr for InputSource {
+    type Err = SelectorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        // "Input 0x1B" is how `name` shows vendor-specific values.
+        let value = parse_value(s).or_else(|| match s.get(..5) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("input") => parse_value(s[5..].trim()),
+            _ => None,
+        });
+        if let Some(value) = value {
+            return Ok(InputSource::from_vcp_value(value));
+        }
+
+        let name = normalize(s);
+        let split = name
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(name.len());
+        let (kind, number) = name.split_at(split);
+        let kind = match kind {
+            "dp" => "displayport",
+            "typec" => "usbc",
+            kind => kind,
+        };
+        let number = if number.is_empty() { "1" } else { number };
+        let wanted = format!("{}{}", kind, number);
+        (0x01..=0x17)
+            .map(InputSource::from_vcp_value)
+            .find(|input| normalize(&input.name()) == wanted)
+            .ok_or_else(|| SelectorError::UnknownInput(s.to_string()))
+    }
+}
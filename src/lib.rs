@@ -1,10 +1,53 @@
+mod backend;
+mod capabilities;
+#[cfg(unix)]
+mod client;
 mod config;
+mod cycle;
+#[cfg(unix)]
+mod daemon;
+mod diagnostics;
+mod edid;
+mod ffi;
 mod input_source;
 mod monitor;
-mod ffi;
+mod power_mode;
+mod profile;
+mod quirks;
+mod selector;
+#[cfg(feature = "simulated")]
+mod simulated;
+mod snapshot;
+mod toggle;
+mod watcher;
 
-pub use config::Config;
-pub use input_source::InputSource;
-pub use monitor::{Monitor, MonitorError};
+pub use backend::{Backend, DdcHiBackend, Device, DeviceInfo};
+pub use capabilities::Capabilities;
+#[cfg(unix)]
+pub use client::DaemonClient;
+pub use config::{
+    Config, ConfigError, Favorite, MonitorConfig, Profile, ProfileTarget, CONFIG_PATH_ENV,
+    CONFIG_VERSION,
+};
+pub use cycle::{active_profile, cycle_favorites, cycle_profiles, CycleDirection};
+#[cfg(unix)]
+pub use daemon::{socket_path, Daemon, DaemonError, DaemonOptions, SOCKET_PATH_ENV};
+pub use diagnostics::{BusReport, DdcStatus, Diagnostics, DiagnosticsReport, GroupMembership};
+pub use edid::{EdidInfo, EdidVersion, PhysicalSize, Resolution, VideoInterface};
 pub use ffi::*;
-
+pub use input_source::InputSource;
+pub use monitor::{
+    AvailableInputs, InputListSource, Monitor, MonitorError, MonitorErrorCode, SwitchOptions,
+    VcpFeature, VerifyPolicy, PERCENT_STEP,
+};
+pub use power_mode::PowerMode;
+pub use profile::{
+    apply_profile, apply_profile_with, ApplyOptions, ProfileReport, TargetOutcome, TargetReport,
+};
+pub use quirks::{builtin_quirks, QuirkEntry, Quirks};
+pub use selector::{resolve_input, MonitorSelector, SelectorError};
+#[cfg(feature = "simulated")]
+pub use simulated::{SimulatedBackend, SimulatedMonitor, SimulatedMonitorHandle};
+pub use snapshot::{MonitorSnapshot, Setting};
+pub use toggle::{switch_input, toggle_input, InputHistory};
+pub use watcher::{ConfigEvent, ConfigWatcher};
@@ -1,8 +1,25 @@
-use ddc_hi::{Ddc, Display};
+use std::collections::HashMap;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{
+    Backend, Capabilities, Config, DdcHiBackend, Device, EdidInfo, InputSource, PowerMode, Quirks,
+};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use crate::InputSource;
 
 const VCP_INPUT_SELECT: u8 = 0x60;
+const VCP_BRIGHTNESS: u8 = 0x10;
+const VCP_CONTRAST: u8 = 0x12;
+const VCP_POWER_MODE: u8 = 0xD6;
+const VCP_AUDIO_VOLUME: u8 = 0x62;
+const VCP_AUDIO_MUTE: u8 = 0x8D;
+
+const AUDIO_MUTED: u16 = 0x01;
+const AUDIO_UNMUTED: u16 = 0x02;
+
+pub const PERCENT_STEP: i8 = 5;
 
 #[derive(Error, Debug)]
 pub enum MonitorError {
@@ -12,62 +29,642 @@ pub enum MonitorError {
     NotFound,
     #[error("Operation not supported by this monitor")]
     NotSupported,
+    #[error("Monitor acknowledged the switch to {expected} but did not change input")]
+    InputNotApplied {
+        expected: InputSource,
+        actual: Option<InputSource>,
+    },
+    #[error("Permission denied opening {device}")]
+    PermissionDenied { device: String },
+    #[error("No I2C devices found")]
+    I2cUnavailable,
+    #[error("Monitor did not reply in time")]
+    Timeout,
+    #[error("Monitor sent a corrupted reply")]
+    ChecksumMismatch,
+    #[error("Monitor does not respond to DDC/CI")]
+    DdcUnsupported,
+    #[error("No earlier input to switch back to")]
+    NoPreviousInput,
+    #[error("No favorite inputs for this monitor")]
+    NoFavorites,
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+}
+
+/// C-compatible class of a [`MonitorError`], see `monitor_last_error_code`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[repr(C)]
+pub enum MonitorErrorCode {
+    None = 0,
+    DdcError = 1,
+    NotFound = 2,
+    NotSupported = 3,
+    InputNotApplied = 4,
+    PermissionDenied = 5,
+    I2cUnavailable = 6,
+    Timeout = 7,
+    ChecksumMismatch = 8,
+    DdcUnsupported = 9,
+    NoPreviousInput = 10,
+    NoFavorites = 11,
+    InvalidArgument = 12,
+}
+
+impl MonitorError {
+    pub fn code(&self) -> MonitorErrorCode {
+        match self {
+            MonitorError::DdcError(_) => MonitorErrorCode::DdcError,
+            MonitorError::NotFound => MonitorErrorCode::NotFound,
+            MonitorError::NotSupported => MonitorErrorCode::NotSupported,
+            MonitorError::InputNotApplied { .. } => MonitorErrorCode::InputNotApplied,
+            MonitorError::PermissionDenied { .. } => MonitorErrorCode::PermissionDenied,
+            MonitorError::I2cUnavailable => MonitorErrorCode::I2cUnavailable,
+            MonitorError::Timeout => MonitorErrorCode::Timeout,
+            MonitorError::ChecksumMismatch => MonitorErrorCode::ChecksumMismatch,
+            MonitorError::DdcUnsupported => MonitorErrorCode::DdcUnsupported,
+            MonitorError::NoPreviousInput => MonitorErrorCode::NoPreviousInput,
+            MonitorError::NoFavorites => MonitorErrorCode::NoFavorites,
+            MonitorError::InvalidArgument(_) => MonitorErrorCode::InvalidArgument,
+        }
+    }
+
+    /// What the user can do about the error, if anything.
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            MonitorError::DdcError(_)
+            | MonitorError::NotSupported
+            | MonitorError::InvalidArgument(_) => None,
+            MonitorError::NotFound => Some("Reconnect the monitor and refresh the list"),
+            MonitorError::InputNotApplied { .. } => Some(
+                "Add a \"verify\" policy or an input switch delay for this monitor in config.json",
+            ),
+            MonitorError::PermissionDenied { .. } => {
+                Some("Add yourself to the i2c group (sudo usermod -aG i2c $USER) and log in again")
+            }
+            MonitorError::I2cUnavailable => {
+                Some("Load the i2c-dev kernel module (sudo modprobe i2c-dev)")
+            }
+            MonitorError::Timeout => Some("The monitor may be busy or asleep, try again"),
+            MonitorError::ChecksumMismatch => {
+                Some("Try again, or connect the monitor directly instead of through a dock or KVM")
+            }
+            MonitorError::DdcUnsupported => Some("Enable DDC/CI in the monitor's on-screen menu"),
+            MonitorError::NoPreviousInput => {
+                Some("Switch inputs once first, or name the two inputs to toggle between")
+            }
+            MonitorError::NoFavorites => Some("Mark some of the monitor's inputs as favorites"),
+        }
+    }
+}
+
+/// How a [`MonitorError`] is sent to daemon clients: its code and message,
+/// plus the fields of the variant so the client gets the same error back.
+#[derive(Serialize, Deserialize)]
+struct ErrorData {
+    code: MonitorErrorCode,
+    message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    device: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expected: Option<InputSource>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    actual: Option<InputSource>,
+}
+
+impl Serialize for MonitorError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut data = ErrorData {
+            code: self.code(),
+            message: self.to_string(),
+            detail: None,
+            device: None,
+            expected: None,
+            actual: None,
+        };
+        match self {
+            MonitorError::DdcError(detail) | MonitorError::InvalidArgument(detail) => {
+                data.detail = Some(detail.clone())
+            }
+            MonitorError::PermissionDenied { device } => data.device = Some(device.clone()),
+            MonitorError::InputNotApplied { expected, actual } => {
+                data.expected = Some(*expected);
+                data.actual = *actual;
+            }
+            _ => {}
+        }
+        data.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for MonitorError {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let data = ErrorData::deserialize(deserializer)?;
+        Ok(match data.code {
+            MonitorErrorCode::None => return Err(D::Error::custom("not an error")),
+            MonitorErrorCode::DdcError => MonitorError::DdcError(data.detail.unwrap_or_default()),
+            MonitorErrorCode::NotFound => MonitorError::NotFound,
+            MonitorErrorCode::NotSupported => MonitorError::NotSupported,
+            MonitorErrorCode::InputNotApplied => MonitorError::InputNotApplied {
+                expected: data
+                    .expected
+                    .ok_or_else(|| D::Error::missing_field("expected"))?,
+                actual: data.actual,
+            },
+            MonitorErrorCode::PermissionDenied => MonitorError::PermissionDenied {
+                device: data.device.unwrap_or_default(),
+            },
+            MonitorErrorCode::I2cUnavailable => MonitorError::I2cUnavailable,
+            MonitorErrorCode::Timeout => MonitorError::Timeout,
+            MonitorErrorCode::ChecksumMismatch => MonitorError::ChecksumMismatch,
+            MonitorErrorCode::DdcUnsupported => MonitorError::DdcUnsupported,
+            MonitorErrorCode::NoPreviousInput => MonitorError::NoPreviousInput,
+            MonitorErrorCode::NoFavorites => MonitorError::NoFavorites,
+            MonitorErrorCode::InvalidArgument => {
+                MonitorError::InvalidArgument(data.detail.unwrap_or_default())
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct VcpFeature {
+    pub current: u16,
+    pub maximum: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwitchOptions {
+    /// Wake the monitor before writing the input, since many monitors
+    /// ignore input changes while in standby.
+    pub ensure_powered_on: bool,
+    pub wake_delay: Duration,
+    /// Read VCP 0x60 back after writing and retry until it matches.
+    pub verify: Option<VerifyPolicy>,
+}
+
+impl Default for SwitchOptions {
+    fn default() -> Self {
+        SwitchOptions {
+            ensure_powered_on: false,
+            wake_delay: Duration::from_millis(1500),
+            verify: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VerifyPolicy {
+    /// Extra writes after the first one is not reflected in the read-back.
+    pub retries: u32,
+    /// Wait before the first read-back; doubled after every attempt.
+    pub initial_backoff_ms: u64,
+    /// Overall time budget for writing and verifying.
+    pub timeout_ms: u64,
+}
+
+impl Default for VerifyPolicy {
+    fn default() -> Self {
+        VerifyPolicy {
+            retries: 3,
+            initial_backoff_ms: 200,
+            timeout_ms: 5000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputListSource {
+    /// Values listed for VCP 0x60 in the monitor's capabilities string.
+    Capabilities,
+    /// The common HDMI/DisplayPort/USB-C list, used when the capabilities
+    /// string is missing, unparseable or doesn't list any input values.
+    Fallback,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AvailableInputs {
+    pub inputs: Vec<InputSource>,
+    pub source: InputListSource,
 }
 
 pub struct Monitor {
-    display: Display,
-    index: usize,
+    device: Box<dyn Device>,
+    id: String,
+    legacy_id: String,
+    capabilities: Option<Capabilities>,
+    capabilities_read: bool,
+    quirks: Quirks,
+    last_input: Option<InputSource>,
+    switch_options: SwitchOptions,
 }
 
 impl Monitor {
     pub fn enumerate() -> Vec<Monitor> {
-        Display::enumerate()
+        Self::enumerate_with_config(&Config::default())
+    }
+
+    pub fn enumerate_with_config(config: &Config) -> Vec<Monitor> {
+        Self::enumerate_with_backend(&DdcHiBackend, config)
+    }
+
+    pub fn enumerate_with_backend(backend: &dyn Backend, config: &Config) -> Vec<Monitor> {
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        backend
+            .enumerate()
             .into_iter()
             .enumerate()
-            .map(|(index, display)| Monitor { display, index })
+            .map(|(index, device)| {
+                let info = device.info();
+                let legacy_id = info.legacy_id(index);
+                let mut id = info.stable_id().unwrap_or_else(|| legacy_id.clone());
+                // Identical units without a serial share an EDID; number the
+                // extra ones so they can still be configured separately.
+                let count = seen.entry(id.clone()).or_insert(0);
+                *count += 1;
+                if *count > 1 {
+                    id = format!("{}-{}", id, count);
+                }
+
+                let mut monitor = Monitor {
+                    device,
+                    id,
+                    legacy_id,
+                    capabilities: None,
+                    capabilities_read: false,
+                    quirks: Quirks::default(),
+                    last_input: None,
+                    switch_options: SwitchOptions::default(),
+                };
+                monitor.configure(config);
+                monitor
+            })
             .collect()
     }
 
+    /// Picks up the quirks and verify policy for this monitor from `config`,
+    /// so a long-running process can apply config changes without
+    /// enumerating again.
+    pub fn configure(&mut self, config: &Config) {
+        let info = self.device.info();
+        self.quirks = Quirks::lookup(
+            info.manufacturer_id.as_deref(),
+            info.model_name.as_deref(),
+            &config.quirks,
+        );
+        self.switch_options.verify = config
+            .monitors
+            .get(&self.id)
+            .or_else(|| config.monitors.get(&self.legacy_id))
+            .and_then(|m| m.verify.clone());
+    }
+
+    /// Stable identifier used to key this monitor's settings in `Config`.
     pub fn id(&self) -> String {
-        let base_id = &self.display.info.id;
-        if let Some(serial) = &self.display.info.serial_number {
-            format!("{}-{}", base_id, serial)
-        } else if let Some(serial) = self.display.info.serial {
-            format!("{}-{}", base_id, serial)
-        } else {
-            format!("{}-{}", base_id, self.index)
-        }
+        self.id.clone()
+    }
+
+    /// The enumeration-order based id older versions stored in `Config`.
+    pub fn legacy_id(&self) -> &str {
+        &self.legacy_id
     }
 
     pub fn model_name(&self) -> Option<String> {
-        self.display.info.model_name.clone()
+        self.device.info().model_name.clone()
     }
 
     pub fn manufacturer_id(&self) -> Option<String> {
-        self.display.info.manufacturer_id.clone()
+        self.device.info().manufacturer_id.clone()
+    }
+
+    /// The serial number from the EDID, falling back to the numeric serial.
+    pub fn serial_number(&self) -> Option<String> {
+        let info = self.device.info();
+        let serial_number = info.serial_number.as_deref().map(str::trim);
+        match serial_number.filter(|s| !s.is_empty()) {
+            Some(serial) => Some(serial.to_string()),
+            None => info.serial.filter(|&s| s != 0).map(|s| s.to_string()),
+        }
+    }
+
+    /// The decoded EDID, if the backend could read one.
+    pub fn edid(&self) -> Option<EdidInfo> {
+        let raw = self.device.info().edid.as_deref()?;
+        EdidInfo::parse(raw)
+            .map_err(|e| warn!("Could not decode EDID of {}: {}", self.id, e))
+            .ok()
+    }
+
+    pub fn quirks(&self) -> &Quirks {
+        &self.quirks
+    }
+
+    /// The options `set_input` uses, including the verify policy configured
+    /// for this monitor.
+    pub fn switch_options(&self) -> &SwitchOptions {
+        &self.switch_options
+    }
+
+    pub fn get_vcp(&mut self, code: u8) -> Result<VcpFeature, MonitorError> {
+        self.device.get_vcp_feature(code)
+    }
+
+    pub fn set_vcp(&mut self, code: u8, value: u16) -> Result<(), MonitorError> {
+        self.device.set_vcp_feature(code, value)
     }
 
     pub fn get_current_input(&mut self) -> Result<InputSource, MonitorError> {
-        let value = self
-            .display
-            .handle
-            .get_vcp_feature(VCP_INPUT_SELECT)
-            .map_err(|e| MonitorError::DdcError(e.to_string()))?;
+        if self.quirks.skips_input_readback() {
+            return self.last_input.ok_or(MonitorError::NotSupported);
+        }
 
-        Ok(InputSource::from_vcp_value(value.value()))
+        match self.get_vcp(VCP_INPUT_SELECT) {
+            Ok(feature) => Ok(InputSource::from_vcp_value(
+                self.quirks.input_from_monitor(feature.current),
+            )),
+            Err(e) => match self.last_input {
+                Some(input) if self.quirks.drops_off_bus() => Ok(input),
+                _ => Err(e),
+            },
+        }
     }
 
     pub fn set_input(&mut self, input: InputSource) -> Result<(), MonitorError> {
-        self.display
-            .handle
-            .set_vcp_feature(VCP_INPUT_SELECT, input.to_vcp_value())
-            .map_err(|e| MonitorError::DdcError(e.to_string()))
+        let options = self.switch_options.clone();
+        self.set_input_with_options(input, &options)
+    }
+
+    pub fn set_input_with_options(
+        &mut self,
+        input: InputSource,
+        options: &SwitchOptions,
+    ) -> Result<(), MonitorError> {
+        if options.ensure_powered_on {
+            self.ensure_powered_on(options.wake_delay)?;
+        }
+
+        let can_verify = !self.quirks.skips_input_readback() && !self.quirks.drops_off_bus();
+        match &options.verify {
+            Some(policy) if can_verify => self.write_input_verified(input, policy),
+            _ => self.write_input(input),
+        }
+    }
+
+    fn write_input(&mut self, input: InputSource) -> Result<(), MonitorError> {
+        let value = self.quirks.input_to_monitor(input.to_vcp_value());
+        self.set_vcp(VCP_INPUT_SELECT, value)?;
+        self.last_input = Some(input);
+
+        if let Some(delay) = self.quirks.input_switch_delay() {
+            thread::sleep(delay);
+        }
+        Ok(())
+    }
+
+    fn write_input_verified(
+        &mut self,
+        input: InputSource,
+        policy: &VerifyPolicy,
+    ) -> Result<(), MonitorError> {
+        let deadline = Instant::now() + Duration::from_millis(policy.timeout_ms);
+        let mut backoff = Duration::from_millis(policy.initial_backoff_ms);
+        let mut last_error = None;
+
+        for attempt in 0..=policy.retries {
+            match self.write_input(input) {
+                Ok(()) => {
+                    thread::sleep(backoff.min(deadline.saturating_duration_since(Instant::now())));
+                    match self.get_current_input() {
+                        Ok(actual) if actual == input => return Ok(()),
+                        Ok(actual) => {
+                            debug!(
+                                "Attempt {} to switch to {}: monitor reports {}",
+                                attempt + 1,
+                                input,
+                                actual
+                            );
+                            last_error = Some(MonitorError::InputNotApplied {
+                                expected: input,
+                                actual: Some(actual),
+                            });
+                        }
+                        // The bus failed, which says nothing about whether
+                        // the monitor switched.
+                        Err(e) => {
+                            debug!("Attempt {} to read back {}: {}", attempt + 1, input, e);
+                            last_error = Some(e);
+                        }
+                    }
+                }
+                Err(e) => {
+                    last_error = Some(e);
+                    // Give the bus time to recover before writing again.
+                    if attempt < policy.retries {
+                        let remaining = deadline.saturating_duration_since(Instant::now());
+                        thread::sleep(backoff.min(remaining));
+                    }
+                }
+            }
+
+            if Instant::now() >= deadline {
+                break;
+            }
+            backoff *= 2;
+        }
+
+        Err(last_error.unwrap_or(MonitorError::InputNotApplied {
+            expected: input,
+            actual: None,
+        }))
+    }
+
+    pub fn power_mode(&mut self) -> Result<PowerMode, MonitorError> {
+        let feature = self.get_vcp(VCP_POWER_MODE)?;
+        Ok(PowerMode::from_vcp_value(feature.current))
+    }
+
+    pub fn set_power_mode(&mut self, mode: PowerMode) -> Result<(), MonitorError> {
+        if mode == PowerMode::Unknown {
+            return Err(MonitorError::NotSupported);
+        }
+        self.set_vcp(VCP_POWER_MODE, mode.to_vcp_value())
+    }
+
+    fn ensure_powered_on(&mut self, wake_delay: Duration) -> Result<(), MonitorError> {
+        match self.power_mode() {
+            Ok(PowerMode::On) | Err(MonitorError::NotSupported) => Ok(()),
+            Ok(_) => {
+                self.set_power_mode(PowerMode::On)?;
+                thread::sleep(wake_delay);
+                Ok(())
+            }
+            // A sleeping monitor may not answer reads at all, so try waking it anyway.
+            Err(_) => {
+                let _ = self.set_power_mode(PowerMode::On);
+                thread::sleep(wake_delay);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn capabilities(&mut self) -> Option<&Capabilities> {
+        if !self.capabilities_read {
+            self.capabilities_read = true;
+            self.capabilities = match self.read_capabilities() {
+                Ok(caps) => Some(caps),
+                Err(e) => {
+                    warn!("Could not read capabilities of {}: {}", self.id(), e);
+                    None
+                }
+            };
+        }
+        self.capabilities.as_ref()
+    }
+
+    fn read_capabilities(&mut self) -> Result<Capabilities, MonitorError> {
+        let raw = self.device.capabilities_string()?;
+        Capabilities::parse(&raw)
+    }
+
+    pub fn brightness(&mut self) -> Result<u8, MonitorError> {
+        self.get_percent(VCP_BRIGHTNESS)
+    }
+
+    pub fn set_brightness(&mut self, percent: u8) -> Result<(), MonitorError> {
+        self.set_percent(VCP_BRIGHTNESS, percent)
+    }
+
+    pub fn adjust_brightness(&mut self, delta: i8) -> Result<u8, MonitorError> {
+        self.adjust_percent(VCP_BRIGHTNESS, delta)
+    }
+
+    pub fn contrast(&mut self) -> Result<u8, MonitorError> {
+        self.get_percent(VCP_CONTRAST)
+    }
+
+    pub fn set_contrast(&mut self, percent: u8) -> Result<(), MonitorError> {
+        self.set_percent(VCP_CONTRAST, percent)
+    }
+
+    pub fn adjust_contrast(&mut self, delta: i8) -> Result<u8, MonitorError> {
+        self.adjust_percent(VCP_CONTRAST, delta)
+    }
+
+    pub fn supports_volume(&mut self) -> bool {
+        self.supports_vcp(VCP_AUDIO_VOLUME)
+    }
+
+    pub fn volume(&mut self) -> Result<u8, MonitorError> {
+        self.require_vcp(VCP_AUDIO_VOLUME)?;
+        self.get_percent(VCP_AUDIO_VOLUME)
+    }
+
+    pub fn set_volume(&mut self, percent: u8) -> Result<(), MonitorError> {
+        self.require_vcp(VCP_AUDIO_VOLUME)?;
+        self.set_percent(VCP_AUDIO_VOLUME, percent)
+    }
+
+    pub fn adjust_volume(&mut self, delta: i8) -> Result<u8, MonitorError> {
+        self.require_vcp(VCP_AUDIO_VOLUME)?;
+        self.adjust_percent(VCP_AUDIO_VOLUME, delta)
+    }
+
+    pub fn supports_mute(&mut self) -> bool {
+        self.supports_vcp(VCP_AUDIO_MUTE)
+    }
+
+    pub fn is_muted(&mut self) -> Result<bool, MonitorError> {
+        self.require_vcp(VCP_AUDIO_MUTE)?;
+        let feature = self.get_vcp(VCP_AUDIO_MUTE)?;
+        Ok(feature.current == AUDIO_MUTED)
+    }
+
+    pub fn set_muted(&mut self, muted: bool) -> Result<(), MonitorError> {
+        self.require_vcp(VCP_AUDIO_MUTE)?;
+        let value = if muted { AUDIO_MUTED } else { AUDIO_UNMUTED };
+        self.set_vcp(VCP_AUDIO_MUTE, value)
+    }
+
+    pub fn supports_vcp(&mut self, code: u8) -> bool {
+        self.capabilities()
+            .map(|caps| caps.supports_vcp(code))
+            .unwrap_or(false)
+    }
+
+    fn require_vcp(&mut self, code: u8) -> Result<(), MonitorError> {
+        if self.supports_vcp(code) {
+            Ok(())
+        } else {
+            Err(MonitorError::NotSupported)
+        }
+    }
+
+    fn get_percent(&mut self, code: u8) -> Result<u8, MonitorError> {
+        let feature = self.get_vcp(code)?;
+        if feature.maximum == 0 {
+            return Err(MonitorError::NotSupported);
+        }
+        Ok(value_to_percent(feature.current, feature.maximum))
+    }
+
+    fn set_percent(&mut self, code: u8, percent: u8) -> Result<(), MonitorError> {
+        let feature = self.get_vcp(code)?;
+        if feature.maximum == 0 {
+            return Err(MonitorError::NotSupported);
+        }
+        self.set_vcp(code, percent_to_value(percent, feature.maximum))
+    }
+
+    fn adjust_percent(&mut self, code: u8, delta: i8) -> Result<u8, MonitorError> {
+        let feature = self.get_vcp(code)?;
+        if feature.maximum == 0 {
+            return Err(MonitorError::NotSupported);
+        }
+        let current = value_to_percent(feature.current, feature.maximum) as i16;
+        let percent = (current + delta as i16).clamp(0, 100) as u8;
+        self.set_vcp(code, percent_to_value(percent, feature.maximum))?;
+        Ok(percent)
     }
 
     pub fn get_available_inputs(&mut self) -> Result<Vec<InputSource>, MonitorError> {
-        let _ = self.display.update_capabilities();
+        Ok(self.get_available_inputs_with_source().inputs)
+    }
+
+    pub fn get_available_inputs_with_source(&mut self) -> AvailableInputs {
+        let values: Vec<u8> = self
+            .capabilities()
+            .and_then(|caps| caps.vcp_values(VCP_INPUT_SELECT))
+            .map(|values| values.to_vec())
+            .unwrap_or_default();
 
-        Ok(self.get_common_inputs())
+        let mut inputs: Vec<InputSource> = Vec::new();
+        for value in values {
+            let value = self.quirks.input_from_monitor(value as u16);
+            let input = InputSource::from_vcp_value(value);
+            if !inputs.contains(&input) {
+                inputs.push(input);
+            }
+        }
+
+        if inputs.is_empty() {
+            AvailableInputs {
+                inputs: self.get_common_inputs(),
+                source: InputListSource::Fallback,
+            }
+        } else {
+            AvailableInputs {
+                inputs,
+                source: InputListSource::Capabilities,
+            }
+        }
     }
 
     fn get_common_inputs(&self) -> Vec<InputSource> {
@@ -82,3 +679,12 @@ impl Monitor {
     }
 }
 
+fn value_to_percent(value: u16, maximum: u16) -> u8 {
+    let value = value.min(maximum) as u32;
+    ((value * 100 + maximum as u32 / 2) / maximum as u32) as u8
+}
+
+fn percent_to_value(percent: u8, maximum: u16) -> u16 {
+    let percent = percent.min(100) as u32;
+    ((percent * maximum as u32 + 50) / 100) as u16
+}
@@ -0,0 +1,49 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[repr(C)]
+pub enum PowerMode {
+    On = 0x01,
+    Standby = 0x02,
+    Suspend = 0x03,
+    Off = 0x04,
+    HardOff = 0x05,
+    Unknown = 0xFF,
+}
+
+impl PowerMode {
+    pub fn from_vcp_value(value: u16) -> Self {
+        match value {
+            0x01 => PowerMode::On,
+            0x02 => PowerMode::Standby,
+            0x03 => PowerMode::Suspend,
+            0x04 => PowerMode::Off,
+            0x05 => PowerMode::HardOff,
+            _ => PowerMode::Unknown,
+        }
+    }
+
+    pub fn to_vcp_value(self) -> u16 {
+        self as u16
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PowerMode::On => "On",
+            PowerMode::Standby => "Standby",
+            PowerMode::Suspend => "Suspend",
+            PowerMode::Off => "Off",
+            PowerMode::HardOff => "Hard Off",
+            PowerMode::Unknown => "Unknown",
+        }
+    }
+}
+
+impl fmt::Display for PowerMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
@@ -0,0 +1,231 @@
+use std::thread;
+use std::time::Duration;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{InputSource, Monitor, MonitorError, Profile, SwitchOptions};
+
+/// How `apply_profile_with` switches the monitors of a profile.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ApplyOptions {
+    /// Monitor ids to switch first, in this order. The others follow in the
+    /// order the profile lists them.
+    pub order: Vec<String>,
+    /// Pause between monitors, e.g. for a dock or KVM to settle.
+    pub delay: Duration,
+    /// When a monitor fails to switch, stop and switch the monitors already
+    /// changed back to their previous input. Monitors that aren't connected
+    /// are skipped without triggering a rollback.
+    pub rollback: bool,
+    /// Wake monitors in standby before switching them. Otherwise each
+    /// monitor's own `switch_options` decide.
+    pub wake: bool,
+}
+
+/// Outcome of applying a profile, one entry per monitor in the order they
+/// were switched.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProfileReport {
+    pub profile: String,
+    pub monitors: Vec<TargetReport>,
+    /// A monitor failed and every one switched before it is back on its
+    /// previous input. Not set if switching one of them back failed too; see
+    /// `TargetOutcome::RollbackFailed`.
+    pub rolled_back: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TargetReport {
+    pub monitor_id: String,
+    pub input: InputSource,
+    /// The input before switching, if the monitor could report it.
+    pub previous_input: Option<InputSource>,
+    pub outcome: TargetOutcome,
+    /// `None` if the profile leaves the brightness alone or the monitor
+    /// wasn't switched. Brightness is not rolled back.
+    #[serde(
+        serialize_with = "brightness_to_json",
+        deserialize_with = "brightness_from_json"
+    )]
+    pub brightness_result: Option<Result<(), MonitorError>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "status", content = "error")]
+pub enum TargetOutcome {
+    Switched,
+    /// The monitor isn't connected.
+    NotFound,
+    Failed(MonitorError),
+    /// Not attempted because an earlier monitor failed.
+    Skipped,
+    /// Switched, then back to `previous_input` after a later failure.
+    RolledBack,
+    /// Switched, but switching back failed, e.g. because the monitor no
+    /// longer answers this computer on its new input.
+    RollbackFailed(MonitorError),
+}
+
+impl ProfileReport {
+    pub fn is_ok(&self) -> bool {
+        self.monitors.iter().all(TargetReport::is_ok)
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &TargetReport> {
+        self.monitors.iter().filter(|m| !m.is_ok())
+    }
+}
+
+impl TargetReport {
+    pub fn is_ok(&self) -> bool {
+        matches!(self.outcome, TargetOutcome::Switched)
+            && !matches!(self.brightness_result, Some(Err(_)))
+    }
+
+    /// The error behind a failed outcome or brightness change.
+    pub fn error(&self) -> Option<&MonitorError> {
+        match (&self.outcome, &self.brightness_result) {
+            (TargetOutcome::Failed(e) | TargetOutcome::RollbackFailed(e), _) => Some(e),
+            (_, Some(Err(e))) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Switches every monitor in `profile` that is among `monitors`, carrying on
+/// past failures so one unplugged or unresponsive monitor doesn't hold up
+/// the rest.
+pub fn apply_profile(monitors: &mut [Monitor], profile: &Profile) -> ProfileReport {
+    apply_profile_with(monitors, profile, &ApplyOptions::default())
+}
+
+pub fn apply_profile_with(
+    monitors: &mut [Monitor],
+    profile: &Profile,
+    options: &ApplyOptions,
+) -> ProfileReport {
+    let mut targets: Vec<_> = profile.monitors.iter().collect();
+    targets.sort_by_key(|t| {
+        let position = options.order.iter().position(|id| *id == t.monitor_id);
+        position.unwrap_or(usize::MAX)
+    });
+
+    let mut reports: Vec<TargetReport> = Vec::new();
+    let mut failed = false;
+    let mut first = true;
+    for target in targets {
+        let mut report = TargetReport {
+            monitor_id: target.monitor_id.clone(),
+            input: InputSource::from_vcp_value(target.input_value),
+            previous_input: None,
+            outcome: TargetOutcome::Skipped,
+            brightness_result: None,
+        };
+        let monitor = monitors.iter_mut().find(|m| m.id() == target.monitor_id);
+        match monitor {
+            _ if failed => {}
+            None => report.outcome = TargetOutcome::NotFound,
+            Some(monitor) => {
+                if !first {
+                    thread::sleep(options.delay);
+                }
+                first = false;
+
+                report.previous_input = monitor.get_current_input().ok();
+                // Brightness first: most monitors only listen to DDC/CI on
+                // the active input, so this computer can't reach them after
+                // switching.
+                report.brightness_result = target
+                    .brightness
+                    .map(|percent| monitor.set_brightness(percent));
+                let switch_options = SwitchOptions {
+                    ensure_powered_on: options.wake || monitor.switch_options().ensure_powered_on,
+                    ..monitor.switch_options().clone()
+                };
+                report.outcome = match monitor.set_input_with_options(report.input, &switch_options)
+                {
+                    Ok(()) => TargetOutcome::Switched,
+                    Err(e) => {
+                        failed = options.rollback;
+                        TargetOutcome::Failed(e)
+                    }
+                };
+            }
+        }
+        reports.push(report);
+    }
+
+    let rolled_back = failed && roll_back(monitors, &mut reports, options.delay);
+    ProfileReport {
+        profile: profile.name.clone(),
+        monitors: reports,
+        rolled_back,
+    }
+}
+
+/// Switches the monitors that were changed back, last one first. Returns
+/// whether all of them made it.
+fn roll_back(monitors: &mut [Monitor], reports: &mut [TargetReport], delay: Duration) -> bool {
+    let mut first = true;
+    let switched = reports
+        .iter_mut()
+        .rev()
+        .filter(|r| matches!(r.outcome, TargetOutcome::Switched));
+    for report in switched {
+        let Some(previous) = report.previous_input else {
+            report.outcome = TargetOutcome::RollbackFailed(MonitorError::NotSupported);
+            continue;
+        };
+        if previous == report.input {
+            report.outcome = TargetOutcome::RolledBack;
+            continue;
+        }
+        let Some(monitor) = monitors.iter_mut().find(|m| m.id() == report.monitor_id) else {
+            report.outcome = TargetOutcome::RollbackFailed(MonitorError::NotFound);
+            continue;
+        };
+
+        if !first {
+            thread::sleep(delay);
+        }
+        first = false;
+        report.outcome = match monitor.set_input(previous) {
+            Ok(()) => TargetOutcome::RolledBack,
+            Err(e) => TargetOutcome::RollbackFailed(e),
+        };
+    }
+    !reports
+        .iter()
+        .any(|r| matches!(r.outcome, TargetOutcome::RollbackFailed(_)))
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "status", content = "error")]
+enum BrightnessOutcome<E> {
+    Set,
+    Failed(E),
+}
+
+fn brightness_to_json<S: Serializer>(
+    result: &Option<Result<(), MonitorError>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match result {
+        None => serializer.serialize_none(),
+        Some(Ok(())) => serializer.serialize_some(&BrightnessOutcome::<()>::Set),
+        Some(Err(e)) => serializer.serialize_some(&BrightnessOutcome::Failed(e)),
+    }
+}
+
+fn brightness_from_json<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Result<(), MonitorError>>, D::Error> {
+    Ok(
+        Option::<BrightnessOutcome<MonitorError>>::deserialize(deserializer)?.map(|outcome| {
+            match outcome {
+                BrightnessOutcome::Set => Ok(()),
+                BrightnessOutcome::Failed(e) => Err(e),
+            }
+        }),
+    )
+}
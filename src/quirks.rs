@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Per-model deviations from standard DDC/CI behaviour. Every field is
+/// optional so that a user entry only overrides what it sets.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Quirks {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_switch_delay_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skip_input_readback: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drops_off_bus: Option<bool>,
+    /// Reported VCP 0x60 value -> standard MCCS value.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub input_read_codes: BTreeMap<u16, u16>,
+    /// Standard MCCS value -> value to write to VCP 0x60.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub input_write_codes: BTreeMap<u16, u16>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuirkEntry {
+    pub manufacturer_id: String,
+    /// Matches every model of the manufacturer when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_name: Option<String>,
+    pub quirks: Quirks,
+}
+
+impl QuirkEntry {
+    pub fn matches(&self, manufacturer_id: Option<&str>, model_name: Option<&str>) -> bool {
+        let manufacturer_matches = manufacturer_id
+            .map(|id| id.eq_ignore_ascii_case(&self.manufacturer_id))
+            .unwrap_or(false);
+        let model_matches = match (&self.model_name, model_name) {
+            (None, _) => true,
+            (Some(expected), Some(actual)) => expected.eq_ignore_ascii_case(actual.trim()),
+            (Some(_), None) => false,
+        };
+        manufacturer_matches && model_matches
+    }
+}
+
+impl Quirks {
+    /// Resolves the quirks for a monitor: built-in entries first, then
+    /// `overrides`, each with manufacturer-wide entries applied before
+    /// model-specific ones so that the most specific user entry wins.
+    pub fn lookup(
+        manufacturer_id: Option<&str>,
+        model_name: Option<&str>,
+        overrides: &[QuirkEntry],
+    ) -> Quirks {
+        let mut quirks = Quirks::default();
+        for table in [builtin_quirks().as_slice(), overrides] {
+            let mut matching: Vec<&QuirkEntry> = table
+                .iter()
+                .filter(|entry| entry.matches(manufacturer_id, model_name))
+                .collect();
+            matching.sort_by_key(|entry| entry.model_name.is_some());
+
+            for entry in matching {
+                quirks.merge(&entry.quirks);
+            }
+        }
+        quirks
+    }
+
+    pub fn merge(&mut self, other: &Quirks) {
+        if other.input_switch_delay_ms.is_some() {
+            self.input_switch_delay_ms = other.input_switch_delay_ms;
+        }
+        if other.skip_input_readback.is_some() {
+            self.skip_input_readback = other.skip_input_readback;
+        }
+        if other.drops_off_bus.is_some() {
+            self.drops_off_bus = other.drops_off_bus;
+        }
+        self.input_read_codes.extend(&other.input_read_codes);
+        self.input_write_codes.extend(&other.input_write_codes);
+    }
+
+    pub fn input_switch_delay(&self) -> Option<Duration> {
+        self.input_switch_delay_ms
+            .filter(|&ms| ms > 0)
+            .map(Duration::from_millis)
+    }
+
+    pub fn skips_input_readback(&self) -> bool {
+        self.skip_input_readback.unwrap_or(false)
+    }
+
+    pub fn drops_off_bus(&self) -> bool {
+        self.drops_off_bus.unwrap_or(false)
+    }
+
+    pub fn input_from_monitor(&self, value: u16) -> u16 {
+        self.input_read_codes.get(&value).copied().unwrap_or(value)
+    }
+
+    pub fn input_to_monitor(&self, value: u16) -> u16 {
+        self.input_write_codes.get(&value).copied().unwrap_or(value)
+    }
+}
+
+pub fn builtin_quirks() -> Vec<QuirkEntry> {
+    vec![
+        // LG 27UK850 and siblings report inputs with LG's own codes.
+        QuirkEntry {
+            manufacturer_id: "GSM".to_string(),
+            model_name: Some("LG HDR 4K".to_string()),
+            quirks: Quirks {
+                input_read_codes: BTreeMap::from([
+                    (0x90, 0x11),
+                    (0x91, 0x12),
+                    (0xD0, 0x0F),
+                    (0xD1, 0x15),
+                ]),
+                ..Quirks::default()
+            },
+        },
+    ]
+}
@@ -0,0 +1,212 @@
+use std::fmt;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::{Config, InputSource, Monitor};
+
+/// Picks a monitor out of the enumerated ones, for scripts and the command
+/// line.
+///
+/// Parsed from:
+/// - `2`: the second monitor in enumeration order (1-based)
+/// - `id:DEL-A0F4-ABC123`, `serial:ABC123`, `alias:Left`, `model:dell`:
+///   only that kind of match
+/// - anything else is tried as an id, an alias, a serial and a model name
+///   substring, in that order, case-insensitively except for the id
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MonitorSelector {
+    Index(usize),
+    Id(String),
+    Serial(String),
+    Alias(String),
+    Model(String),
+    Any(String),
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SelectorError {
+    #[error("Empty selector")]
+    Empty,
+    #[error("No monitor matches \"{0}\"")]
+    NoMonitor(String),
+    #[error("\"{selector}\" matches several monitors: {}", matches.join(", "))]
+    AmbiguousMonitor {
+        selector: String,
+        matches: Vec<String>,
+    },
+    #[error("Unknown input \"{0}\"")]
+    UnknownInput(String),
+    #[error("\"{selector}\" names several inputs: {}", matches.join(", "))]
+    AmbiguousInput {
+        selector: String,
+        matches: Vec<String>,
+    },
+}
+
+impl FromStr for MonitorSelector {
+    type Err = SelectorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(SelectorError::Empty);
+        }
+        if let Ok(index) = s.parse::<usize>() {
+            return Ok(MonitorSelector::Index(index));
+        }
+        let selector = match s.split_once(':') {
+            Some(("id", id)) => MonitorSelector::Id(id.to_string()),
+            Some(("serial", serial)) => MonitorSelector::Serial(serial.to_string()),
+            Some(("alias", alias)) => MonitorSelector::Alias(alias.to_string()),
+            Some(("model", model)) => MonitorSelector::Model(model.to_string()),
+            _ => MonitorSelector::Any(s.to_string()),
+        };
+        Ok(selector)
+    }
+}
+
+impl fmt::Display for MonitorSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MonitorSelector::Index(index) => write!(f, "{}", index),
+            MonitorSelector::Id(id) => write!(f, "id:{}", id),
+            MonitorSelector::Serial(serial) => write!(f, "serial:{}", serial),
+            MonitorSelector::Alias(alias) => write!(f, "alias:{}", alias),
+            MonitorSelector::Model(model) => write!(f, "model:{}", model),
+            MonitorSelector::Any(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+impl MonitorSelector {
+    /// The position in `monitors` of the one monitor this selects. Aliases
+    /// are looked up in `config`.
+    pub fn resolve(&self, monitors: &[Monitor], config: &Config) -> Result<usize, SelectorError> {
+        let found = match self {
+            MonitorSelector::Index(index) => {
+                (1..=monitors.len()).contains(index).then(|| index - 1)
+            }
+            MonitorSelector::Id(id) => self.unique(monitors, |m| matches_id(m, id))?,
+            MonitorSelector::Serial(serial) => {
+                self.unique(monitors, |m| matches_serial(m, serial))?
+            }
+            MonitorSelector::Alias(alias) => {
+                self.unique(monitors, |m| matches_alias(m, config, alias))?
+            }
+            MonitorSelector::Model(model) => self.unique(monitors, |m| matches_model(m, model))?,
+            // Stops at the first kind that matches anything, so a selector
+            // that is ambiguous as an alias isn't quietly taken as a model.
+            MonitorSelector::Any(s) => {
+                let kinds: [&dyn Fn(&Monitor) -> bool; 4] = [
+                    &|m| matches_id(m, s),
+                    &|m| matches_alias(m, config, s),
+                    &|m| matches_serial(m, s),
+                    &|m| matches_model(m, s),
+                ];
+                let mut found = None;
+                for matches in kinds {
+                    found = self.unique(monitors, matches)?;
+                    if found.is_some() {
+                        break;
+                    }
+                }
+                found
+            }
+        };
+        found.ok_or_else(|| SelectorError::NoMonitor(self.to_string()))
+    }
+
+    fn unique(
+        &self,
+        monitors: &[Monitor],
+        matches: impl Fn(&Monitor) -> bool,
+    ) -> Result<Option<usize>, SelectorError> {
+        let found: Vec<usize> = (0..monitors.len())
+            .filter(|&i| matches(&monitors[i]))
+            .collect();
+        match found[..] {
+            [] => Ok(None),
+            [index] => Ok(Some(index)),
+            _ => Err(SelectorError::AmbiguousMonitor {
+                selector: self.to_string(),
+                matches: found.iter().map(|&i| monitors[i].id()).collect(),
+            }),
+        }
+    }
+}
+
+/// Resolves an input of the monitor with `monitor_id`: a VCP value in
+/// decimal or hex, one of the user's aliases for it, or a name that
+/// `InputSource` parses. Aliases win over names, so an alias can't be
+/// shadowed by a standard name it happens to share.
+pub fn resolve_input(
+    config: &Config,
+    monitor_id: &str,
+    selector: &str,
+) -> Result<InputSource, SelectorError> {
+    let selector = selector.trim();
+    if let Some(value) = parse_value(selector) {
+        return Ok(InputSource::from_vcp_value(value));
+    }
+
+    let aliases = config.monitors.get(monitor_id).map(|m| &m.input_aliases);
+    let mut aliased: Vec<u16> = aliases
+        .into_iter()
+        .flatten()
+        .filter(|(_, alias)| alias.trim().eq_ignore_ascii_case(selector))
+        .map(|(&value, _)| value)
+        .collect();
+    aliased.sort_unstable();
+    match aliased[..] {
+        [value] => Ok(InputSource::from_vcp_value(value)),
+        [] => selector.parse(),
+        _ => Err(SelectorError::AmbiguousInput {
+            selector: selector.to_string(),
+            matches: aliased
+                .iter()
+                .map(|&v| InputSource::from_vcp_value(v).name().into_owned())
+                .collect(),
+        }),
+    }
+}
+
+/// A VCP value in decimal or `0x` hex.
+pub(crate) fn parse_value(s: &str) -> Option<u16> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// Lower case without spaces or punctuation, so "USB-C 1" matches "usbc1".
+pub(crate) fn normalize(s: &str) -> String {
+    s.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn matches_id(monitor: &Monitor, id: &str) -> bool {
+    monitor.id() == id || monitor.legacy_id() == id
+}
+
+fn matches_serial(monitor: &Monitor, serial: &str) -> bool {
+    monitor
+        .serial_number()
+        .is_some_and(|s| s.eq_ignore_ascii_case(serial.trim()))
+}
+
+fn matches_alias(monitor: &Monitor, config: &Config, alias: &str) -> bool {
+    config
+        .get_monitor_alias(&monitor.id())
+        .is_some_and(|a| a.trim().eq_ignore_ascii_case(alias.trim()))
+}
+
+fn matches_model(monitor: &Monitor, model: &str) -> bool {
+    let model = model.trim().to_lowercase();
+    !model.is_empty()
+        && monitor
+            .model_name()
+            .is_some_and(|name| name.to_lowercase().contains(&model))
+}
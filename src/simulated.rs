@@ -0,0 +1,313 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use crate::{Backend, Device, DeviceInfo, MonitorError, VcpFeature};
+
+const VCP_INPUT_SELECT: u8 = 0x60;
+
+/// An in-memory DDC backend for tests and demos. Monitors added to it keep
+/// their state across enumerations, so a test can switch an input through
+/// `Monitor` and then inspect the result through the returned handle.
+#[derive(Clone, Default)]
+pub struct SimulatedBackend {
+    monitors: Arc<Mutex<Vec<SimulatedMonitorHandle>>>,
+}
+
+impl SimulatedBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_monitor(&self, monitor: SimulatedMonitor) -> SimulatedMonitorHandle {
+        let handle = SimulatedMonitorHandle {
+            state: Arc::new(Mutex::new(monitor)),
+        };
+        self.monitors.lock().unwrap().push(handle.clone());
+        handle
+    }
+}
+
+impl Backend for SimulatedBackend {
+    fn enumerate(&self) -> Vec<Box<dyn Device>> {
+        self.monitors
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|handle| handle.state().connected)
+            .map(|handle| {
+                let info = handle.state().info.clone();
+                Box::new(SimulatedDevice {
+                    info,
+                    handle: handle.clone(),
+                }) as Box<dyn Device>
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SimulatedMonitor {
+    info: DeviceInfo,
+    features: BTreeMap<u8, VcpFeature>,
+    inputs: Vec<u16>,
+    capabilities: Option<String>,
+    latency: Duration,
+    connected: bool,
+    host_input: Option<u16>,
+    failing_reads: u32,
+    failing_writes: u32,
+    ignored_writes: u32,
+    writes: Vec<(u8, u16)>,
+}
+
+impl SimulatedMonitor {
+    pub fn new(manufacturer_id: &str, model_name: &str) -> Self {
+        SimulatedMonitor {
+            info: DeviceInfo {
+                id: format!("sim-{}", model_name.to_lowercase().replace(' ', "-")),
+                manufacturer_id: Some(manufacturer_id.to_string()),
+                model_name: Some(model_name.to_string()),
+                ..DeviceInfo::default()
+            },
+            features: BTreeMap::new(),
+            inputs: Vec::new(),
+            capabilities: None,
+            latency: Duration::ZERO,
+            connected: true,
+            host_input: None,
+            failing_reads: 0,
+            failing_writes: 0,
+            ignored_writes: 0,
+            writes: Vec::new(),
+        }
+    }
+
+    pub fn id(mut self, id: &str) -> Self {
+        self.info.id = id.to_string();
+        self
+    }
+
+    pub fn serial_number(mut self, serial: &str) -> Self {
+        self.info.serial_number = Some(serial.to_string());
+        self
+    }
+
+    /// EDID product code. Together with the manufacturer this gives the
+    /// monitor an EDID-based id instead of the legacy one.
+    pub fn product_code(mut self, code: u16) -> Self {
+        self.info.model_id = Some(code);
+        self
+    }
+
+    pub fn serial(mut self, serial: u32) -> Self {
+        self.info.serial = Some(serial);
+        self
+    }
+
+    /// `year` is the full year, e.g. 2021.
+    pub fn manufactured(mut self, week: u8, year: u16) -> Self {
+        self.info.manufacture_week = Some(week);
+        self.info.manufacture_year = Some(year.saturating_sub(1990) as u8);
+        self
+    }
+
+    pub fn edid(mut self, edid: &[u8]) -> Self {
+        self.info.edid = Some(edid.to_vec());
+        self
+    }
+
+    /// Inputs listed for VCP 0x60 in the generated capabilities string. The
+    /// first one becomes the current input.
+    pub fn inputs(mut self, inputs: &[u16]) -> Self {
+        self.inputs = inputs.to_vec();
+        if let Some(&first) = inputs.first() {
+            self = self.feature(VCP_INPUT_SELECT, first, 0);
+        }
+        self
+    }
+
+    pub fn current_input(self, value: u16) -> Self {
+        self.feature(VCP_INPUT_SELECT, value, 0)
+    }
+
+    pub fn feature(mut self, code: u8, current: u16, maximum: u16) -> Self {
+        self.features.insert(code, VcpFeature { current, maximum });
+        self
+    }
+
+    /// Replaces the generated capabilities string, e.g. with a malformed one.
+    pub fn capabilities(mut self, raw: &str) -> Self {
+        self.capabilities = Some(raw.to_string());
+        self
+    }
+
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Stops answering once switched away from `input`, like monitors that
+    /// only listen to DDC/CI on the active input.
+    pub fn host_input(mut self, input: u16) -> Self {
+        self.host_input = Some(input);
+        self
+    }
+
+    fn capabilities_string(&self) -> String {
+        if let Some(raw) = &self.capabilities {
+            return raw.clone();
+        }
+
+        let vcp: Vec<String> = self
+            .features
+            .keys()
+            .map(|&code| {
+                if code == VCP_INPUT_SELECT && !self.inputs.is_empty() {
+                    let values: Vec<String> =
+                        self.inputs.iter().map(|v| format!("{:02X}", v)).collect();
+                    format!("{:02X}({})", code, values.join(" "))
+                } else {
+                    format!("{:02X}", code)
+                }
+            })
+            .collect();
+
+        format!(
+            "(prot(monitor)type(lcd)model({})cmds(01 02 03 0C E3 F3)vcp({})mccs_ver(2.2))",
+            self.info.model_name.as_deref().unwrap_or("SIM"),
+            vcp.join(" ")
+        )
+    }
+}
+
+/// Shared view of a simulated monitor's state, used to inspect writes and
+/// inject failures while a `Monitor` is talking to it.
+#[derive(Clone)]
+pub struct SimulatedMonitorHandle {
+    state: Arc<Mutex<SimulatedMonitor>>,
+}
+
+impl SimulatedMonitorHandle {
+    fn state(&self) -> MutexGuard<'_, SimulatedMonitor> {
+        self.state.lock().unwrap()
+    }
+
+    pub fn vcp(&self, code: u8) -> Option<u16> {
+        self.state().features.get(&code).map(|f| f.current)
+    }
+
+    pub fn input(&self) -> Option<u16> {
+        self.vcp(VCP_INPUT_SELECT)
+    }
+
+    /// Changes a value as if from the monitor's OSD, without recording a write.
+    pub fn set_vcp(&self, code: u8, value: u16) {
+        let mut state = self.state();
+        let maximum = state.features.get(&code).map(|f| f.maximum).unwrap_or(0);
+        state.features.insert(
+            code,
+            VcpFeature {
+                current: value,
+                maximum,
+            },
+        );
+    }
+
+    pub fn writes(&self) -> Vec<(u8, u16)> {
+        self.state().writes.clone()
+    }
+
+    pub fn set_connected(&self, connected: bool) {
+        self.state().connected = connected;
+    }
+
+    pub fn fail_next_reads(&self, count: u32) {
+        self.state().failing_reads = count;
+    }
+
+    pub fn fail_next_writes(&self, count: u32) {
+        self.state().failing_writes = count;
+    }
+
+    /// Acknowledges the next `count` writes without applying them, like a
+    /// monitor that drops commands while busy.
+    pub fn ignore_next_writes(&self, count: u32) {
+        self.state().ignored_writes = count;
+    }
+}
+
+struct SimulatedDevice {
+    info: DeviceInfo,
+    handle: SimulatedMonitorHandle,
+}
+
+impl SimulatedDevice {
+    fn begin(&self) -> Result<MutexGuard<'_, SimulatedMonitor>, MonitorError> {
+        let latency = self.handle.state().latency;
+        if !latency.is_zero() {
+            thread::sleep(latency);
+        }
+
+        let state = self.handle.state();
+        if !state.connected {
+            return Err(MonitorError::DdcError(
+                "Simulated monitor disconnected".to_string(),
+            ));
+        }
+        let current_input = state.features.get(&VCP_INPUT_SELECT).map(|f| f.current);
+        if state.host_input.is_some() && current_input != state.host_input {
+            return Err(MonitorError::DdcError(
+                "Simulated monitor is showing another input".to_string(),
+            ));
+        }
+        Ok(state)
+    }
+}
+
+impl Device for SimulatedDevice {
+    fn info(&self) -> &DeviceInfo {
+        &self.info
+    }
+
+    fn get_vcp_feature(&mut self, code: u8) -> Result<VcpFeature, MonitorError> {
+        let mut state = self.begin()?;
+        if state.failing_reads > 0 {
+            state.failing_reads -= 1;
+            return Err(MonitorError::DdcError("Simulated read failure".to_string()));
+        }
+        state
+            .features
+            .get(&code)
+            .copied()
+            .ok_or(MonitorError::NotSupported)
+    }
+
+    fn set_vcp_feature(&mut self, code: u8, value: u16) -> Result<(), MonitorError> {
+        let mut state = self.begin()?;
+        if state.failing_writes > 0 {
+            state.failing_writes -= 1;
+            return Err(MonitorError::DdcError(
+                "Simulated write failure".to_string(),
+            ));
+        }
+
+        if !state.features.contains_key(&code) {
+            return Err(MonitorError::NotSupported);
+        }
+
+        state.writes.push((code, value));
+        if state.ignored_writes > 0 {
+            state.ignored_writes -= 1;
+        } else if let Some(feature) = state.features.get_mut(&code) {
+            feature.current = value;
+        }
+        Ok(())
+    }
+
+    fn capabilities_string(&mut self) -> Result<Vec<u8>, MonitorError> {
+        let state = self.begin()?;
+        Ok(state.capabilities_string().into_bytes())
+    }
+}
@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{EdidInfo, InputListSource, InputSource, Monitor, MonitorError, PowerMode};
+
+/// What is known about a monitor at one point in time: what the daemon
+/// caches and sends to its clients. Settings that weren't read, or that the
+/// monitor doesn't support, are `None`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MonitorSnapshot {
+    /// Position in enumeration order, starting at 0.
+    pub index: usize,
+    pub id: String,
+    pub model_name: Option<String>,
+    pub manufacturer_id: Option<String>,
+    pub serial_number: Option<String>,
+    #[serde(default)]
+    pub edid: Option<EdidInfo>,
+    pub current_input: Option<InputSource>,
+    pub inputs: Vec<InputSource>,
+    pub input_list_source: Option<InputListSource>,
+    pub brightness: Option<u8>,
+    pub contrast: Option<u8>,
+    pub volume: Option<u8>,
+    pub muted: Option<bool>,
+    /// `Unknown` if the monitor supports power modes but the read failed.
+    pub power_mode: Option<PowerMode>,
+}
+
+impl MonitorSnapshot {
+    /// Only what identifies the monitor, without talking to it.
+    pub fn new(monitor: &Monitor, index: usize) -> Self {
+        MonitorSnapshot {
+            index,
+            id: monitor.id(),
+            model_name: monitor.model_name(),
+            manufacturer_id: monitor.manufacturer_id(),
+            serial_number: monitor.serial_number(),
+            edid: monitor.edid(),
+            current_input: None,
+            inputs: Vec::new(),
+            input_list_source: None,
+            brightness: None,
+            contrast: None,
+            volume: None,
+            muted: None,
+            power_mode: None,
+        }
+    }
+
+    /// Reads the current input, the available inputs and every setting.
+    pub fn read(monitor: &mut Monitor, index: usize) -> Self {
+        let available = monitor.get_available_inputs_with_source();
+        MonitorSnapshot {
+            current_input: monitor.get_current_input().ok(),
+            inputs: available.inputs,
+            input_list_source: Some(available.source),
+            brightness: monitor.brightness().ok(),
+            contrast: monitor.contrast().ok(),
+            volume: monitor.volume().ok(),
+            muted: monitor.is_muted().ok(),
+            power_mode: match monitor.power_mode() {
+                Ok(mode) => Some(mode),
+                Err(MonitorError::NotSupported) => None,
+                Err(_) => Some(PowerMode::Unknown),
+            },
+            ..Self::new(monitor, index)
+        }
+    }
+
+    /// The model name, else the manufacturer, else "Monitor N".
+    pub fn name(&self) -> String {
+        self.model_name
+            .clone()
+            .or_else(|| self.manufacturer_id.clone())
+            .unwrap_or_else(|| format!("Monitor {}", self.index + 1))
+    }
+
+    /// Records a change made through `setting`.
+    pub fn update(&mut self, setting: Setting) {
+        match setting {
+            Setting::Brightness(percent) => self.brightness = Some(percent),
+            Setting::Contrast(percent) => self.contrast = Some(percent),
+            Setting::Volume(percent) => self.volume = Some(percent),
+            Setting::Muted(muted) => self.muted = Some(muted),
+            Setting::PowerMode(mode) => self.power_mode = Some(mode),
+        }
+    }
+}
+
+/// A change to one of the settings in a [`MonitorSnapshot`] other than the
+/// input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "setting", content = "value")]
+pub enum Setting {
+    Brightness(u8),
+    Contrast(u8),
+    Volume(u8),
+    Muted(bool),
+    PowerMode(PowerMode),
+}
+
+impl Setting {
+    pub fn apply(self, monitor: &mut Monitor) -> Result<(), MonitorError> {
+        match self {
+            Setting::Brightness(percent) => monitor.set_brightness(percent),
+            Setting::Contrast(percent) => monitor.set_contrast(percent),
+            Setting::Volume(percent) => monitor.set_volume(percent),
+            Setting::Muted(muted) => monitor.set_muted(muted),
+            Setting::PowerMode(mode) => monitor.set_power_mode(mode),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{Config, ConfigError, InputSource, Monitor, MonitorError, SwitchOptions};
+
+/// The input each monitor was on before its last switch, so `toggle_input`
+/// can go back to it. Kept next to the config file but apart from it: it
+/// changes on every switch and isn't something to edit or sync.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputHistory {
+    #[serde(default)]
+    previous_inputs: HashMap<String, u16>,
+}
+
+impl InputHistory {
+    /// `config.state.json` beside `Config::config_path`.
+    pub fn path() -> Option<PathBuf> {
+        Config::config_path().map(|path| path.with_extension("state.json"))
+    }
+
+    /// Loads the history, starting afresh if it is missing or unreadable.
+    pub fn load() -> Self {
+        Self::path()
+            .map(|p| Self::load_from(&p))
+            .unwrap_or_default()
+    }
+
+    pub fn load_from(path: &Path) -> Self {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => {
+                warn!("Could not read {}: {}", path.display(), e);
+                return Self::default();
+            }
+        };
+        serde_json::from_str(&content)
+            .map_err(|e| warn!("Ignoring invalid {}: {}", path.display(), e))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), ConfigError> {
+        let path = Self::path().ok_or(ConfigError::NoConfigDir)?;
+        self.save_to(&path)
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<(), ConfigError> {
+        let io_error = |source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(io_error)?;
+        }
+        let content = serde_json::to_string_pretty(self).expect("history serialises to JSON");
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, content).map_err(io_error)?;
+        fs::rename(&tmp, path).map_err(io_error)
+    }
+
+    pub fn previous_input(&self, monitor_id: &str) -> Option<InputSource> {
+        self.previous_inputs
+            .get(monitor_id)
+            .map(|&value| InputSource::from_vcp_value(value))
+    }
+
+    pub fn record(&mut self, monitor_id: &str, from: InputSource, to: InputSource) {
+        if from != to {
+            self.previous_inputs
+                .insert(monitor_id.to_string(), from.to_vcp_value());
+        }
+    }
+}
+
+/// Switches like `Monitor::set_input_with_options`, recording the input the
+/// monitor was on in `history`.
+pub fn switch_input(
+    monitor: &mut Monitor,
+    input: InputSource,
+    options: &SwitchOptions,
+    history: &mut InputHistory,
+) -> Result<(), MonitorError> {
+    let current = monitor.get_current_input().ok();
+    monitor.set_input_with_options(input, options)?;
+    if let Some(current) = current {
+        history.record(&monitor.id(), current, input);
+    }
+    Ok(())
+}
+
+/// Switches `monitor` to the other input of `pair`, or back to the input it
+/// was on before its last switch when there is no pair. A monitor on
+/// neither input of the pair goes to the first one. With `wake` a monitor in
+/// standby is woken first; otherwise its `switch_options` decide. Returns
+/// the input switched to and records the switch in `history`.
+pub fn toggle_input(
+    monitor: &mut Monitor,
+    pair: Option<(InputSource, InputSource)>,
+    wake: bool,
+    history: &mut InputHistory,
+) -> Result<InputSource, MonitorError> {
+    let current = monitor.get_current_input()?;
+    let target = match pair {
+        Some((first, second)) if current == first => second,
+        Some((first, _)) => first,
+        None => history
+            .previous_input(&monitor.id())
+            .filter(|&previous| previous != current)
+            .ok_or(MonitorError::NoPreviousInput)?,
+    };
+
+    let options = SwitchOptions {
+        ensure_powered_on: wake || monitor.switch_options().ensure_powered_on,
+        ..monitor.switch_options().clone()
+    };
+    monitor.set_input_with_options(target, &options)?;
+    history.record(&monitor.id(), current, target);
+    Ok(target)
+}
@@ -0,0 +1,199 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::config::{written_by_us, Config, ConfigError};
+
+/// How long the watcher thread waits for filesystem events before checking
+/// whether it should stop. Also the polling interval without inotify.
+const WAIT_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigEvent {
+    /// The file has new content written by another process or by hand;
+    /// reload it.
+    Changed,
+    /// The file was deleted or moved away.
+    Removed,
+}
+
+/// Watches a config file for changes made outside this process, e.g. a hand
+/// edit or a sync from another machine. Saves made through `Config` in this
+/// process are not reported. Uses inotify on Linux and polls the file
+/// elsewhere; watching stops when the watcher is dropped.
+pub struct ConfigWatcher {
+    path: PathBuf,
+    events: Receiver<ConfigEvent>,
+    stop: Arc<AtomicBool>,
+}
+
+impl ConfigWatcher {
+    /// Watches the file at `Config::config_path`.
+    pub fn new() -> Result<Self, ConfigError> {
+        let path = Config::config_path().ok_or(ConfigError::NoConfigDir)?;
+        Self::watch(&path)
+    }
+
+    pub fn watch(path: &Path) -> Result<Self, ConfigError> {
+        let io_error = |source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        // Saves replace the file, so watch the directory it lives in.
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        fs::create_dir_all(&dir).map_err(io_error)?;
+        let dir_events = DirEvents::new(&dir).map_err(io_error)?;
+
+        let (sender, events) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let file = path.to_path_buf();
+        let stopped = stop.clone();
+        let mut last = fs::read(&file).ok();
+        thread::Builder::new()
+            .name("config-watcher".into())
+            .spawn(move || {
+                let name = file.file_name().unwrap_or_default().to_os_string();
+                while !stopped.load(Ordering::Relaxed) {
+                    if !dir_events.wait(&name, WAIT_INTERVAL) {
+                        continue;
+                    }
+                    let current = fs::read(&file).ok();
+                    let event = match (&last, &current) {
+                        (Some(_), None) => Some(ConfigEvent::Removed),
+                        (_, Some(content)) if last.as_ref() != Some(content) => {
+                            (!written_by_us(&file, content)).then_some(ConfigEvent::Changed)
+                        }
+                        _ => None,
+                    };
+                    last = current;
+                    if let Some(event) = event {
+                        if sender.send(event).is_err() {
+                            break;
+                        }
+                    }
+                }
+            })
+            .map_err(io_error)?;
+
+        Ok(ConfigWatcher {
+            path: path.to_path_buf(),
+            events,
+            stop,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The next pending event, without blocking.
+    pub fn try_recv(&self) -> Option<ConfigEvent> {
+        self.events.try_recv().ok()
+    }
+
+    /// All pending events, without blocking.
+    pub fn try_iter(&self) -> impl Iterator<Item = ConfigEvent> + '_ {
+        self.events.try_iter()
+    }
+
+    /// Waits up to `timeout` for the next event.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<ConfigEvent> {
+        self.events.recv_timeout(timeout).ok()
+    }
+}
+
+impl Drop for ConfigWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(target_os = "linux")]
+struct DirEvents {
+    fd: std::os::fd::OwnedFd,
+}
+
+#[cfg(target_os = "linux")]
+impl DirEvents {
+    fn new(dir: &Path) -> std::io::Result<Self> {
+        use std::ffi::CString;
+        use std::io;
+        use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+        use std::os::unix::ffi::OsStrExt;
+
+        let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC | libc::IN_NONBLOCK) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let dir = CString::new(dir.as_os_str().as_bytes())?;
+        // Not IN_MODIFY: only look at the file once the writer is done.
+        let mask = libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO | libc::IN_MOVED_FROM | libc::IN_DELETE;
+        if unsafe { libc::inotify_add_watch(fd.as_raw_fd(), dir.as_ptr(), mask) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(DirEvents { fd })
+    }
+
+    /// Waits up to `timeout` for events; true if any was about `name`.
+    fn wait(&self, name: &std::ffi::OsStr, timeout: Duration) -> bool {
+        use std::mem::size_of;
+        use std::os::fd::AsRawFd;
+        use std::os::unix::ffi::OsStrExt;
+
+        let fd = self.fd.as_raw_fd();
+        let mut pollfd = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout_ms = timeout.as_millis().try_into().unwrap_or(i32::MAX);
+        if unsafe { libc::poll(&mut pollfd, 1, timeout_ms) } <= 0 {
+            return false;
+        }
+
+        let mut touched = false;
+        let mut buf = [0u8; 4096];
+        loop {
+            let len = unsafe { libc::read(fd, buf.as_mut_ptr().cast(), buf.len()) };
+            if len <= 0 {
+                break;
+            }
+            let mut offset = 0;
+            while offset + size_of::<libc::inotify_event>() <= len as usize {
+                let event: libc::inotify_event =
+                    unsafe { std::ptr::read_unaligned(buf.as_ptr().add(offset).cast()) };
+                let name_start = offset + size_of::<libc::inotify_event>();
+                let name_end = (name_start + event.len as usize).min(len as usize);
+                let event_name = buf[name_start..name_end].split(|&b| b == 0).next();
+                touched |= event_name == Some(name.as_bytes());
+                offset = name_end;
+            }
+        }
+        touched
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+struct DirEvents;
+
+#[cfg(not(target_os = "linux"))]
+impl DirEvents {
+    fn new(_dir: &Path) -> std::io::Result<Self> {
+        Ok(DirEvents)
+    }
+
+    /// Without inotify, wake up every interval and compare the contents.
+    fn wait(&self, _name: &std::ffi::OsStr, timeout: Duration) -> bool {
+        thread::sleep(timeout);
+        true
+    }
+}
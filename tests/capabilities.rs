@@ -0,0 +1,32 @@
+use monitor_core::{Capabilities, MonitorError};
+
+/// Capabilities string of a Dell U2720Q, trimmed.
+const U2720Q: &[u8] = b"(prot(monitor)type(LCD)model(U2720Q)cmds(01 02 03 07 0C E3 F3)\
+vcp(02 04 05 08 10 12 14(01 04 05 06 08 0B) 16 18 1A 52 60(0F 11 12 1B) AA(01 02 04) \
+D6(01 04 05) DC(00 03 05) FD)mccs_ver(2.1)mswhql(1))";
+
+#[test]
+fn parses_input_values_from_vcp_60() {
+    let caps = Capabilities::parse(U2720Q).unwrap();
+
+    assert_eq!(caps.vcp_values(0x60), Some(&[0x0F, 0x11, 0x12, 0x1B][..]));
+    assert_eq!(caps.vcp_values(0xD6), Some(&[0x01, 0x04, 0x05][..]));
+}
+
+#[test]
+fn features_without_values_are_supported_but_list_nothing() {
+    let caps = Capabilities::parse(U2720Q).unwrap();
+
+    assert!(caps.supports_vcp(0x10));
+    assert_eq!(caps.vcp_values(0x10), Some(&[][..]));
+    assert!(!caps.supports_vcp(0x62));
+    assert_eq!(caps.vcp_values(0x62), None);
+}
+
+#[test]
+fn malformed_strings_are_errors() {
+    assert!(matches!(
+        Capabilities::parse(b"(vcp(60(11"),
+        Err(MonitorError::DdcError(_))
+    ));
+}
@@ -0,0 +1,219 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use monitor_core::{Config, ConfigError, CONFIG_VERSION};
+use tempfile::TempDir;
+
+fn broken_backups(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            let name = path.file_name().unwrap().to_string_lossy();
+            name.starts_with("config.json.broken-")
+        })
+        .collect()
+}
+
+/// Copies a fixture from `tests/fixtures/config` into `dir` as `config.json`.
+fn install_fixture(dir: &Path, name: &str) -> PathBuf {
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/config")
+        .join(name);
+    let path = dir.join("config.json");
+    fs::copy(fixture, &path).unwrap();
+    path
+}
+
+#[test]
+fn missing_file_loads_defaults() {
+    let dir = TempDir::new().unwrap();
+    let config = Config::load_from(&dir.path().join("config.json")).unwrap();
+    assert!(config.monitors.is_empty());
+    assert!(config.favorites.is_empty());
+}
+
+#[test]
+fn syntax_errors_report_their_location() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("config.json");
+    fs::write(&path, "{\n  \"monitors\": {},\n  \"favorites\": [,]\n}\n").unwrap();
+
+    match Config::load_from(&path) {
+        Err(ConfigError::Parse { line, column, .. }) => {
+            assert_eq!((line, column), (3, 17));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    let message = Config::load_from(&path).unwrap_err().to_string();
+    assert!(
+        message.ends_with("line 3, column 17: expected value"),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn saving_over_a_broken_file_backs_it_up() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("config.json");
+    let broken = "{ \"favorites\": [ { \"monitor_id\": \"DEL-A0F4-ABC123\" ";
+    fs::write(&path, broken).unwrap();
+
+    let mut config = Config::default();
+    config.add_favorite("DEL-A0F4-ABC123", 0x11);
+    config.save_to(&path).unwrap();
+
+    let backups = broken_backups(dir.path());
+    assert_eq!(backups.len(), 1);
+    assert_eq!(fs::read_to_string(&backups[0]).unwrap(), broken);
+    assert!(Config::load_from(&path)
+        .unwrap()
+        .is_favorite("DEL-A0F4-ABC123", 0x11));
+
+    config.save_to(&path).unwrap();
+    assert_eq!(broken_backups(dir.path()).len(), 1);
+}
+
+#[test]
+fn updates_leave_a_broken_file_alone() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("config.json");
+    let broken = "{ \"favorites\": [ { \"monitor_id\": \"DEL-A0F4-ABC123\" ";
+    fs::write(&path, broken).unwrap();
+
+    let result = Config::update_at(&path, |c| c.add_favorite("DEL-A0F4-ABC123", 0x11));
+    assert!(matches!(result, Err(ConfigError::Parse { line: 1, .. })));
+    assert_eq!(fs::read(&path).unwrap(), broken.as_bytes());
+    assert!(broken_backups(dir.path()).is_empty());
+}
+
+#[test]
+fn updates_merge_with_changes_saved_by_other_processes() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("config.json");
+
+    // Two instances start from the same file...
+    Config::update_at(&path, |c| {
+        c.set_alias("DEL-A0F4-ABC123", 0x11, "Laptop".into())
+    })
+    .unwrap();
+    let stale = Config::load_from(&path).unwrap();
+
+    // ...one adds a favorite, then the other, still holding its old copy,
+    // changes an alias.
+    Config::update_at(&path, |c| c.add_favorite("DEL-A0F4-ABC123", 0x0F)).unwrap();
+    assert!(!stale.is_favorite("DEL-A0F4-ABC123", 0x0F));
+    let merged = Config::update_at(&path, |c| {
+        c.set_alias("DEL-A0F4-ABC123", 0x0F, "Desktop".into())
+    })
+    .unwrap();
+
+    assert!(merged.is_favorite("DEL-A0F4-ABC123", 0x0F));
+    assert_eq!(merged.get_alias("DEL-A0F4-ABC123", 0x11), Some("Laptop"));
+    assert_eq!(
+        Config::load_from(&path)
+            .unwrap()
+            .get_alias("DEL-A0F4-ABC123", 0x0F),
+        Some("Desktop")
+    );
+}
+
+#[test]
+fn concurrent_updates_are_serialised() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("config.json");
+
+    let threads: Vec<_> = (0..8u16)
+        .map(|i| {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                Config::update_at(&path, |c| c.add_favorite("DEL-A0F4-ABC123", i)).unwrap();
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let config = Config::load_from(&path).unwrap();
+    assert_eq!(config.favorites.len(), 8);
+    let leftovers: Vec<_> = fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.contains(".tmp-"))
+        .collect();
+    assert!(leftovers.is_empty());
+}
+
+#[test]
+fn unversioned_0_2_config_is_migrated() {
+    let dir = TempDir::new().unwrap();
+    let path = install_fixture(dir.path(), "0.2.x.json");
+    let original = fs::read_to_string(&path).unwrap();
+
+    let config = Config::load_from(&path).unwrap();
+    assert_eq!(config.version, CONFIG_VERSION);
+    assert_eq!(
+        config.get_alias("/dev/i2c-4-ABC123", 0x11),
+        Some("Work Laptop")
+    );
+    assert_eq!(config.get_alias("/dev/i2c-4-ABC123", 0x15), Some("MacBook"));
+    assert!(config.is_favorite("/dev/i2c-4-ABC123", 0x11));
+
+    // Loading alone leaves the file untouched; the first save upgrades it
+    // and keeps the original next to it.
+    assert_eq!(fs::read_to_string(&path).unwrap(), original);
+    config.save_to(&path).unwrap();
+    let backup = Config::version_backup_path(&path, 0);
+    assert_eq!(fs::read_to_string(&backup).unwrap(), original);
+
+    // The upgrade is a version bump; the 0.2.x entries are kept as they were.
+    let saved: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    let original_json: serde_json::Value = serde_json::from_str(&original).unwrap();
+    assert_eq!(saved["version"], CONFIG_VERSION);
+    assert_eq!(saved["monitors"], original_json["monitors"]);
+    assert_eq!(saved["favorites"], original_json["favorites"]);
+    assert!(Config::load_from(&path)
+        .unwrap()
+        .is_favorite("/dev/i2c-4-ABC123", 0x11));
+
+    // Later saves don't replace the backup.
+    Config::update_at(&path, |c| c.remove_favorite("/dev/i2c-4-ABC123", 0x11)).unwrap();
+    assert_eq!(fs::read_to_string(&backup).unwrap(), original);
+}
+
+#[test]
+fn current_config_loads_without_backup() {
+    let dir = TempDir::new().unwrap();
+    let path = install_fixture(dir.path(), "v1.json");
+
+    let config = Config::load_from(&path).unwrap();
+    assert_eq!(config.version, 1);
+    assert_eq!(
+        config.get_alias("DEL-A0F4-ABC123", 0x11),
+        Some("Work Laptop")
+    );
+    let verify = config.monitors["DEL-A0F4-ABC123"].verify.as_ref().unwrap();
+    assert_eq!(verify.retries, 2);
+    assert_eq!(config.quirks.len(), 1);
+
+    config.save_to(&path).unwrap();
+    assert!(!Config::version_backup_path(&path, 1).exists());
+}
+
+#[test]
+fn newer_config_is_refused_and_left_alone() {
+    let dir = TempDir::new().unwrap();
+    let path = install_fixture(dir.path(), "future.json");
+    let original = fs::read_to_string(&path).unwrap();
+
+    match Config::load_from(&path) {
+        Err(ConfigError::UnsupportedVersion { version, .. }) => assert_eq!(version, 999),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(Config::update_at(&path, |c| c.add_favorite("DEL-A0F4-ABC123", 0x11)).is_err());
+    assert!(Config::default().save_to(&path).is_err());
+    assert_eq!(fs::read_to_string(&path).unwrap(), original);
+}
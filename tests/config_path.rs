@@ -0,0 +1,50 @@
+//! Kept in its own test binary: it changes process-wide environment
+//! variables and the config path override.
+
+use std::env;
+
+use monitor_core::{Config, CONFIG_PATH_ENV};
+use tempfile::TempDir;
+
+#[test]
+fn config_path_precedence() {
+    let dir = TempDir::new().unwrap();
+    env::remove_var(CONFIG_PATH_ENV);
+    env::set_var("HOME", dir.path().join("home"));
+
+    env::remove_var("XDG_CONFIG_HOME");
+    assert_eq!(
+        Config::config_path(),
+        Some(dir.path().join("home/.config/monitor-switch/config.json"))
+    );
+
+    // Relative XDG paths are invalid and ignored.
+    env::set_var("XDG_CONFIG_HOME", "relative/config");
+    assert_eq!(
+        Config::config_path(),
+        Some(dir.path().join("home/.config/monitor-switch/config.json"))
+    );
+
+    env::set_var("XDG_CONFIG_HOME", dir.path().join("xdg"));
+    assert_eq!(
+        Config::config_path(),
+        Some(dir.path().join("xdg/monitor-switch/config.json"))
+    );
+
+    let from_env = dir.path().join("portable.json");
+    env::set_var(CONFIG_PATH_ENV, &from_env);
+    assert_eq!(Config::config_path(), Some(from_env.clone()));
+
+    let explicit = dir.path().join("second.json");
+    Config::set_config_path(Some(explicit.clone()));
+    assert_eq!(Config::config_path(), Some(explicit.clone()));
+
+    Config::update(|c| c.add_favorite("DEL-A0F4-ABC123", 0x11)).unwrap();
+    assert!(explicit.exists());
+    assert!(!from_env.exists());
+    assert!(Config::load().unwrap().is_favorite("DEL-A0F4-ABC123", 0x11));
+
+    Config::set_config_path(None);
+    assert_eq!(Config::config_path(), Some(from_env));
+    assert!(Config::load().unwrap().favorites.is_empty());
+}
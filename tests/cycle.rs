@@ -0,0 +1,148 @@
+use monitor_core::{
+    active_profile, cycle_favorites, cycle_profiles, ApplyOptions, Config, CycleDirection,
+    InputHistory, InputSource, Monitor, MonitorError, PowerMode, Profile, ProfileTarget,
+    SimulatedBackend, SimulatedMonitor,
+};
+
+fn target(monitor_id: &str, input_value: u16) -> ProfileTarget {
+    ProfileTarget {
+        monitor_id: monitor_id.to_string(),
+        input_value,
+        brightness: None,
+    }
+}
+
+fn profile(name: &str, monitors: Vec<ProfileTarget>) -> Profile {
+    Profile {
+        name: name.into(),
+        monitors,
+    }
+}
+
+#[test]
+fn favorites_cycle_in_order_and_wrap() {
+    let backend = SimulatedBackend::new();
+    let handle = backend.add_monitor(
+        SimulatedMonitor::new("ACM", "Left")
+            .serial_number("L")
+            .current_input(0x01),
+    );
+    let mut monitors = Monitor::enumerate_with_backend(&backend, &Config::default());
+    let monitor = &mut monitors[0];
+    let mut history = InputHistory::default();
+    let mut config = Config::default();
+    config.add_favorite("sim-left-L", 0x11);
+    config.add_favorite("sim-other-O", 0x12);
+    config.add_toggle_favorite("sim-left-L", 0x0F, Some(0x15));
+    config.add_favorite("sim-left-L", 0x11);
+
+    let mut cycle = |direction| cycle_favorites(monitor, &config, direction, true, &mut history);
+    // Not on a favorite yet: forward starts at the first one.
+    assert_eq!(cycle(CycleDirection::Forward).unwrap(), InputSource::HDMI1);
+    assert_eq!(
+        cycle(CycleDirection::Forward).unwrap(),
+        InputSource::DisplayPort1
+    );
+    assert_eq!(cycle(CycleDirection::Forward).unwrap(), InputSource::USBC1);
+    assert_eq!(cycle(CycleDirection::Forward).unwrap(), InputSource::HDMI1);
+    assert_eq!(cycle(CycleDirection::Backward).unwrap(), InputSource::USBC1);
+    assert_eq!(handle.input(), Some(0x15));
+
+    handle.set_vcp(0x60, 0x01);
+    assert_eq!(cycle(CycleDirection::Backward).unwrap(), InputSource::USBC1);
+    assert_eq!(
+        history.previous_input("sim-left-L"),
+        Some(InputSource::VGA1)
+    );
+
+    assert!(matches!(
+        cycle_favorites(
+            monitor,
+            &Config::default(),
+            CycleDirection::Forward,
+            true,
+            &mut history
+        ),
+        Err(MonitorError::NoFavorites)
+    ));
+}
+
+#[test]
+fn cycling_only_wakes_a_sleeping_monitor_when_asked() {
+    let backend = SimulatedBackend::new();
+    let handle = backend.add_monitor(
+        SimulatedMonitor::new("ACM", "Sleepy")
+            .serial_number("S")
+            .current_input(0x11)
+            .feature(0xD6, PowerMode::Standby.to_vcp_value(), 5),
+    );
+    let mut monitor = Monitor::enumerate_with_backend(&backend, &Config::default()).remove(0);
+    let mut config = Config::default();
+    config.add_favorite("sim-sleepy-S", 0x11);
+    config.add_favorite("sim-sleepy-S", 0x0F);
+
+    let mut history = InputHistory::default();
+    let input = cycle_favorites(
+        &mut monitor,
+        &config,
+        CycleDirection::Forward,
+        false,
+        &mut history,
+    );
+    assert_eq!(input.unwrap(), InputSource::DisplayPort1);
+    assert_eq!(handle.writes(), vec![(0x60, 0x0F)]);
+}
+
+#[test]
+fn profiles_cycle_from_the_active_one() {
+    let backend = SimulatedBackend::new();
+    let left = backend.add_monitor(
+        SimulatedMonitor::new("ACM", "Left")
+            .serial_number("L")
+            .current_input(0x11),
+    );
+    backend.add_monitor(
+        SimulatedMonitor::new("ACM", "Right")
+            .serial_number("R")
+            .current_input(0x11),
+    );
+    let mut monitors = Monitor::enumerate_with_backend(&backend, &Config::default());
+    let mut config = Config::default();
+    config.set_profile(profile(
+        "Desktop",
+        vec![target("sim-left-L", 0x11), target("sim-right-R", 0x11)],
+    ));
+    // A monitor that isn't connected doesn't stop a profile being active.
+    config.set_profile(profile(
+        "Laptop",
+        vec![
+            target("sim-left-L", 0x0F),
+            target("sim-right-R", 0x0F),
+            target("sim-gone-G", 0x0F),
+        ],
+    ));
+    config.set_profile(profile("Elsewhere", vec![target("sim-gone-G", 0x11)]));
+    let options = ApplyOptions::default();
+
+    assert_eq!(active_profile(&mut monitors, &config.profiles), Some(0));
+    let report = cycle_profiles(&mut monitors, &config, CycleDirection::Forward, &options);
+    assert_eq!(report.unwrap().profile, "Laptop");
+    assert_eq!(left.input(), Some(0x0F));
+    assert_eq!(active_profile(&mut monitors, &config.profiles), Some(1));
+
+    // Going back from "Laptop" lands on "Desktop"; "Elsewhere" has no
+    // connected monitors, so it is never active.
+    let report = cycle_profiles(&mut monitors, &config, CycleDirection::Backward, &options);
+    assert_eq!(report.unwrap().profile, "Desktop");
+    let report = cycle_profiles(&mut monitors, &config, CycleDirection::Backward, &options);
+    assert_eq!(report.unwrap().profile, "Elsewhere");
+    assert_eq!(active_profile(&mut monitors, &config.profiles), Some(0));
+
+    assert!(cycle_profiles(
+        &mut monitors,
+        &Config::default(),
+        CycleDirection::Forward,
+        &options
+    )
+    .is_none());
+}
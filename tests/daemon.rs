@@ -0,0 +1,368 @@
+#![cfg(unix)]
+
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use monitor_core::{
+    ApplyOptions, Backend, Config, CycleDirection, Daemon, DaemonClient, DaemonError,
+    DaemonOptions, Device, InputSource, MonitorError, PowerMode, Profile, ProfileTarget, Setting,
+    SimulatedBackend, SimulatedMonitor, SimulatedMonitorHandle, TargetOutcome,
+};
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+/// Counts enumerations, which the daemon should only do when it has to.
+#[derive(Clone, Default)]
+struct CountingBackend {
+    inner: SimulatedBackend,
+    enumerations: Arc<AtomicUsize>,
+}
+
+impl CountingBackend {
+    fn enumerations(&self) -> usize {
+        self.enumerations.load(Ordering::SeqCst)
+    }
+}
+
+impl Backend for CountingBackend {
+    fn enumerate(&self) -> Vec<Box<dyn Device>> {
+        self.enumerations.fetch_add(1, Ordering::SeqCst);
+        self.inner.enumerate()
+    }
+}
+
+fn desk(backend: &SimulatedBackend) -> (SimulatedMonitorHandle, SimulatedMonitorHandle) {
+    let monitor = |name: &str| {
+        SimulatedMonitor::new("ACM", name)
+            .serial_number(&name[..1])
+            .inputs(&[0x0F, 0x11, 0x12])
+            .current_input(0x11)
+            .feature(0x10, 50, 100)
+    };
+    (
+        backend.add_monitor(monitor("Left")),
+        backend.add_monitor(monitor("Right")),
+    )
+}
+
+fn start(dir: &Path, backend: impl Backend + Send + 'static) -> PathBuf {
+    let socket = dir.join("daemon.sock");
+    let options = DaemonOptions {
+        config_path: Some(dir.join("config.json")),
+        history_path: Some(dir.join("config.state.json")),
+    };
+    let daemon = Daemon::bind(&socket, backend, options).unwrap();
+    thread::spawn(move || daemon.serve());
+    socket
+}
+
+fn write_config(dir: &Path, config: &Config) {
+    fs::write(
+        dir.join("config.json"),
+        serde_json::to_string(config).unwrap(),
+    )
+    .unwrap();
+}
+
+fn profile(name: &str, input_value: u16) -> Profile {
+    let target = |monitor_id: &str| ProfileTarget {
+        monitor_id: monitor_id.to_string(),
+        input_value,
+        brightness: None,
+    };
+    Profile {
+        name: name.into(),
+        monitors: vec![target("sim-left-L"), target("sim-right-R")],
+    }
+}
+
+#[test]
+fn clients_share_one_enumeration() {
+    let dir = TempDir::new().unwrap();
+    let backend = CountingBackend::default();
+    let (left, _right) = desk(&backend.inner);
+    let socket = start(dir.path(), backend.clone());
+
+    let mut status_bar = DaemonClient::connect(&socket).unwrap();
+    let monitors = status_bar.list().unwrap();
+    assert_eq!(monitors.len(), 2);
+    assert_eq!(monitors[0].id, "sim-left-L");
+    assert_eq!(monitors[0].current_input, Some(InputSource::HDMI1));
+    assert_eq!(monitors[0].inputs.len(), 3);
+    assert_eq!(monitors[0].brightness, Some(50));
+
+    let mut cli = DaemonClient::connect(&socket).unwrap();
+    let monitor = cli.set_input("left", "dp1", true).unwrap();
+    assert_eq!(monitor.current_input, Some(InputSource::DisplayPort1));
+    assert_eq!(left.input(), Some(0x0F));
+    // Other clients see the switch without reading the monitor again.
+    let monitors = status_bar.list().unwrap();
+    assert_eq!(monitors[0].current_input, Some(InputSource::DisplayPort1));
+
+    let monitor = cli.toggle_input("1", None, true).unwrap();
+    assert_eq!(monitor.current_input, Some(InputSource::HDMI1));
+    assert!(dir.path().join("config.state.json").exists());
+    let monitor = cli.set("id:sim-left-L", Setting::Brightness(80)).unwrap();
+    assert_eq!(monitor.brightness, Some(80));
+    assert_eq!(left.vcp(0x10), Some(80));
+
+    // Switched from the monitor's own buttons: only reading it again shows.
+    left.set_vcp(0x60, 0x12);
+    assert_eq!(
+        status_bar.list().unwrap()[0].current_input,
+        Some(InputSource::HDMI1)
+    );
+    assert_eq!(
+        status_bar.get("left").unwrap().current_input,
+        Some(InputSource::HDMI2)
+    );
+
+    let clients: Vec<_> = (0..4)
+        .map(|i| {
+            let socket = socket.clone();
+            thread::spawn(move || {
+                let mut client = DaemonClient::connect(&socket).unwrap();
+                let input = if i % 2 == 0 { "hdmi1" } else { "dp1" };
+                for _ in 0..5 {
+                    client.set_input("right", input, true).unwrap();
+                }
+            })
+        })
+        .collect();
+    for client in clients {
+        client.join().unwrap();
+    }
+
+    assert_eq!(backend.enumerations(), 1);
+    assert_eq!(cli.refresh().unwrap().len(), 2);
+    assert_eq!(backend.enumerations(), 2);
+}
+
+#[test]
+fn errors_keep_their_kind() {
+    let dir = TempDir::new().unwrap();
+    let backend = SimulatedBackend::new();
+    let (left, _right) = desk(&backend);
+    let socket = start(dir.path(), backend);
+    let mut client = DaemonClient::connect(&socket).unwrap();
+
+    let error = client.set_input("nope", "hdmi1", true).unwrap_err();
+    assert!(matches!(error, DaemonError::NoMonitor(_)), "{:?}", error);
+    let error = client.set_input("left", "composite 9", true).unwrap_err();
+    assert!(
+        matches!(error, DaemonError::InvalidParams(_)),
+        "{:?}",
+        error
+    );
+    let error = client.cycle_input("left", CycleDirection::Forward, true);
+    assert!(matches!(
+        error,
+        Err(DaemonError::Monitor(MonitorError::NoFavorites))
+    ));
+    let error = client.set("left", Setting::Volume(30));
+    assert!(matches!(
+        error,
+        Err(DaemonError::Monitor(MonitorError::NotSupported))
+    ));
+    left.fail_next_writes(1);
+    match client.set_input("left", "dp1", true) {
+        Err(DaemonError::Monitor(MonitorError::DdcError(detail))) => {
+            assert_eq!(detail, "Simulated write failure")
+        }
+        other => panic!("{:?}", other),
+    }
+    let error = client.call("shutdown", Value::Null).unwrap_err();
+    assert!(matches!(error, DaemonError::Remote { code: -32601, .. }));
+
+    // Status bars can talk to the socket directly.
+    let mut stream = UnixStream::connect(&socket).unwrap();
+    let mut replies = BufReader::new(stream.try_clone().unwrap()).lines();
+    writeln!(stream, "{{ not json").unwrap();
+    let reply: Value = serde_json::from_str(&replies.next().unwrap().unwrap()).unwrap();
+    assert_eq!(reply["error"]["code"], -32700);
+    // Notifications get no reply.
+    writeln!(stream, r#"{{"jsonrpc": "2.0", "method": "list"}}"#).unwrap();
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": "status",
+        "method": "set_input",
+        "params": { "monitor": 2, "input": 15 },
+    });
+    writeln!(stream, "{}", request).unwrap();
+    let reply: Value = serde_json::from_str(&replies.next().unwrap().unwrap()).unwrap();
+    assert_eq!(reply["id"], "status");
+    assert_eq!(reply["result"]["current_input"], 0x0F);
+}
+
+#[test]
+fn set_input_only_wakes_a_sleeping_monitor_when_asked() {
+    let dir = TempDir::new().unwrap();
+    let backend = SimulatedBackend::new();
+    let sleepy = backend.add_monitor(
+        SimulatedMonitor::new("ACM", "Sleepy")
+            .inputs(&[0x0F, 0x11])
+            .current_input(0x11)
+            .feature(0xD6, PowerMode::Standby.to_vcp_value(), 5),
+    );
+    let socket = start(dir.path(), backend);
+    let mut client = DaemonClient::connect(&socket).unwrap();
+
+    let monitor = client.set_input("sleepy", "dp1", false).unwrap();
+    assert_eq!(monitor.current_input, Some(InputSource::DisplayPort1));
+    assert_eq!(sleepy.writes(), vec![(0x60, 0x0F)]);
+}
+
+#[test]
+fn profiles_follow_config_changes() {
+    let dir = TempDir::new().unwrap();
+    let backend = SimulatedBackend::new();
+    let (left, right) = desk(&backend);
+    let mut config = Config::default();
+    config.set_profile(profile("Laptop", 0x0F));
+    write_config(dir.path(), &config);
+    let socket = start(dir.path(), backend);
+    let mut client = DaemonClient::connect(&socket).unwrap();
+
+    let report = client
+        .apply_profile("Laptop", &ApplyOptions::default())
+        .unwrap();
+    assert!(report.is_ok());
+    assert_eq!((left.input(), right.input()), (Some(0x0F), Some(0x0F)));
+    let error = client.apply_profile("Desktop", &ApplyOptions::default());
+    assert!(matches!(error, Err(DaemonError::InvalidParams(_))));
+
+    // Picked up without restarting the daemon.
+    config.set_profile(profile("Desktop", 0x11));
+    write_config(dir.path(), &config);
+    let started = Instant::now();
+    let report = loop {
+        match client.cycle_profile(CycleDirection::Forward, &ApplyOptions::default()) {
+            Ok(report) if report.profile == "Desktop" => break report,
+            _ if started.elapsed() < Duration::from_secs(5) => {
+                thread::sleep(Duration::from_millis(50))
+            }
+            other => panic!("{:?}", other.map(|report| report.profile)),
+        }
+    };
+    assert!(report.is_ok());
+    assert_eq!(left.input(), Some(0x11));
+
+    right.fail_next_writes(10);
+    let options = ApplyOptions {
+        rollback: true,
+        ..ApplyOptions::default()
+    };
+    let report = client.apply_profile("Laptop", &options).unwrap();
+    assert!(report.rolled_back);
+    assert!(matches!(
+        report.monitors[0].outcome,
+        TargetOutcome::RolledBack
+    ));
+    assert!(matches!(
+        report.monitors[1].outcome,
+        TargetOutcome::Failed(MonitorError::DdcError(_))
+    ));
+    assert_eq!(
+        client.list().unwrap()[0].current_input,
+        Some(InputSource::HDMI1)
+    );
+}
+
+#[test]
+fn legacy_ids_are_migrated_on_enumeration() {
+    let dir = TempDir::new().unwrap();
+    let backend = SimulatedBackend::new();
+    backend.add_monitor(
+        SimulatedMonitor::new("ACM", "Legacy")
+            .id("/dev/i2c-3")
+            .product_code(0x1234)
+            .serial(42)
+            .current_input(0x0F),
+    );
+    let mut config = Config::default();
+    config.set_alias("/dev/i2c-3-42", 0x11, "Laptop".to_string());
+    config.add_favorite("/dev/i2c-3-42", 0x11);
+    write_config(dir.path(), &config);
+    let socket = start(dir.path(), backend);
+
+    let mut client = DaemonClient::connect(&socket).unwrap();
+    let monitor = client.set_input("ACM-1234-42", "Laptop", true).unwrap();
+    assert_eq!(monitor.current_input, Some(InputSource::HDMI1));
+    let config = Config::load_from(&dir.path().join("config.json")).unwrap();
+    assert_eq!(config.get_alias("ACM-1234-42", 0x11), Some("Laptop"));
+    assert!(config.is_favorite("ACM-1234-42", 0x11));
+    assert!(!config.monitors.contains_key("/dev/i2c-3-42"));
+}
+
+#[test]
+fn only_one_daemon_listens() {
+    let dir = TempDir::new().unwrap();
+    let socket = start(dir.path(), SimulatedBackend::new());
+    let error = Daemon::bind(&socket, SimulatedBackend::new(), DaemonOptions::default());
+    assert!(matches!(error, Err(DaemonError::AlreadyRunning(_))));
+
+    // A socket left behind by a daemon that was killed is taken over.
+    let stale = dir.path().join("stale.sock");
+    drop(UnixListener::bind(&stale).unwrap());
+    assert!(stale.exists());
+    let options = DaemonOptions {
+        config_path: Some(dir.path().join("config.json")),
+        history_path: Some(dir.path().join("config.state.json")),
+    };
+    let daemon = Daemon::bind(&stale, SimulatedBackend::new(), options).unwrap();
+    drop(daemon);
+    assert!(!stale.exists());
+}
+
+#[test]
+fn only_the_owner_can_reach_the_socket() {
+    let dir = TempDir::new().unwrap();
+    let socket = start(dir.path(), SimulatedBackend::new());
+
+    let mode = fs::metadata(&socket).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+}
+
+#[test]
+fn clients_give_up_on_a_daemon_that_does_not_answer() {
+    let dir = TempDir::new().unwrap();
+    let socket = dir.path().join("stuck.sock");
+    let listener = UnixListener::bind(&socket).unwrap();
+    let stuck = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut writer = stream;
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        // Wedged mid-DDC: answers the first request only after the client
+        // has given up on it.
+        thread::sleep(Duration::from_millis(300));
+        for id in 1..=2 {
+            let reply = json!({ "jsonrpc": "2.0", "id": id, "result": [] });
+            writeln!(writer, "{}", reply).unwrap();
+            line.clear();
+            if id == 1 {
+                reader.read_line(&mut line).unwrap();
+            }
+        }
+    });
+
+    let mut client = DaemonClient::connect(&socket).unwrap();
+    client.set_timeout(Duration::from_millis(100)).unwrap();
+    let started = Instant::now();
+    let error = client.list().unwrap_err();
+    assert!(matches!(error, DaemonError::Timeout(_)), "{:?}", error);
+    assert!(started.elapsed() < Duration::from_millis(300));
+
+    // The late reply is skipped rather than taken for the next one.
+    client.set_timeout(Duration::from_secs(5)).unwrap();
+    assert!(client.list().unwrap().is_empty());
+    stuck.join().unwrap();
+}
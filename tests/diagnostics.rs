@@ -0,0 +1,182 @@
+use std::fs;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::Path;
+
+use monitor_core::{DdcStatus, Diagnostics, MonitorError};
+use tempfile::TempDir;
+
+const UID: u32 = 4242;
+
+/// A fake system with `i2c-dev` loaded and the given `/dev/i2c-N` nodes.
+/// Returns the gid the nodes end up owned by.
+fn fake_system(root: &Path, buses: &[(u32, u32, &str)]) -> u32 {
+    for dir in ["dev", "sys/module/i2c_dev", "proc/self", "etc"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+
+    for &(bus, mode, adapter) in buses {
+        let node = root.join(format!("dev/i2c-{}", bus));
+        fs::write(&node, "").unwrap();
+        fs::set_permissions(&node, fs::Permissions::from_mode(mode)).unwrap();
+
+        let class = root.join(format!("sys/class/i2c-dev/i2c-{}", bus));
+        fs::create_dir_all(&class).unwrap();
+        fs::write(class.join("name"), format!("{}\n", adapter)).unwrap();
+    }
+
+    fs::write(
+        root.join("etc/passwd"),
+        format!(
+            "root:x:0:0::/root:/bin/sh\nalice:x:{}:{}::/home/alice:/bin/sh\n",
+            UID, UID
+        ),
+    )
+    .unwrap();
+    fs::metadata(root.join("dev")).unwrap().gid()
+}
+
+fn write_session(root: &Path, groups: &[u32]) {
+    let groups: Vec<String> = groups.iter().map(|g| g.to_string()).collect();
+    fs::write(
+        root.join("proc/self/status"),
+        format!(
+            "Name:\tmonitor-switch\nUid:\t{uid}\t{uid}\t{uid}\t{uid}\nGid:\t{uid}\t{uid}\t{uid}\t{uid}\nGroups:\t{}\n",
+            groups.join(" "),
+            uid = UID
+        ),
+    )
+    .unwrap();
+}
+
+fn write_groups(root: &Path, gid: u32, members: &str) {
+    fs::write(
+        root.join("etc/group"),
+        format!("i2c:x:{}:{}\n", gid, members),
+    )
+    .unwrap();
+}
+
+fn diagnostics(root: &Path) -> Diagnostics {
+    Diagnostics::new()
+        .dev_root(root.join("dev"))
+        .sys_root(root.join("sys"))
+        .proc_root(root.join("proc"))
+        .etc_root(root.join("etc"))
+}
+
+#[test]
+fn reports_missing_i2c_dev() {
+    let root = TempDir::new().unwrap();
+    fs::create_dir_all(root.path().join("dev")).unwrap();
+
+    let report = diagnostics(root.path()).run();
+    assert!(!report.i2c_dev_loaded);
+    assert!(report.buses.is_empty());
+    assert!(matches!(
+        report.problem(),
+        Some(MonitorError::I2cUnavailable)
+    ));
+}
+
+#[test]
+fn reports_permission_problems_and_pending_group_membership() {
+    let root = TempDir::new().unwrap();
+    let gid = fake_system(root.path(), &[(3, 0o660, "AMDGPU DM i2c hw bus 1")]);
+    write_session(root.path(), &[]);
+    write_groups(root.path(), gid, "bob,alice");
+
+    let report = diagnostics(root.path())
+        .probe(|_| panic!("inaccessible buses must not be probed"))
+        .run();
+
+    assert!(report.i2c_dev_loaded);
+    assert_eq!(report.uid, Some(UID));
+    let bus = &report.buses[0];
+    assert_eq!(bus.adapter.as_deref(), Some("AMDGPU DM i2c hw bus 1"));
+    assert!(!bus.readable && !bus.writable);
+    assert_eq!(bus.ddc, DdcStatus::NotProbed);
+
+    let group = &report.groups[0];
+    assert_eq!(group.name.as_deref(), Some("i2c"));
+    assert!(!group.active);
+    assert!(group.listed);
+
+    match report.problem() {
+        Some(MonitorError::PermissionDenied { device }) => assert!(device.ends_with("i2c-3")),
+        other => panic!("unexpected problem: {:?}", other),
+    }
+}
+
+#[test]
+fn probes_accessible_buses() {
+    let root = TempDir::new().unwrap();
+    let gid = fake_system(
+        root.path(),
+        &[
+            (10, 0o660, "SMBus I801 adapter"),
+            (2, 0o660, "i915 gmbus dpb"),
+        ],
+    );
+    write_session(root.path(), &[gid]);
+    write_groups(root.path(), gid, "alice");
+
+    let report = diagnostics(root.path())
+        .probe(|path| {
+            assert!(path.ends_with("i2c-2"), "only display buses are probed");
+            Ok(())
+        })
+        .run();
+
+    let paths: Vec<_> = report.buses.iter().map(|b| b.path.clone()).collect();
+    assert!(paths[0].ends_with("i2c-2") && paths[1].ends_with("i2c-10"));
+    assert_eq!(report.buses[0].ddc, DdcStatus::Responding);
+    assert_eq!(report.buses[1].ddc, DdcStatus::NotDisplay);
+    assert!(report.groups[0].active);
+    assert!(report.problem().is_none());
+}
+
+#[test]
+fn buses_are_only_probed_when_asked() {
+    let root = TempDir::new().unwrap();
+    fake_system(root.path(), &[(7, 0o666, "AUX B/DDI B/PHY B")]);
+    write_session(root.path(), &[]);
+    // DisplayPort AUX channels hang off a DRM connector.
+    let connector = root
+        .path()
+        .join("sys/devices/pci0000:00/0000:00:02.0/drm/card1/card1-DP-1/i2c-7");
+    fs::create_dir_all(&connector).unwrap();
+    std::os::unix::fs::symlink(
+        &connector,
+        root.path().join("sys/class/i2c-dev/i2c-7/device"),
+    )
+    .unwrap();
+
+    let report = diagnostics(root.path()).run();
+    assert_eq!(report.buses[0].ddc, DdcStatus::Skipped);
+    assert!(matches!(
+        report.problem(),
+        Some(MonitorError::DdcUnsupported)
+    ));
+
+    let report = diagnostics(root.path()).probe(|_| Ok(())).run();
+    assert_eq!(report.buses[0].ddc, DdcStatus::Responding);
+}
+
+#[test]
+fn silent_buses_point_at_ddc_ci_settings() {
+    let root = TempDir::new().unwrap();
+    fake_system(root.path(), &[(4, 0o666, "NVIDIA i2c adapter 4")]);
+    write_session(root.path(), &[]);
+
+    let report = diagnostics(root.path())
+        .probe(|_| Err(MonitorError::Timeout))
+        .run();
+
+    assert!(report.buses[0].readable && report.buses[0].writable);
+    assert!(matches!(
+        report.problem(),
+        Some(MonitorError::DdcUnsupported)
+    ));
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["buses"][0]["ddc"]["status"], "no_response");
+}
@@ -0,0 +1,121 @@
+use monitor_core::{
+    Config, EdidInfo, EdidVersion, Monitor, PhysicalSize, Resolution, SimulatedBackend,
+    SimulatedMonitor, VideoInterface,
+};
+
+/// A DELL U2720Q-style EDID 1.4 base block with a DisplayPort input.
+fn dell_edid() -> Vec<u8> {
+    let mut edid = vec![0u8; 128];
+    edid[..8].copy_from_slice(&[0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00]);
+    edid[8..10].copy_from_slice(&[0x10, 0xAC]);
+    edid[10..12].copy_from_slice(&0xA0F4u16.to_le_bytes());
+    edid[12..16].copy_from_slice(&0x4C4E_3032u32.to_le_bytes());
+    edid[16] = 12;
+    edid[17] = 31;
+    edid[18] = 1;
+    edid[19] = 4;
+    edid[20] = 0xA5;
+    edid[21] = 60;
+    edid[22] = 34;
+
+    let timing = &mut edid[54..72];
+    timing[..2].copy_from_slice(&53325u16.to_le_bytes());
+    timing[2] = 0x00;
+    timing[4] = 0xF0;
+    timing[5] = 0x70;
+    timing[7] = 0x80;
+    timing[12] = 0x55;
+    timing[13] = 0x50;
+    timing[14] = 0x21;
+
+    edid[72..90].copy_from_slice(&descriptor(0xFC, "DELL U2720Q"));
+    edid[90..108].copy_from_slice(&descriptor(0xFF, "ABC123"));
+    edid[108..126].copy_from_slice(&descriptor(0x10, ""));
+    edid
+}
+
+fn descriptor(tag: u8, text: &str) -> [u8; 18] {
+    let mut descriptor = [0u8; 18];
+    descriptor[3] = tag;
+    let mut payload = text.as_bytes().to_vec();
+    payload.push(0x0A);
+    payload.resize(13, 0x20);
+    descriptor[5..].copy_from_slice(&payload);
+    descriptor
+}
+
+#[test]
+fn decodes_base_block() {
+    let edid = EdidInfo::parse(&dell_edid()).unwrap();
+
+    assert_eq!(edid.manufacturer_id, "DEL");
+    assert_eq!(edid.product_code, 0xA0F4);
+    assert_eq!(edid.serial, Some(0x4C4E_3032));
+    assert_eq!(edid.serial_number.as_deref(), Some("ABC123"));
+    assert_eq!(edid.model_name.as_deref(), Some("DELL U2720Q"));
+    assert_eq!(edid.manufacture_week, Some(12));
+    assert_eq!(edid.manufacture_year, 2021);
+    assert_eq!(edid.version, EdidVersion { major: 1, minor: 4 });
+    assert_eq!(
+        edid.native_resolution,
+        Some(Resolution {
+            width: 3840,
+            height: 2160
+        })
+    );
+    assert_eq!(
+        edid.physical_size,
+        Some(PhysicalSize {
+            width_mm: 597,
+            height_mm: 336
+        })
+    );
+    assert_eq!(edid.video_interfaces, vec![VideoInterface::DisplayPort]);
+}
+
+#[test]
+fn hdmi_is_detected_from_the_cea_extension() {
+    let mut raw = dell_edid();
+    raw[19] = 3;
+    raw[126] = 1;
+    let mut cea = vec![0u8; 128];
+    cea[0] = 0x02;
+    cea[1] = 0x03;
+    cea[2] = 0x0C;
+    cea[4..12].copy_from_slice(&[0x67, 0x03, 0x0C, 0x00, 0x10, 0x00, 0x00, 0x3C]);
+    raw.extend(cea);
+
+    let edid = EdidInfo::parse(&raw).unwrap();
+    assert_eq!(edid.video_interfaces, vec![VideoInterface::Hdmi]);
+}
+
+#[test]
+fn rejects_truncated_or_headerless_data() {
+    assert!(EdidInfo::parse(&dell_edid()[..64]).is_err());
+    assert!(EdidInfo::parse(&[0u8; 128]).is_err());
+}
+
+#[test]
+fn serialises_raw_bytes_as_hex() {
+    let edid = EdidInfo::parse(&dell_edid()).unwrap();
+    let json = serde_json::to_value(&edid).unwrap();
+
+    assert!(json["raw"]
+        .as_str()
+        .unwrap()
+        .starts_with("00ffffffffffff0010ac"));
+    assert_eq!(json["video_interfaces"][0], "display_port");
+    let decoded: EdidInfo = serde_json::from_value(json).unwrap();
+    assert_eq!(decoded, edid);
+}
+
+#[test]
+fn monitor_exposes_decoded_edid() {
+    let backend = SimulatedBackend::new();
+    backend.add_monitor(SimulatedMonitor::new("DEL", "DELL U2720Q").edid(&dell_edid()));
+    backend.add_monitor(SimulatedMonitor::new("ACM", "Plain"));
+
+    let monitors = Monitor::enumerate_with_backend(&backend, &Config::default());
+    assert_eq!(monitors[0].edid().unwrap().product_code, 0xA0F4);
+    assert!(monitors[1].edid().is_none());
+}
@@ -0,0 +1,29 @@
+use std::ffi::CStr;
+
+use monitor_core::{
+    monitor_last_error_code, monitor_last_error_message, monitor_set_power_mode, string_free,
+    MonitorErrorCode,
+};
+
+#[test]
+fn out_of_range_power_modes_are_rejected() {
+    for mode in [0x00, 0x06, 0xFF] {
+        assert!(!monitor_set_power_mode(0, mode));
+        assert_eq!(monitor_last_error_code(), MonitorErrorCode::InvalidArgument);
+    }
+
+    let message = monitor_last_error_message();
+    let text = unsafe { CStr::from_ptr(message) }
+        .to_string_lossy()
+        .into_owned();
+    unsafe { string_free(message) };
+    assert_eq!(text, "Invalid argument: power mode 0xff");
+}
+
+#[test]
+fn valid_power_modes_reach_the_monitor() {
+    // No monitors are enumerated in this thread, so a valid mode gets as far
+    // as looking up the monitor.
+    assert!(!monitor_set_power_mode(0, 0x01));
+    assert_eq!(monitor_last_error_code(), MonitorErrorCode::NotFound);
+}
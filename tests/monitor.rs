@@ -0,0 +1,450 @@
+use std::time::{Duration, Instant};
+
+use monitor_core::{
+    Config, InputListSource, InputSource, Monitor, MonitorConfig, MonitorError, PowerMode,
+    QuirkEntry, Quirks, SimulatedBackend, SimulatedMonitor, SwitchOptions, VerifyPolicy,
+};
+
+fn fast_verify(retries: u32) -> VerifyPolicy {
+    VerifyPolicy {
+        retries,
+        initial_backoff_ms: 1,
+        timeout_ms: 1000,
+    }
+}
+
+fn single(monitor: SimulatedMonitor) -> (Monitor, monitor_core::SimulatedMonitorHandle) {
+    let backend = SimulatedBackend::new();
+    let handle = backend.add_monitor(monitor);
+    let mut monitors = Monitor::enumerate_with_backend(&backend, &Config::default());
+    assert_eq!(monitors.len(), 1);
+    (monitors.remove(0), handle)
+}
+
+#[test]
+fn enumerates_every_connected_monitor() {
+    let backend = SimulatedBackend::new();
+    backend.add_monitor(SimulatedMonitor::new("ACM", "Left").serial_number("L1"));
+    let right = backend.add_monitor(SimulatedMonitor::new("ACM", "Right").serial_number("R1"));
+    right.set_connected(false);
+
+    let monitors = Monitor::enumerate_with_backend(&backend, &Config::default());
+    assert_eq!(monitors.len(), 1);
+    assert_eq!(monitors[0].model_name().as_deref(), Some("Left"));
+    assert_eq!(monitors[0].id(), "sim-left-L1");
+}
+
+#[test]
+fn available_inputs_come_from_capabilities() {
+    let (mut monitor, _) = single(SimulatedMonitor::new("ACM", "Caps").inputs(&[0x11, 0x13, 0x03]));
+
+    let available = monitor.get_available_inputs_with_source();
+    assert_eq!(available.source, InputListSource::Capabilities);
+    assert_eq!(
+        available.inputs,
+        vec![InputSource::DVI1, InputSource::HDMI1, InputSource::HDMI3]
+    );
+}
+
+#[test]
+fn available_inputs_fall_back_when_capabilities_are_unparseable() {
+    let (mut monitor, _) = single(
+        SimulatedMonitor::new("ACM", "Broken")
+            .current_input(0x11)
+            .capabilities("(vcp(60(11"),
+    );
+
+    let available = monitor.get_available_inputs_with_source();
+    assert_eq!(available.source, InputListSource::Fallback);
+    assert!(available.inputs.contains(&InputSource::HDMI1));
+}
+
+#[test]
+fn set_input_round_trips_vendor_codes() {
+    let (mut monitor, handle) =
+        single(SimulatedMonitor::new("ACM", "Vendor").inputs(&[0x0F, 0x1B]));
+
+    monitor
+        .set_input(InputSource::from_vcp_value(0x1B))
+        .unwrap();
+    assert_eq!(handle.input(), Some(0x1B));
+    assert_eq!(
+        monitor.get_current_input().unwrap(),
+        InputSource::Other(0x1B)
+    );
+    assert_eq!(monitor.get_current_input().unwrap().to_vcp_value(), 0x1B);
+}
+
+#[test]
+fn vendor_inputs_are_listed_and_named_by_their_code() {
+    let (mut monitor, _) = single(SimulatedMonitor::new("ACM", "Vendor").inputs(&[0x0F, 0x1B]));
+
+    let inputs = monitor.get_available_inputs().unwrap();
+    assert_eq!(
+        inputs,
+        vec![InputSource::DisplayPort1, InputSource::Other(0x1B)]
+    );
+    assert_eq!(inputs[1].name(), "Input 0x1B");
+    assert_eq!(serde_json::to_string(&inputs[1]).unwrap(), "27");
+
+    // A standard value wrapped in `Other` is still the same input.
+    let set: std::collections::HashSet<_> = [InputSource::Other(0x11)].into();
+    assert!(set.contains(&InputSource::HDMI1));
+}
+
+#[test]
+fn write_failures_surface_as_errors() {
+    let (mut monitor, handle) = single(SimulatedMonitor::new("ACM", "Flaky").inputs(&[0x0F, 0x11]));
+    handle.fail_next_writes(1);
+
+    assert!(matches!(
+        monitor.set_input(InputSource::HDMI1),
+        Err(MonitorError::DdcError(_))
+    ));
+    assert_eq!(handle.input(), Some(0x0F));
+
+    monitor.set_input(InputSource::HDMI1).unwrap();
+    assert_eq!(handle.input(), Some(0x11));
+}
+
+#[test]
+fn raw_vcp_values_are_read_and_written_unscaled() {
+    let (mut monitor, handle) = single(SimulatedMonitor::new("ACM", "Raw").feature(0xDC, 3, 5));
+
+    let feature = monitor.get_vcp(0xDC).unwrap();
+    assert_eq!((feature.current, feature.maximum), (3, 5));
+    monitor.set_vcp(0xDC, 0x05).unwrap();
+    assert_eq!(handle.writes(), vec![(0xDC, 0x05)]);
+    assert_eq!(monitor.get_vcp(0xDC).unwrap().current, 0x05);
+
+    assert!(matches!(
+        monitor.get_vcp(0xE0),
+        Err(MonitorError::NotSupported)
+    ));
+}
+
+#[test]
+fn brightness_is_normalised_to_percent() {
+    let (mut monitor, handle) =
+        single(SimulatedMonitor::new("ACM", "Bright").feature(0x10, 40, 80));
+
+    assert_eq!(monitor.brightness().unwrap(), 50);
+    monitor.set_brightness(25).unwrap();
+    assert_eq!(handle.vcp(0x10), Some(20));
+    assert_eq!(monitor.adjust_brightness(-30).unwrap(), 0);
+    assert_eq!(monitor.adjust_brightness(5).unwrap(), 5);
+    assert_eq!(handle.vcp(0x10), Some(4));
+}
+
+#[test]
+fn contrast_is_normalised_to_percent() {
+    let (mut monitor, handle) = single(
+        SimulatedMonitor::new("ACM", "Contrast")
+            .feature(0x12, 75, 100)
+            .feature(0x10, 0, 0),
+    );
+
+    assert_eq!(monitor.contrast().unwrap(), 75);
+    assert_eq!(monitor.adjust_contrast(40).unwrap(), 100);
+    monitor.set_contrast(30).unwrap();
+    assert_eq!(handle.writes(), vec![(0x12, 100), (0x12, 30)]);
+
+    // A maximum of 0 means the monitor doesn't really offer the control.
+    assert!(matches!(
+        monitor.brightness(),
+        Err(MonitorError::NotSupported)
+    ));
+}
+
+#[test]
+fn volume_is_hidden_without_capability() {
+    let (mut with_speakers, _) = single(
+        SimulatedMonitor::new("ACM", "Speakers")
+            .feature(0x62, 30, 100)
+            .feature(0x8D, 0x02, 2),
+    );
+    assert!(with_speakers.supports_volume());
+    assert_eq!(with_speakers.volume().unwrap(), 30);
+    with_speakers.set_muted(true).unwrap();
+    assert!(with_speakers.is_muted().unwrap());
+
+    let (mut without_speakers, _) = single(SimulatedMonitor::new("ACM", "Quiet").inputs(&[0x11]));
+    assert!(!without_speakers.supports_volume());
+    assert!(matches!(
+        without_speakers.volume(),
+        Err(MonitorError::NotSupported)
+    ));
+}
+
+#[test]
+fn volume_and_mute_write_their_vcp_codes() {
+    let (mut monitor, handle) = single(
+        SimulatedMonitor::new("ACM", "Speakers")
+            .feature(0x62, 10, 50)
+            .feature(0x8D, 0x01, 2),
+    );
+    assert!(monitor.supports_mute());
+    assert!(monitor.is_muted().unwrap());
+
+    monitor.set_volume(60).unwrap();
+    assert_eq!(monitor.adjust_volume(-10).unwrap(), 50);
+    monitor.set_muted(false).unwrap();
+    assert!(!monitor.is_muted().unwrap());
+    assert_eq!(handle.writes(), vec![(0x62, 30), (0x62, 25), (0x8D, 0x02)]);
+
+    let (mut without_mute, handle) =
+        single(SimulatedMonitor::new("ACM", "Volume").feature(0x62, 10, 50));
+    assert!(!without_mute.supports_mute());
+    assert!(matches!(
+        without_mute.set_muted(true),
+        Err(MonitorError::NotSupported)
+    ));
+    assert!(handle.writes().is_empty());
+}
+
+#[test]
+fn power_mode_is_read_and_set_through_vcp_d6() {
+    let (mut monitor, handle) =
+        single(SimulatedMonitor::new("ACM", "Power").feature(0xD6, 0x01, 5));
+
+    assert_eq!(monitor.power_mode().unwrap(), PowerMode::On);
+    monitor.set_power_mode(PowerMode::Off).unwrap();
+    assert_eq!(handle.vcp(0xD6), Some(0x04));
+    assert_eq!(monitor.power_mode().unwrap(), PowerMode::Off);
+
+    assert!(matches!(
+        monitor.set_power_mode(PowerMode::Unknown),
+        Err(MonitorError::NotSupported)
+    ));
+    handle.set_vcp(0xD6, 0x42);
+    assert_eq!(monitor.power_mode().unwrap(), PowerMode::Unknown);
+    assert_eq!(handle.writes(), vec![(0xD6, 0x04)]);
+}
+
+#[test]
+fn switching_wakes_a_sleeping_monitor_first() {
+    let (mut monitor, handle) = single(
+        SimulatedMonitor::new("ACM", "Sleepy")
+            .inputs(&[0x0F, 0x11])
+            .feature(0xD6, PowerMode::Standby.to_vcp_value(), 5),
+    );
+
+    let options = SwitchOptions {
+        ensure_powered_on: true,
+        wake_delay: std::time::Duration::ZERO,
+        ..SwitchOptions::default()
+    };
+    monitor
+        .set_input_with_options(InputSource::HDMI1, &options)
+        .unwrap();
+
+    assert_eq!(handle.writes(), vec![(0xD6, 0x01), (0x60, 0x11)]);
+    assert_eq!(monitor.power_mode().unwrap(), PowerMode::On);
+}
+
+#[test]
+fn quirks_remap_input_codes() {
+    let backend = SimulatedBackend::new();
+    let handle = backend.add_monitor(SimulatedMonitor::new("ACM", "Remap").inputs(&[0x90, 0xD0]));
+    let config = Config {
+        quirks: vec![QuirkEntry {
+            manufacturer_id: "ACM".to_string(),
+            model_name: Some("Remap".to_string()),
+            quirks: Quirks {
+                input_read_codes: [(0x90, 0x11), (0xD0, 0x0F)].into(),
+                input_write_codes: [(0x11, 0x90), (0x0F, 0xD0)].into(),
+                ..Quirks::default()
+            },
+        }],
+        ..Config::default()
+    };
+    let mut monitor = Monitor::enumerate_with_backend(&backend, &config).remove(0);
+
+    assert_eq!(
+        monitor.get_available_inputs().unwrap(),
+        vec![InputSource::HDMI1, InputSource::DisplayPort1]
+    );
+    assert_eq!(monitor.get_current_input().unwrap(), InputSource::HDMI1);
+
+    monitor.set_input(InputSource::DisplayPort1).unwrap();
+    assert_eq!(handle.input(), Some(0xD0));
+}
+
+#[test]
+fn quirks_without_readback_report_last_written_input() {
+    let backend = SimulatedBackend::new();
+    let handle = backend.add_monitor(SimulatedMonitor::new("ACM", "Mute").inputs(&[0x0F, 0x11]));
+    let config: Config = serde_json::from_str(
+        r#"{ "quirks": [{ "manufacturer_id": "acm", "quirks": { "skip_input_readback": true } }] }"#,
+    )
+    .unwrap();
+    let mut monitor = Monitor::enumerate_with_backend(&backend, &config).remove(0);
+
+    assert!(matches!(
+        monitor.get_current_input(),
+        Err(MonitorError::NotSupported)
+    ));
+    monitor.set_input(InputSource::HDMI1).unwrap();
+    handle.set_vcp(0x60, 0x0F);
+    assert_eq!(monitor.get_current_input().unwrap(), InputSource::HDMI1);
+}
+
+#[test]
+fn verified_switch_retries_dropped_writes() {
+    let (mut monitor, handle) = single(SimulatedMonitor::new("ACM", "Drop").inputs(&[0x0F, 0x11]));
+    handle.ignore_next_writes(2);
+
+    let options = SwitchOptions {
+        verify: Some(fast_verify(3)),
+        ..SwitchOptions::default()
+    };
+    monitor
+        .set_input_with_options(InputSource::HDMI1, &options)
+        .unwrap();
+
+    assert_eq!(handle.input(), Some(0x11));
+    assert_eq!(handle.writes().len(), 3);
+}
+
+#[test]
+fn verified_switch_distinguishes_ignored_writes_from_bus_failures() {
+    let (mut monitor, handle) = single(SimulatedMonitor::new("ACM", "Stuck").inputs(&[0x0F, 0x11]));
+    let options = SwitchOptions {
+        verify: Some(fast_verify(1)),
+        ..SwitchOptions::default()
+    };
+
+    handle.ignore_next_writes(2);
+    match monitor.set_input_with_options(InputSource::HDMI1, &options) {
+        Err(MonitorError::InputNotApplied { expected, actual }) => {
+            assert_eq!(expected, InputSource::HDMI1);
+            assert_eq!(actual, Some(InputSource::DisplayPort1));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    handle.fail_next_writes(2);
+    assert!(matches!(
+        monitor.set_input_with_options(InputSource::HDMI1, &options),
+        Err(MonitorError::DdcError(_))
+    ));
+
+    // The switch went through, but reading it back failed on the bus.
+    handle.fail_next_reads(2);
+    match monitor.set_input_with_options(InputSource::HDMI1, &options) {
+        Err(MonitorError::DdcError(detail)) => assert_eq!(detail, "Simulated read failure"),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(handle.input(), Some(0x11));
+}
+
+#[test]
+fn verified_switch_backs_off_between_failed_writes() {
+    let (mut monitor, handle) = single(SimulatedMonitor::new("ACM", "Busy").inputs(&[0x0F, 0x11]));
+    let options = SwitchOptions {
+        verify: Some(VerifyPolicy {
+            retries: 2,
+            initial_backoff_ms: 20,
+            timeout_ms: 1000,
+        }),
+        ..SwitchOptions::default()
+    };
+
+    handle.fail_next_writes(3);
+    let started = Instant::now();
+    assert!(matches!(
+        monitor.set_input_with_options(InputSource::HDMI1, &options),
+        Err(MonitorError::DdcError(_))
+    ));
+    // 20 ms after the first failure and 40 ms after the second.
+    let elapsed = started.elapsed();
+    assert!(elapsed >= Duration::from_millis(60), "{:?}", elapsed);
+
+    handle.fail_next_writes(2);
+    monitor
+        .set_input_with_options(InputSource::HDMI1, &options)
+        .unwrap();
+    assert_eq!(handle.input(), Some(0x11));
+}
+
+#[test]
+fn verify_policy_is_read_from_config_per_monitor() {
+    let backend = SimulatedBackend::new();
+    let handle = backend.add_monitor(
+        SimulatedMonitor::new("ACM", "Policy")
+            .serial_number("P1")
+            .inputs(&[0x0F, 0x11]),
+    );
+    let mut config = Config::default();
+    config.monitors.insert(
+        "sim-policy-P1".to_string(),
+        MonitorConfig {
+            verify: Some(fast_verify(2)),
+            ..MonitorConfig::default()
+        },
+    );
+
+    let mut monitor = Monitor::enumerate_with_backend(&backend, &config).remove(0);
+    assert_eq!(monitor.switch_options().verify, Some(fast_verify(2)));
+
+    handle.ignore_next_writes(1);
+    monitor.set_input(InputSource::HDMI1).unwrap();
+    assert_eq!(handle.input(), Some(0x11));
+}
+
+#[test]
+fn ids_come_from_the_edid_and_survive_reordering() {
+    let dell = || {
+        SimulatedMonitor::new("DEL", "DELL U2720Q")
+            .id("/dev/i2c-4")
+            .product_code(0xA0F4)
+            .serial_number("ABC123")
+    };
+    let twin = |port: &str| {
+        SimulatedMonitor::new("ACM", "Twin")
+            .id(port)
+            .product_code(0x0001)
+            .manufactured(12, 2021)
+            .edid(&[0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00])
+    };
+
+    let backend = SimulatedBackend::new();
+    backend.add_monitor(twin("/dev/i2c-7"));
+    backend.add_monitor(dell());
+    backend.add_monitor(twin("/dev/i2c-8"));
+    let ids: Vec<String> = Monitor::enumerate_with_backend(&backend, &Config::default())
+        .iter()
+        .map(|m| m.id())
+        .collect();
+    assert_eq!(ids[1], "DEL-A0F4-ABC123");
+    assert!(ids[0].starts_with("ACM-0001-2021W12-"));
+    assert_eq!(ids[2], format!("{}-2", ids[0]));
+
+    let replugged = SimulatedBackend::new();
+    replugged.add_monitor(dell().id("/dev/i2c-9"));
+    let monitors = Monitor::enumerate_with_backend(&replugged, &Config::default());
+    assert_eq!(monitors[0].id(), "DEL-A0F4-ABC123");
+}
+
+#[test]
+fn legacy_config_entries_are_migrated_to_edid_ids() {
+    let backend = SimulatedBackend::new();
+    backend.add_monitor(
+        SimulatedMonitor::new("ACM", "Legacy")
+            .id("/dev/i2c-3")
+            .product_code(0x1234)
+            .serial(42),
+    );
+    let monitors = Monitor::enumerate_with_backend(&backend, &Config::default());
+    assert_eq!(monitors[0].legacy_id(), "/dev/i2c-3-42");
+
+    let mut config = Config::default();
+    config.set_alias("/dev/i2c-3-42", 0x11, "Laptop".to_string());
+    config.add_favorite("/dev/i2c-3-42", 0x11);
+
+    assert!(config.migrate_monitor_ids(&monitors));
+    assert_eq!(config.get_alias("ACM-1234-42", 0x11), Some("Laptop"));
+    assert!(config.is_favorite("ACM-1234-42", 0x11));
+    assert!(!config.monitors.contains_key("/dev/i2c-3-42"));
+    assert!(!config.migrate_monitor_ids(&monitors));
+}
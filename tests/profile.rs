@@ -0,0 +1,345 @@
+use std::time::Duration;
+
+use monitor_core::{
+    apply_profile, apply_profile_with, ApplyOptions, Config, InputSource, Monitor, MonitorError,
+    PowerMode, Profile, ProfileTarget, SimulatedBackend, SimulatedMonitor, SimulatedMonitorHandle,
+    TargetOutcome,
+};
+
+fn target(monitor_id: &str, input_value: u16, brightness: Option<u8>) -> ProfileTarget {
+    ProfileTarget {
+        monitor_id: monitor_id.to_string(),
+        input_value,
+        brightness,
+    }
+}
+
+fn desk(backend: &SimulatedBackend, names: &[&str]) -> Vec<SimulatedMonitorHandle> {
+    names
+        .iter()
+        .map(|name| {
+            backend.add_monitor(
+                SimulatedMonitor::new("ACM", name)
+                    .serial_number(&name[..1])
+                    .current_input(0x11),
+            )
+        })
+        .collect()
+}
+
+fn work_laptop() -> Profile {
+    Profile {
+        name: "Work laptop".into(),
+        monitors: vec![
+            target("sim-left-L1", 0x15, Some(70)),
+            target("sim-right-R1", 0x0F, None),
+        ],
+    }
+}
+
+#[test]
+fn profile_switches_every_monitor() {
+    let backend = SimulatedBackend::new();
+    let left = backend.add_monitor(
+        SimulatedMonitor::new("ACM", "Left")
+            .serial_number("L1")
+            .current_input(0x11)
+            .feature(0x10, 30, 100),
+    );
+    let right = backend.add_monitor(
+        SimulatedMonitor::new("ACM", "Right")
+            .serial_number("R1")
+            .current_input(0x11)
+            .feature(0x10, 30, 100),
+    );
+    let mut monitors = Monitor::enumerate_with_backend(&backend, &Config::default());
+
+    let report = apply_profile(&mut monitors, &work_laptop());
+    assert!(report.is_ok(), "{:?}", report);
+    assert_eq!(report.profile, "Work laptop");
+    assert_eq!(report.monitors[0].input, InputSource::USBC1);
+    assert_eq!(left.input(), Some(0x15));
+    assert_eq!(left.vcp(0x10), Some(70));
+    assert_eq!(right.input(), Some(0x0F));
+    assert_eq!(right.vcp(0x10), Some(30));
+
+    // Brightness goes out before the monitor switches away.
+    assert_eq!(left.writes(), vec![(0x10, 70), (0x60, 0x15)]);
+}
+
+#[test]
+fn failures_are_reported_per_monitor() {
+    let backend = SimulatedBackend::new();
+    let left = backend.add_monitor(
+        SimulatedMonitor::new("ACM", "Left")
+            .serial_number("L1")
+            .current_input(0x11)
+            .feature(0x10, 30, 100),
+    );
+    left.fail_next_writes(1);
+    let mut monitors = Monitor::enumerate_with_backend(&backend, &Config::default());
+
+    let report = apply_profile(&mut monitors, &work_laptop());
+    assert!(!report.is_ok());
+    assert_eq!(report.failures().count(), 2);
+
+    // The brightness write failed, but the input still switched.
+    let left_report = &report.monitors[0];
+    assert!(matches!(left_report.brightness_result, Some(Err(_))));
+    assert!(matches!(left_report.outcome, TargetOutcome::Switched));
+    assert_eq!(left_report.previous_input, Some(InputSource::HDMI1));
+    assert_eq!(left.input(), Some(0x15));
+
+    let right_report = &report.monitors[1];
+    assert!(matches!(right_report.outcome, TargetOutcome::NotFound));
+    assert!(right_report.brightness_result.is_none());
+    assert!(!report.rolled_back);
+}
+
+#[test]
+fn profiles_are_stored_by_name() {
+    let mut config = Config::default();
+    config.set_profile(work_laptop());
+    config.set_profile(Profile {
+        name: "Desktop".into(),
+        monitors: vec![target("sim-left-L1", 0x0F, None)],
+    });
+
+    let mut edited = work_laptop();
+    edited.monitors.pop();
+    config.set_profile(edited.clone());
+    assert_eq!(config.profiles.len(), 2);
+    assert_eq!(config.profiles[0], edited);
+
+    let json = serde_json::to_string(&config).unwrap();
+    let loaded: Config = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded.get_profile("Work laptop"), Some(&edited));
+
+    config.remove_profile("Work laptop");
+    assert!(config.get_profile("Work laptop").is_none());
+    assert!(config.get_profile("Desktop").is_some());
+}
+
+#[test]
+fn profile_targets_follow_monitor_id_migration() {
+    let backend = SimulatedBackend::new();
+    backend.add_monitor(
+        SimulatedMonitor::new("ACM", "Legacy")
+            .id("/dev/i2c-3")
+            .product_code(0x1234)
+            .serial(42),
+    );
+    let monitors = Monitor::enumerate_with_backend(&backend, &Config::default());
+
+    let mut config = Config::default();
+    config.set_profile(Profile {
+        name: "Desk".into(),
+        monitors: vec![target("/dev/i2c-3-42", 0x11, None)],
+    });
+
+    assert!(config.migrate_monitor_ids(&monitors));
+    let desk = config.get_profile("Desk").unwrap();
+    assert_eq!(desk.monitors[0].monitor_id, "ACM-1234-42");
+}
+
+#[test]
+fn failed_switch_rolls_back_earlier_monitors() {
+    let backend = SimulatedBackend::new();
+    let handles = desk(&backend, &["Left", "Middle", "Right"]);
+    handles[1].fail_next_writes(1);
+    let mut monitors = Monitor::enumerate_with_backend(&backend, &Config::default());
+    let profile = Profile {
+        name: "Desktop".into(),
+        monitors: vec![
+            target("sim-left-L", 0x0F, None),
+            target("sim-middle-M", 0x0F, None),
+            target("sim-right-R", 0x0F, None),
+        ],
+    };
+    let options = ApplyOptions {
+        rollback: true,
+        ..ApplyOptions::default()
+    };
+
+    let report = apply_profile_with(&mut monitors, &profile, &options);
+    assert!(report.rolled_back);
+    assert!(matches!(
+        report.monitors[0].outcome,
+        TargetOutcome::RolledBack
+    ));
+    assert!(matches!(
+        report.monitors[1].outcome,
+        TargetOutcome::Failed(MonitorError::DdcError(_))
+    ));
+    assert!(matches!(report.monitors[2].outcome, TargetOutcome::Skipped));
+
+    // Everything is back where it started and the last monitor was never
+    // touched.
+    assert_eq!(handles[0].writes(), vec![(0x60, 0x0F), (0x60, 0x11)]);
+    assert_eq!(handles[0].input(), Some(0x11));
+    assert_eq!(handles[1].input(), Some(0x11));
+    assert!(handles[2].writes().is_empty());
+}
+
+#[test]
+fn monitors_that_stop_answering_cannot_be_rolled_back() {
+    let backend = SimulatedBackend::new();
+    let left = backend.add_monitor(
+        SimulatedMonitor::new("ACM", "Left")
+            .serial_number("L")
+            .current_input(0x11)
+            .host_input(0x11),
+    );
+    let right = desk(&backend, &["Right"]).remove(0);
+    right.fail_next_writes(1);
+    let mut monitors = Monitor::enumerate_with_backend(&backend, &Config::default());
+    let profile = Profile {
+        name: "Desktop".into(),
+        monitors: vec![
+            target("sim-left-L", 0x0F, None),
+            target("sim-right-R", 0x0F, None),
+        ],
+    };
+    let options = ApplyOptions {
+        rollback: true,
+        ..ApplyOptions::default()
+    };
+
+    let report = apply_profile_with(&mut monitors, &profile, &options);
+    assert!(!report.rolled_back);
+    assert!(matches!(
+        report.monitors[0].outcome,
+        TargetOutcome::RollbackFailed(MonitorError::DdcError(_))
+    ));
+    assert!(report.monitors[0].error().is_some());
+    assert_eq!(left.input(), Some(0x0F));
+    assert_eq!(report.failures().count(), 2);
+}
+
+#[test]
+fn one_failed_rollback_means_the_profile_was_not_rolled_back() {
+    let backend = SimulatedBackend::new();
+    let left = backend.add_monitor(
+        SimulatedMonitor::new("ACM", "Left")
+            .serial_number("L")
+            .current_input(0x11)
+            .host_input(0x11),
+    );
+    let handles = desk(&backend, &["Middle", "Right"]);
+    handles[1].fail_next_writes(1);
+    let mut monitors = Monitor::enumerate_with_backend(&backend, &Config::default());
+    let profile = Profile {
+        name: "Desktop".into(),
+        monitors: vec![
+            target("sim-left-L", 0x0F, None),
+            target("sim-middle-M", 0x0F, None),
+            target("sim-right-R", 0x0F, None),
+        ],
+    };
+    let options = ApplyOptions {
+        rollback: true,
+        ..ApplyOptions::default()
+    };
+
+    let report = apply_profile_with(&mut monitors, &profile, &options);
+    assert!(!report.rolled_back);
+    assert!(matches!(
+        report.monitors[0].outcome,
+        TargetOutcome::RollbackFailed(_)
+    ));
+    assert!(matches!(
+        report.monitors[1].outcome,
+        TargetOutcome::RolledBack
+    ));
+    assert_eq!(left.input(), Some(0x0F));
+    assert_eq!(handles[0].input(), Some(0x11));
+}
+
+#[test]
+fn sleeping_monitors_are_only_woken_when_asked() {
+    let backend = SimulatedBackend::new();
+    let handle = backend.add_monitor(
+        SimulatedMonitor::new("ACM", "Left")
+            .serial_number("L")
+            .current_input(0x11)
+            .feature(0xD6, PowerMode::Standby.to_vcp_value(), 5),
+    );
+    let mut monitors = Monitor::enumerate_with_backend(&backend, &Config::default());
+    let profile = Profile {
+        name: "Desktop".into(),
+        monitors: vec![target("sim-left-L", 0x0F, None)],
+    };
+
+    apply_profile(&mut monitors, &profile);
+    assert_eq!(handle.writes(), vec![(0x60, 0x0F)]);
+
+    let options = ApplyOptions {
+        wake: true,
+        ..ApplyOptions::default()
+    };
+    let report = apply_profile_with(&mut monitors, &profile, &options);
+    assert!(report.is_ok());
+    assert_eq!(handle.writes()[1..], [(0xD6, 0x01), (0x60, 0x0F)]);
+}
+
+#[test]
+fn missing_monitors_do_not_trigger_rollback() {
+    let backend = SimulatedBackend::new();
+    let handles = desk(&backend, &["Left"]);
+    let mut monitors = Monitor::enumerate_with_backend(&backend, &Config::default());
+    let options = ApplyOptions {
+        rollback: true,
+        ..ApplyOptions::default()
+    };
+
+    let profile = Profile {
+        name: "Desktop".into(),
+        monitors: vec![
+            target("sim-right-R", 0x0F, None),
+            target("sim-left-L", 0x0F, None),
+        ],
+    };
+
+    let report = apply_profile_with(&mut monitors, &profile, &options);
+    assert!(!report.rolled_back);
+    assert!(matches!(
+        report.monitors[0].outcome,
+        TargetOutcome::NotFound
+    ));
+    assert!(matches!(
+        report.monitors[1].outcome,
+        TargetOutcome::Switched
+    ));
+    assert_eq!(handles[0].input(), Some(0x0F));
+}
+
+#[test]
+fn monitors_switch_in_the_configured_order_with_delays() {
+    let backend = SimulatedBackend::new();
+    desk(&backend, &["Left", "Right"]);
+    let mut monitors = Monitor::enumerate_with_backend(&backend, &Config::default());
+    let profile = Profile {
+        name: "Desktop".into(),
+        monitors: vec![
+            target("sim-left-L", 0x0F, None),
+            target("sim-right-R", 0x0F, None),
+        ],
+    };
+    let options = ApplyOptions {
+        order: vec!["sim-right-R".into()],
+        delay: Duration::from_millis(50),
+        rollback: true,
+        wake: false,
+    };
+
+    let started = std::time::Instant::now();
+    let report = apply_profile_with(&mut monitors, &profile, &options);
+    assert!(report.is_ok(), "{:?}", report);
+    assert!(started.elapsed() >= Duration::from_millis(50));
+    let order: Vec<&str> = report
+        .monitors
+        .iter()
+        .map(|m| m.monitor_id.as_str())
+        .collect();
+    assert_eq!(order, ["sim-right-R", "sim-left-L"]);
+}
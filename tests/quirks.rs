@@ -0,0 +1,91 @@
+use std::time::Duration;
+
+use monitor_core::{
+    Config, InputSource, Monitor, MonitorError, QuirkEntry, Quirks, SimulatedBackend,
+    SimulatedMonitor,
+};
+
+fn entry(manufacturer_id: &str, model_name: Option<&str>, quirks: Quirks) -> QuirkEntry {
+    QuirkEntry {
+        manufacturer_id: manufacturer_id.to_string(),
+        model_name: model_name.map(str::to_string),
+        quirks,
+    }
+}
+
+fn delay(ms: u64) -> Quirks {
+    Quirks {
+        input_switch_delay_ms: Some(ms),
+        ..Quirks::default()
+    }
+}
+
+#[test]
+fn builtin_quirks_match_manufacturer_and_model() {
+    let lg = Quirks::lookup(Some("gsm"), Some("LG HDR 4K \n"), &[]);
+    assert_eq!(lg.input_from_monitor(0x90), 0x11);
+    assert_eq!(lg.input_from_monitor(0x0F), 0x0F);
+
+    let other_lg = Quirks::lookup(Some("GSM"), Some("LG ULTRAGEAR"), &[]);
+    assert_eq!(other_lg, Quirks::default());
+    assert_eq!(Quirks::lookup(None, None, &[]), Quirks::default());
+}
+
+#[test]
+fn user_entries_override_builtins_and_models_override_manufacturers() {
+    let overrides = [
+        entry("DEL", None, delay(1000)),
+        entry("DEL", Some("DELL U2720Q"), delay(250)),
+        entry("DEL", Some("DELL P2419H"), delay(0)),
+        entry("GSM", Some("LG HDR 4K"), delay(500)),
+    ];
+
+    let u2720q = Quirks::lookup(Some("DEL"), Some("DELL U2720Q"), &overrides);
+    assert_eq!(
+        u2720q.input_switch_delay(),
+        Some(Duration::from_millis(250))
+    );
+    let u2419h = Quirks::lookup(Some("DEL"), Some("DELL U2419H"), &overrides);
+    assert_eq!(u2419h.input_switch_delay(), Some(Duration::from_secs(1)));
+
+    // 0 turns the manufacturer's delay off for one model.
+    let p2419h = Quirks::lookup(Some("DEL"), Some("DELL P2419H"), &overrides);
+    assert_eq!(p2419h.input_switch_delay_ms, Some(0));
+    assert_eq!(p2419h.input_switch_delay(), None);
+
+    // A user entry adds to the built-in one instead of replacing it.
+    let lg = Quirks::lookup(Some("GSM"), Some("LG HDR 4K"), &overrides);
+    assert_eq!(lg.input_switch_delay(), Some(Duration::from_millis(500)));
+    assert_eq!(lg.input_from_monitor(0x90), 0x11);
+}
+
+#[test]
+fn monitors_that_drop_off_the_bus_report_the_last_input() {
+    let backend = SimulatedBackend::new();
+    let handle = backend.add_monitor(SimulatedMonitor::new("ACM", "Flaky").inputs(&[0x0F, 0x11]));
+    let config = Config {
+        quirks: vec![entry(
+            "ACM",
+            Some("Flaky"),
+            Quirks {
+                drops_off_bus: Some(true),
+                ..Quirks::default()
+            },
+        )],
+        ..Config::default()
+    };
+    let mut monitor = Monitor::enumerate_with_backend(&backend, &config).remove(0);
+
+    monitor.set_input(InputSource::HDMI1).unwrap();
+    handle.fail_next_reads(1);
+    assert_eq!(monitor.get_current_input().unwrap(), InputSource::HDMI1);
+
+    // Without the quirk the read failure comes through.
+    let mut plain = Monitor::enumerate_with_backend(&backend, &Config::default()).remove(0);
+    plain.set_input(InputSource::HDMI1).unwrap();
+    handle.fail_next_reads(1);
+    assert!(matches!(
+        plain.get_current_input(),
+        Err(MonitorError::DdcError(_))
+    ));
+}
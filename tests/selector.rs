@@ -0,0 +1,118 @@
+use monitor_core::{
+    resolve_input, Config, InputSource, Monitor, MonitorSelector, SelectorError, SimulatedBackend,
+    SimulatedMonitor,
+};
+
+fn office() -> Vec<Monitor> {
+    let backend = SimulatedBackend::new();
+    backend.add_monitor(SimulatedMonitor::new("DEL", "DELL U2720Q").serial_number("ABC"));
+    backend.add_monitor(SimulatedMonitor::new("DEL", "DELL U2720Q").serial_number("DEF"));
+    backend.add_monitor(SimulatedMonitor::new("GSM", "LG HDR 4K").serial_number("XYZ"));
+    Monitor::enumerate_with_backend(&backend, &Config::default())
+}
+
+fn resolve(selector: &str, monitors: &[Monitor], config: &Config) -> Result<usize, SelectorError> {
+    selector
+        .parse::<MonitorSelector>()?
+        .resolve(monitors, config)
+}
+
+#[test]
+fn input_names_parse_loosely() {
+    let parse = |s: &str| s.parse::<InputSource>();
+    assert_eq!(parse("HDMI 2"), Ok(InputSource::HDMI2));
+    assert_eq!(parse("hdmi2"), Ok(InputSource::HDMI2));
+    assert_eq!(parse("dp-1"), Ok(InputSource::DisplayPort1));
+    assert_eq!(parse("DisplayPort 2"), Ok(InputSource::DisplayPort2));
+    assert_eq!(parse("usb-c"), Ok(InputSource::USBC1));
+    assert_eq!(parse("S-Video 2"), Ok(InputSource::SVideo2));
+    assert_eq!(parse("0x0F"), Ok(InputSource::DisplayPort1));
+    assert_eq!(parse("17"), Ok(InputSource::HDMI1));
+    assert_eq!(parse("Input 0x1B"), Ok(InputSource::Other(0x1B)));
+    assert_eq!(
+        parse("hdmi 9"),
+        Err(SelectorError::UnknownInput("hdmi 9".into()))
+    );
+    assert!(parse("").is_err());
+
+    for value in (0x01..=0x17).chain([0x1B, 0x100]) {
+        let input = InputSource::from_vcp_value(value);
+        assert_eq!(parse(&input.name()), Ok(input));
+    }
+}
+
+#[test]
+fn monitors_are_selected_by_index_serial_alias_or_model() {
+    let monitors = office();
+    let mut config = Config::default();
+    config.set_monitor_alias("sim-dell-u2720q-ABC", Some("Left".into()));
+
+    assert_eq!(resolve("2", &monitors, &config), Ok(1));
+    assert_eq!(resolve("left", &monitors, &config), Ok(0));
+    assert_eq!(resolve("serial:def", &monitors, &config), Ok(1));
+    assert_eq!(resolve("xyz", &monitors, &config), Ok(2));
+    assert_eq!(resolve("lg", &monitors, &config), Ok(2));
+    assert_eq!(resolve("id:sim-dell-u2720q-DEF", &monitors, &config), Ok(1));
+
+    assert_eq!(
+        resolve("4", &monitors, &config),
+        Err(SelectorError::NoMonitor("4".into()))
+    );
+    assert_eq!(
+        resolve("model:left", &monitors, &config),
+        Err(SelectorError::NoMonitor("model:left".into()))
+    );
+    assert_eq!(resolve("  ", &monitors, &config), Err(SelectorError::Empty));
+
+    let error = resolve("dell", &monitors, &config).unwrap_err();
+    assert_eq!(
+        error,
+        SelectorError::AmbiguousMonitor {
+            selector: "dell".into(),
+            matches: vec!["sim-dell-u2720q-ABC".into(), "sim-dell-u2720q-DEF".into()],
+        }
+    );
+    assert!(error.to_string().contains("sim-dell-u2720q-DEF"));
+}
+
+#[test]
+fn aliases_are_ambiguous_before_falling_back_to_models() {
+    let monitors = office();
+    let mut config = Config::default();
+    config.set_monitor_alias("sim-dell-u2720q-ABC", Some("LG".into()));
+    config.set_monitor_alias("sim-dell-u2720q-DEF", Some("lg".into()));
+
+    // Both Dells are called "LG", so the LG's model name doesn't settle it.
+    assert!(matches!(
+        resolve("lg", &monitors, &config),
+        Err(SelectorError::AmbiguousMonitor { .. })
+    ));
+    assert_eq!(resolve("model:lg", &monitors, &config), Ok(2));
+}
+
+#[test]
+fn inputs_resolve_through_the_monitors_aliases() {
+    let mut config = Config::default();
+    config.set_alias("DEL-A0F4-ABC123", 0x11, "Work laptop".into());
+    config.set_alias("DEL-A0F4-ABC123", 0x12, "HDMI 1".into());
+
+    let resolve = |s: &str| resolve_input(&config, "DEL-A0F4-ABC123", s);
+    assert_eq!(resolve("work LAPTOP"), Ok(InputSource::HDMI1));
+    assert_eq!(resolve("hdmi 1"), Ok(InputSource::HDMI2));
+    assert_eq!(resolve("0x11"), Ok(InputSource::HDMI1));
+    assert_eq!(resolve("dp1"), Ok(InputSource::DisplayPort1));
+    assert_eq!(
+        resolve_input(&config, "other", "work laptop"),
+        Err(SelectorError::UnknownInput("work laptop".into()))
+    );
+
+    config.set_alias("DEL-A0F4-ABC123", 0x0F, "Work laptop".into());
+    let error = resolve_input(&config, "DEL-A0F4-ABC123", "work laptop").unwrap_err();
+    assert_eq!(
+        error,
+        SelectorError::AmbiguousInput {
+            selector: "work laptop".into(),
+            matches: vec!["DisplayPort 1".into(), "HDMI 1".into()],
+        }
+    );
+}
@@ -0,0 +1,135 @@
+use monitor_core::{
+    switch_input, toggle_input, Config, InputHistory, InputSource, Monitor, MonitorError,
+    PowerMode, SimulatedBackend, SimulatedMonitor, SwitchOptions,
+};
+use tempfile::TempDir;
+
+fn monitor(backend: &SimulatedBackend, input: u16) -> Monitor {
+    backend.add_monitor(
+        SimulatedMonitor::new("ACM", "Desk")
+            .serial_number("ABC")
+            .current_input(input),
+    );
+    Monitor::enumerate_with_backend(backend, &Config::default()).remove(0)
+}
+
+#[test]
+fn toggle_flips_between_a_pair() {
+    let backend = SimulatedBackend::new();
+    let mut monitor = monitor(&backend, 0x11);
+    let mut history = InputHistory::default();
+    let pair = Some((InputSource::HDMI1, InputSource::DisplayPort1));
+
+    assert_eq!(
+        toggle_input(&mut monitor, pair, true, &mut history).unwrap(),
+        InputSource::DisplayPort1
+    );
+    assert_eq!(
+        toggle_input(&mut monitor, pair, true, &mut history).unwrap(),
+        InputSource::HDMI1
+    );
+
+    // On neither input of the pair: go to the first.
+    monitor.set_input(InputSource::USBC1).unwrap();
+    assert_eq!(
+        toggle_input(&mut monitor, pair, true, &mut history).unwrap(),
+        InputSource::HDMI1
+    );
+    assert_eq!(
+        history.previous_input(&monitor.id()),
+        Some(InputSource::USBC1)
+    );
+}
+
+#[test]
+fn toggle_without_a_pair_goes_back_to_the_previous_input() {
+    let backend = SimulatedBackend::new();
+    let mut monitor = monitor(&backend, 0x11);
+    let mut history = InputHistory::default();
+
+    assert!(matches!(
+        toggle_input(&mut monitor, None, true, &mut history),
+        Err(MonitorError::NoPreviousInput)
+    ));
+
+    let options = SwitchOptions::default();
+    switch_input(
+        &mut monitor,
+        InputSource::DisplayPort1,
+        &options,
+        &mut history,
+    )
+    .unwrap();
+    assert_eq!(
+        toggle_input(&mut monitor, None, true, &mut history).unwrap(),
+        InputSource::HDMI1
+    );
+    assert_eq!(
+        toggle_input(&mut monitor, None, true, &mut history).unwrap(),
+        InputSource::DisplayPort1
+    );
+}
+
+#[test]
+fn toggle_only_wakes_a_sleeping_monitor_when_asked() {
+    let backend = SimulatedBackend::new();
+    let handle = backend.add_monitor(
+        SimulatedMonitor::new("ACM", "Sleepy")
+            .current_input(0x11)
+            .feature(0xD6, PowerMode::Standby.to_vcp_value(), 5),
+    );
+    let mut monitor = Monitor::enumerate_with_backend(&backend, &Config::default()).remove(0);
+    let mut history = InputHistory::default();
+    let pair = Some((InputSource::HDMI1, InputSource::DisplayPort1));
+
+    toggle_input(&mut monitor, pair, false, &mut history).unwrap();
+    assert_eq!(handle.writes(), vec![(0x60, 0x0F)]);
+    assert_eq!(monitor.power_mode().unwrap(), PowerMode::Standby);
+}
+
+#[test]
+fn history_survives_a_restart() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("config.state.json");
+    let mut history = InputHistory::default();
+    history.record(
+        "DEL-A0F4-ABC123",
+        InputSource::HDMI1,
+        InputSource::DisplayPort1,
+    );
+    // Switching to the input it's already on doesn't forget the previous one.
+    history.record(
+        "DEL-A0F4-ABC123",
+        InputSource::DisplayPort1,
+        InputSource::DisplayPort1,
+    );
+    history.save_to(&path).unwrap();
+
+    let loaded = InputHistory::load_from(&path);
+    assert_eq!(loaded, history);
+    assert_eq!(
+        loaded.previous_input("DEL-A0F4-ABC123"),
+        Some(InputSource::HDMI1)
+    );
+
+    std::fs::write(&path, "{ not json").unwrap();
+    assert_eq!(InputHistory::load_from(&path), InputHistory::default());
+}
+
+#[test]
+fn toggle_favorites_are_separate_from_plain_ones() {
+    let mut config = Config::default();
+    config.add_toggle_favorite("DEL-A0F4-ABC123", 0x11, Some(0x0F));
+    assert!(!config.is_favorite("DEL-A0F4-ABC123", 0x11));
+    assert!(config.is_toggle_favorite("DEL-A0F4-ABC123", 0x11, Some(0x0F)));
+
+    config.add_favorite("DEL-A0F4-ABC123", 0x11);
+    config.remove_favorite("DEL-A0F4-ABC123", 0x11);
+    assert_eq!(config.favorites.len(), 1);
+
+    let json = serde_json::to_value(&config).unwrap();
+    assert_eq!(json["favorites"][0]["toggle_with"], 0x0F);
+    config.add_favorite("DEL-A0F4-ABC123", 0x12);
+    let json = serde_json::to_value(&config).unwrap();
+    assert!(json["favorites"][1].get("toggle_with").is_none());
+}
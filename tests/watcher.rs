@@ -0,0 +1,46 @@
+use std::fs;
+use std::time::Duration;
+
+use monitor_core::{Config, ConfigEvent, ConfigWatcher};
+use tempfile::TempDir;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[test]
+fn external_edits_are_reported() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("config.json");
+    let watcher = ConfigWatcher::watch(&path).unwrap();
+
+    fs::write(&path, "{ \"version\": 1, \"favorites\": [] }").unwrap();
+    assert_eq!(watcher.recv_timeout(TIMEOUT), Some(ConfigEvent::Changed));
+
+    // Editors often save by writing a new file and renaming it over the old.
+    let tmp = dir.path().join("config.json~");
+    fs::write(&tmp, "{ \"version\": 1, \"monitors\": {} }").unwrap();
+    fs::rename(&tmp, &path).unwrap();
+    assert_eq!(watcher.recv_timeout(TIMEOUT), Some(ConfigEvent::Changed));
+
+    fs::remove_file(&path).unwrap();
+    assert_eq!(watcher.recv_timeout(TIMEOUT), Some(ConfigEvent::Removed));
+}
+
+#[test]
+fn own_saves_and_other_files_are_ignored() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("config.json");
+    let watcher = ConfigWatcher::watch(&path).unwrap();
+
+    Config::update_at(&path, |c| c.add_favorite("DEL-A0F4-ABC123", 0x11)).unwrap();
+    fs::write(dir.path().join("other.json"), "{}").unwrap();
+    assert_eq!(watcher.recv_timeout(Duration::from_secs(1)), None);
+
+    // A change by someone else after our save is still seen.
+    let mut content = fs::read_to_string(&path).unwrap();
+    content = content.replace("17", "15");
+    fs::write(&path, content).unwrap();
+    assert_eq!(watcher.recv_timeout(TIMEOUT), Some(ConfigEvent::Changed));
+    assert!(Config::load_from(&path)
+        .unwrap()
+        .is_favorite("DEL-A0F4-ABC123", 0x0F));
+}